@@ -1,4 +1,128 @@
 use chrono::{DateTime, Utc};
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// How the samples in the tracking window are combined into one CPU percentage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AveragingMode {
+    /// Arithmetic mean of the samples
+    #[default]
+    Mean,
+    /// Samples weighted by the time they cover, averaged over the tracking window
+    TimeWeighted,
+    /// Exponentially weighted moving average with a configurable half-life, see [`Ewma`]
+    Ewma,
+}
+
+impl AveragingMode {
+    /// All averaging modes, in the order they are listed in help texts
+    pub const ALL: [AveragingMode; 3] = [AveragingMode::Mean, AveragingMode::TimeWeighted, AveragingMode::Ewma];
+
+    /// Name accepted on the command line
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AveragingMode::Mean => "mean",
+            AveragingMode::TimeWeighted => "time-weighted",
+            AveragingMode::Ewma => "ewma",
+        }
+    }
+}
+
+impl fmt::Display for AveragingMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for AveragingMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AveragingMode::ALL
+            .into_iter()
+            .find(|mode| mode.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names: Vec<&str> = AveragingMode::ALL.iter().map(|mode| mode.as_str()).collect();
+                format!("'{}' is not a valid averaging mode (expected one of: {})", s, names.join(", "))
+            })
+    }
+}
+
+/// What 100% means in the reported CPU percentages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CpuScale {
+    /// 100% is the whole machine: usage is divided by the number of cores
+    #[default]
+    Machine,
+    /// 100% is one fully used core: a process using four cores reports 400%
+    Core,
+}
+
+impl CpuScale {
+    /// All scales, in the order they are listed in help texts
+    pub const ALL: [CpuScale; 2] = [CpuScale::Machine, CpuScale::Core];
+
+    /// Name accepted on the command line
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CpuScale::Machine => "machine",
+            CpuScale::Core => "core",
+        }
+    }
+
+    /// Heading of the CPU percentage column
+    pub fn column_label(self) -> &'static str {
+        match self {
+            CpuScale::Machine => "CPU %",
+            CpuScale::Core => "Core %",
+        }
+    }
+
+    /// What 100% stands for, as shown in display headers
+    pub fn description(self) -> &'static str {
+        match self {
+            CpuScale::Machine => "100% = all cores",
+            CpuScale::Core => "100% = one core",
+        }
+    }
+
+    /// Number the raw usage reported by the OS (100% per fully used core) is divided by
+    ///
+    /// # Examples
+    /// ```
+    /// use process_shepherd::cpu_calculator::CpuScale;
+    ///
+    /// assert_eq!(CpuScale::Machine.divisor(8.0), 8.0);
+    /// assert_eq!(CpuScale::Core.divisor(8.0), 1.0);
+    /// ```
+    pub fn divisor(self, cpu_count: f32) -> f32 {
+        match self {
+            CpuScale::Machine => cpu_count.max(1.0),
+            CpuScale::Core => 1.0,
+        }
+    }
+}
+
+impl fmt::Display for CpuScale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for CpuScale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CpuScale::ALL
+            .into_iter()
+            .find(|scale| scale.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names: Vec<&str> = CpuScale::ALL.iter().map(|scale| scale.as_str()).collect();
+                format!("'{}' is not a valid CPU scale (expected one of: {})", s, names.join(", "))
+            })
+    }
+}
 
 /// Represents a CPU usage sample for a process at a specific time
 #[derive(Clone, Debug, PartialEq)]
@@ -16,14 +140,84 @@ impl CpuSample {
     }
 }
 
+/// Cumulative CPU time of a process (or the whole machine) at a specific time
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CpuTimeSample {
+    pub timestamp: DateTime<Utc>,
+    /// CPU-seconds consumed since the process started (or the machine booted)
+    pub cpu_time_secs: f64,
+}
+
+impl CpuTimeSample {
+    pub fn new(timestamp: DateTime<Utc>, cpu_time_secs: f64) -> Self {
+        Self {
+            timestamp,
+            cpu_time_secs,
+        }
+    }
+}
+
+/// Calculate the CPU-seconds consumed between the first and the last reading
+///
+/// # Arguments
+/// * `samples` - Cumulative CPU time readings, oldest first
+///
+/// # Returns
+/// The CPU-seconds consumed, 0.0 with fewer than two readings
+pub fn calculate_cpu_time_consumed(samples: &[CpuTimeSample]) -> f64 {
+    match (samples.first(), samples.last()) {
+        (Some(first), Some(last)) => (last.cpu_time_secs - first.cpu_time_secs).max(0.0),
+        _ => 0.0,
+    }
+}
+
+/// Calculate the CPU percentage from cumulative CPU time readings
+///
+/// Unlike sampled usage, the CPU time counters account for every tick the process ran,
+/// including bursts shorter than the sampling interval.
+///
+/// # Arguments
+/// * `samples` - Cumulative CPU time readings, oldest first
+/// * `divisor` - Number the raw usage is divided by, see [`CpuScale::divisor`]
+///
+/// # Returns
+/// CPU percentage normalized the same way as [`calculate_average_cpu_percentage`],
+/// or `None` if the readings span no time
+///
+/// # Examples
+/// ```
+/// use chrono::{Duration, Utc};
+/// use process_shepherd::cpu_calculator::{CpuTimeSample, calculate_cpu_time_percentage};
+///
+/// let start = Utc::now();
+/// let samples = vec![
+///     CpuTimeSample::new(start, 100.0),
+///     CpuTimeSample::new(start + Duration::seconds(10), 115.0),
+/// ];
+///
+/// // 15 CPU-seconds in 10 seconds on 4 cores: 1.5 of 4 cores busy = 37.5%
+/// assert_eq!(calculate_cpu_time_percentage(&samples, 4.0), Some(37.5));
+/// assert_eq!(calculate_cpu_time_percentage(&samples[..1], 4.0), None);
+/// ```
+pub fn calculate_cpu_time_percentage(samples: &[CpuTimeSample], divisor: f32) -> Option<f32> {
+    let (first, last) = (samples.first()?, samples.last()?);
+    let span_secs = (last.timestamp - first.timestamp).num_milliseconds() as f64 / 1000.0;
+    if span_secs <= 0.0 || divisor <= 0.0 {
+        return None;
+    }
+
+    Some((calculate_cpu_time_consumed(samples) / span_secs * 100.0 / divisor as f64) as f32)
+}
+
 /// Calculate average CPU percentage from a set of samples
 /// 
 /// # Arguments
 /// * `samples` - Vector of CPU usage samples
-/// * `cpu_count` - Number of CPU cores to normalize against
+/// * `divisor` - Number the raw usage is divided by, see [`CpuScale::divisor`]
 /// 
 /// # Returns
-/// Average CPU percentage normalized to 0-100% range where 100% = one full CPU core
+/// Average CPU percentage on the chosen scale: dividing by the core count gives 100% = all
+/// cores busy ([`CpuScale::Machine`]), dividing by 1.0 gives 100% = one core ([`CpuScale::Core`])
 /// 
 /// # Examples
 /// ```
@@ -40,8 +234,8 @@ impl CpuSample {
 /// let result = calculate_average_cpu_percentage(&samples, 4.0);
 /// assert_eq!(result, 18.75);
 /// ```
-pub fn calculate_average_cpu_percentage(samples: &[CpuSample], cpu_count: f32) -> f32 {
-    if samples.is_empty() || cpu_count <= 0.0 {
+pub fn calculate_average_cpu_percentage(samples: &[CpuSample], divisor: f32) -> f32 {
+    if samples.is_empty() || divisor <= 0.0 {
         return 0.0;
     }
 
@@ -49,7 +243,293 @@ pub fn calculate_average_cpu_percentage(samples: &[CpuSample], cpu_count: f32) -
     let average = total_cpu_usage / samples.len() as f32;
     
     // Normalize by CPU count to get 0-100% range
-    average / cpu_count
+    average / divisor
+}
+
+/// Calculate a time-weighted average CPU percentage from a set of samples
+///
+/// Each sample reports the usage since the previous refresh, so it is weighted by the
+/// time since the previous sample. Uneven intervals (sampling jitter, a stalled loop)
+/// then count for the time they actually cover instead of one sample each.
+///
+/// # Arguments
+/// * `samples` - CPU usage samples, oldest first
+/// * `divisor` - Number the raw usage is divided by, see [`CpuScale::divisor`]
+/// * `nominal_interval_secs` - Time assumed to be covered by the first sample, whose predecessor is unknown
+/// * `span_secs` - Length of time to average over; time not covered by samples (e.g. before the
+///   process started) counts as idle. Pass 0.0 to average over the covered time only
+///
+/// # Returns
+/// Average CPU percentage normalized the same way as [`calculate_average_cpu_percentage`]
+///
+/// # Examples
+/// ```
+/// use chrono::{Duration, Utc};
+/// use process_shepherd::cpu_calculator::{CpuSample, calculate_time_weighted_cpu_percentage};
+///
+/// let start = Utc::now();
+/// let samples = vec![
+///     CpuSample::new(start, 0.0),
+///     // The loop stalled: this sample covers 6 seconds instead of 2
+///     CpuSample::new(start + Duration::seconds(6), 100.0),
+///     CpuSample::new(start + Duration::seconds(8), 0.0),
+/// ];
+///
+/// // (0 * 2 + 100 * 6 + 0 * 2) / 10 seconds = 60%, where the plain mean reports 33.33%
+/// assert_eq!(calculate_time_weighted_cpu_percentage(&samples, 1.0, 2.0, 0.0), 60.0);
+///
+/// // Over a 20 second window the process was busy for 6 seconds: 30%
+/// assert_eq!(calculate_time_weighted_cpu_percentage(&samples, 1.0, 2.0, 20.0), 30.0);
+/// ```
+pub fn calculate_time_weighted_cpu_percentage(
+    samples: &[CpuSample],
+    divisor: f32,
+    nominal_interval_secs: f32,
+    span_secs: f32,
+) -> f32 {
+    if samples.is_empty() || divisor <= 0.0 {
+        return 0.0;
+    }
+
+    let mut weighted_usage = samples[0].cpu_usage * nominal_interval_secs.max(0.0);
+    let mut covered_secs = nominal_interval_secs.max(0.0);
+    for pair in samples.windows(2) {
+        let elapsed_secs = (pair[1].timestamp - pair[0].timestamp).num_milliseconds().max(0) as f32 / 1000.0;
+        weighted_usage += pair[1].cpu_usage * elapsed_secs;
+        covered_secs += elapsed_secs;
+    }
+
+    let total_secs = covered_secs.max(span_secs);
+    if total_secs <= 0.0 {
+        // All samples at the same instant: nothing to weight by
+        return calculate_average_cpu_percentage(samples, divisor);
+    }
+
+    weighted_usage / total_secs / divisor
+}
+
+/// Calculate the highest CPU percentage among a set of samples
+///
+/// # Arguments
+/// * `samples` - Vector of CPU usage samples
+/// * `divisor` - Number the raw usage is divided by, see [`CpuScale::divisor`]
+///
+/// # Returns
+/// Peak CPU percentage normalized the same way as [`calculate_average_cpu_percentage`]
+///
+/// # Examples
+/// ```
+/// use chrono::Utc;
+/// use process_shepherd::cpu_calculator::{CpuSample, calculate_peak_cpu_percentage};
+///
+/// let now = Utc::now();
+/// let samples = vec![
+///     CpuSample::new(now, 50.0),
+///     CpuSample::new(now, 100.0),
+/// ];
+///
+/// // With 4 cores, peak 100.0 / 4 = 25%
+/// assert_eq!(calculate_peak_cpu_percentage(&samples, 4.0), 25.0);
+/// ```
+pub fn calculate_peak_cpu_percentage(samples: &[CpuSample], divisor: f32) -> f32 {
+    if samples.is_empty() || divisor <= 0.0 {
+        return 0.0;
+    }
+
+    let peak = samples.iter().map(|s| s.cpu_usage).fold(0.0, f32::max);
+    peak / divisor
+}
+
+/// Normalize every sample to a CPU percentage, oldest first
+///
+/// # Arguments
+/// * `samples` - Vector of CPU usage samples
+/// * `divisor` - Number the raw usage is divided by, see [`CpuScale::divisor`]
+///
+/// # Returns
+/// One CPU percentage per sample, normalized the same way as [`calculate_average_cpu_percentage`]
+pub fn normalize_cpu_samples(samples: &[CpuSample], divisor: f32) -> Vec<f32> {
+    if divisor <= 0.0 {
+        return vec![0.0; samples.len()];
+    }
+
+    samples.iter().map(|s| s.cpu_usage / divisor).collect()
+}
+
+/// Weight of a new sample in an exponentially weighted moving average
+///
+/// After `half_life_secs` the previous average has lost half of its weight, regardless
+/// of how many samples arrived in between. A non-positive half-life disables smoothing.
+///
+/// # Examples
+/// ```
+/// use process_shepherd::cpu_calculator::ewma_weight;
+///
+/// assert_eq!(ewma_weight(10.0, 10.0), 0.5);
+/// assert_eq!(ewma_weight(20.0, 10.0), 0.75);
+/// assert_eq!(ewma_weight(0.0, 10.0), 0.0);
+/// assert_eq!(ewma_weight(2.0, 0.0), 1.0);
+/// ```
+pub fn ewma_weight(elapsed_secs: f32, half_life_secs: f32) -> f32 {
+    if half_life_secs <= 0.0 {
+        return 1.0;
+    }
+    1.0 - 0.5f32.powf(elapsed_secs.max(0.0) / half_life_secs)
+}
+
+/// Exponentially weighted moving average of the CPU usage of one process
+///
+/// Keeps constant state no matter how long the process is tracked. Older samples fade
+/// out gradually instead of dropping off the end of a fixed window, so the average
+/// neither lags a whole window behind a finished spike nor jumps when a sample expires.
+///
+/// # Examples
+/// ```
+/// use chrono::{Duration, Utc};
+/// use process_shepherd::cpu_calculator::{CpuSample, Ewma};
+///
+/// let start = Utc::now();
+/// let mut ewma = Ewma::new(&CpuSample::new(start, 100.0));
+///
+/// // One half-life of idling halves the average
+/// ewma.update(&CpuSample::new(start + Duration::seconds(10), 0.0), 10.0);
+/// assert_eq!(ewma.cpu_percentage(2.0), 25.0);
+/// assert_eq!(ewma.sample_count(), 2);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ewma {
+    /// Smoothed raw CPU usage (not normalized)
+    value: f32,
+    /// Highest raw CPU usage folded in, since the average keeps no window to take it from
+    peak: f32,
+    /// Most recent sample folded in
+    last_sample: CpuSample,
+    sample_count: usize,
+}
+
+impl Ewma {
+    /// Start the average at the first sample of a process
+    pub fn new(sample: &CpuSample) -> Self {
+        Self {
+            value: sample.cpu_usage,
+            peak: sample.cpu_usage,
+            last_sample: sample.clone(),
+            sample_count: 1,
+        }
+    }
+
+    /// Fold in the next sample, weighted by the time since the previous one
+    pub fn update(&mut self, sample: &CpuSample, half_life_secs: f32) {
+        let elapsed_secs = (sample.timestamp - self.last_sample.timestamp).num_milliseconds() as f32 / 1000.0;
+        let weight = ewma_weight(elapsed_secs, half_life_secs);
+
+        self.value += weight * (sample.cpu_usage - self.value);
+        self.peak = self.peak.max(sample.cpu_usage);
+        if sample.timestamp >= self.last_sample.timestamp {
+            self.last_sample = sample.clone();
+        }
+        self.sample_count += 1;
+    }
+
+    /// Most recent sample folded into the average
+    pub fn last_sample(&self) -> &CpuSample {
+        &self.last_sample
+    }
+
+    /// Smoothed CPU percentage normalized the same way as [`calculate_average_cpu_percentage`]
+    pub fn cpu_percentage(&self, divisor: f32) -> f32 {
+        if divisor <= 0.0 {
+            return 0.0;
+        }
+        self.value / divisor
+    }
+
+    /// Highest sample folded into the average, normalized the same way as [`Ewma::cpu_percentage`]
+    pub fn peak_cpu_percentage(&self, divisor: f32) -> f32 {
+        if divisor <= 0.0 {
+            return 0.0;
+        }
+        self.peak / divisor
+    }
+
+    /// Number of samples folded into the average
+    pub fn sample_count(&self) -> usize {
+        self.sample_count
+    }
+}
+
+/// Distribution of the CPU percentages of the samples in the tracking window
+///
+/// Every value is normalized the same way as [`calculate_average_cpu_percentage`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CpuStats {
+    pub min: f32,
+    pub max: f32,
+    /// Median sample
+    pub p50: f32,
+    pub p95: f32,
+    pub p99: f32,
+    /// Population standard deviation of the samples
+    pub stddev: f32,
+    /// Most recent sample
+    pub last: f32,
+}
+
+/// Nearest-rank percentile of values sorted in ascending order
+fn percentile(sorted: &[f32], percent: f32) -> f32 {
+    let rank = (percent / 100.0 * sorted.len() as f32).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Summarize the distribution of a set of samples
+///
+/// Percentiles use the nearest-rank method, so they are always one of the samples.
+///
+/// # Arguments
+/// * `samples` - CPU usage samples, oldest first
+/// * `divisor` - Number the raw usage is divided by, see [`CpuScale::divisor`]
+///
+/// # Returns
+/// The statistics of the normalized samples, all zero if there are no samples
+///
+/// # Examples
+/// ```
+/// use chrono::Utc;
+/// use process_shepherd::cpu_calculator::{CpuSample, calculate_cpu_stats};
+///
+/// let now = Utc::now();
+/// // A process idling at 10% that spikes to 100% every fifth sample
+/// let samples: Vec<CpuSample> = (0..10)
+///     .map(|i| CpuSample::new(now, if i % 5 == 4 { 100.0 } else { 10.0 }))
+///     .collect();
+///
+/// let stats = calculate_cpu_stats(&samples, 1.0);
+/// assert_eq!(stats.p50, 10.0);
+/// assert_eq!(stats.p95, 100.0);
+/// assert_eq!(stats.stddev, 36.0);
+/// assert_eq!(stats.last, 100.0);
+/// ```
+pub fn calculate_cpu_stats(samples: &[CpuSample], divisor: f32) -> CpuStats {
+    let values = normalize_cpu_samples(samples, divisor);
+    let Some(&last) = values.last() else {
+        return CpuStats::default();
+    };
+
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+    let variance = values.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / values.len() as f32;
+
+    let mut sorted = values;
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+    CpuStats {
+        min: sorted[0],
+        max: sorted[sorted.len() - 1],
+        p50: percentile(&sorted, 50.0),
+        p95: percentile(&sorted, 95.0),
+        p99: percentile(&sorted, 99.0),
+        stddev: variance.sqrt(),
+        last,
+    }
 }
 
 #[cfg(test)]
@@ -99,7 +579,7 @@ mod tests {
     }
 
     #[test]
-    fn test_zero_cpu_count() {
+    fn test_zero_divisor() {
         let now = Utc::now();
         let samples = vec![CpuSample::new(now, 100.0)];
         
@@ -109,11 +589,11 @@ mod tests {
     }
 
     #[test]
-    fn test_negative_cpu_count() {
+    fn test_negative_divisor() {
         let now = Utc::now();
         let samples = vec![CpuSample::new(now, 100.0)];
         
-        // Should return 0 for invalid CPU count
+        // Should return 0 for an invalid divisor
         let result = calculate_average_cpu_percentage(&samples, -1.0);
         assert_eq!(result, 0.0);
     }
@@ -189,6 +669,146 @@ mod tests {
         assert_eq!(result, 75.0);
     }
 
+    #[test]
+    fn test_peak_picks_highest_sample() {
+        let now = Utc::now();
+        let samples = vec![
+            CpuSample::new(now, 10.0),
+            CpuSample::new(now, 400.0),
+            CpuSample::new(now, 20.0),
+        ];
+
+        // Peak: 400, normalized on 8 cores: 50%
+        assert_eq!(calculate_peak_cpu_percentage(&samples, 8.0), 50.0);
+    }
+
+    #[test]
+    fn test_peak_empty_and_invalid_divisor() {
+        let now = Utc::now();
+        let samples = vec![CpuSample::new(now, 100.0)];
+
+        assert_eq!(calculate_peak_cpu_percentage(&[], 4.0), 0.0);
+        assert_eq!(calculate_peak_cpu_percentage(&samples, 0.0), 0.0);
+        assert_eq!(calculate_peak_cpu_percentage(&samples, -1.0), 0.0);
+    }
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        use chrono::TimeZone;
+        Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap() + chrono::Duration::seconds(seconds)
+    }
+
+    #[test]
+    fn test_averaging_mode_parse_and_display() {
+        assert_eq!("mean".parse::<AveragingMode>(), Ok(AveragingMode::Mean));
+        assert_eq!("Time-Weighted".parse::<AveragingMode>(), Ok(AveragingMode::TimeWeighted));
+        assert_eq!("EWMA".parse::<AveragingMode>(), Ok(AveragingMode::Ewma));
+        assert_eq!(AveragingMode::TimeWeighted.to_string(), "time-weighted");
+        assert_eq!(AveragingMode::default(), AveragingMode::Mean);
+        assert!("median".parse::<AveragingMode>().unwrap_err().contains("mean, time-weighted, ewma"));
+    }
+
+    #[test]
+    fn test_cpu_scale_parse_and_display() {
+        assert_eq!("machine".parse::<CpuScale>(), Ok(CpuScale::Machine));
+        assert_eq!("Core".parse::<CpuScale>(), Ok(CpuScale::Core));
+        assert_eq!(CpuScale::Core.to_string(), "core");
+        assert_eq!(CpuScale::default(), CpuScale::Machine);
+        assert!("socket".parse::<CpuScale>().unwrap_err().contains("machine, core"));
+    }
+
+    #[test]
+    fn test_cpu_scale_divisor() {
+        // Two samples of a process using two full cores on a four core machine
+        let samples = vec![CpuSample::new(at(0), 200.0), CpuSample::new(at(2), 200.0)];
+
+        assert_eq!(calculate_average_cpu_percentage(&samples, CpuScale::Machine.divisor(4.0)), 50.0);
+        assert_eq!(calculate_average_cpu_percentage(&samples, CpuScale::Core.divisor(4.0)), 200.0);
+        // A source reporting no cores still divides by at least one
+        assert_eq!(CpuScale::Machine.divisor(0.0), 1.0);
+    }
+
+    #[test]
+    fn test_time_weighted_regular_intervals_match_mean() {
+        let samples = vec![
+            CpuSample::new(at(0), 10.0),
+            CpuSample::new(at(2), 20.0),
+            CpuSample::new(at(4), 60.0),
+        ];
+
+        assert_eq!(
+            calculate_time_weighted_cpu_percentage(&samples, 2.0, 2.0, 0.0),
+            calculate_average_cpu_percentage(&samples, 2.0)
+        );
+    }
+
+    #[test]
+    fn test_time_weighted_irregular_intervals() {
+        // Jittery sampling: 1s, 3s and 0.5s apart
+        let samples = vec![
+            CpuSample::new(at(0), 40.0),
+            CpuSample::new(at(1), 80.0),
+            CpuSample::new(at(4), 20.0),
+            CpuSample::new(at(4) + chrono::Duration::milliseconds(500), 100.0),
+        ];
+
+        // (40 * 1 + 80 * 1 + 20 * 3 + 100 * 0.5) / 5.5 = 41.82%
+        let result = calculate_time_weighted_cpu_percentage(&samples, 1.0, 1.0, 0.0);
+        assert!((result - 230.0 / 5.5).abs() < 0.001);
+
+        // The mean over-weights the short 0.5s sample
+        assert_eq!(calculate_average_cpu_percentage(&samples, 1.0), 60.0);
+    }
+
+    #[test]
+    fn test_time_weighted_gap_counts_for_its_length() {
+        // A stalled loop left a 30 second gap; the sample after it covers all of that time
+        let samples = vec![
+            CpuSample::new(at(0), 100.0),
+            CpuSample::new(at(2), 100.0),
+            CpuSample::new(at(32), 0.0),
+            CpuSample::new(at(34), 0.0),
+        ];
+
+        // (100 * 2 + 100 * 2 + 0 * 30 + 0 * 2) / 36 = 11.11%
+        let result = calculate_time_weighted_cpu_percentage(&samples, 1.0, 2.0, 0.0);
+        assert!((result - 400.0 / 36.0).abs() < 0.001);
+        assert_eq!(calculate_average_cpu_percentage(&samples, 1.0), 50.0);
+    }
+
+    #[test]
+    fn test_time_weighted_brief_process_averaged_over_span() {
+        // Present for a single 2 second sample of a 60 second window
+        let samples = vec![CpuSample::new(at(58), 100.0)];
+
+        assert_eq!(calculate_time_weighted_cpu_percentage(&samples, 1.0, 2.0, 60.0), 100.0 * 2.0 / 60.0);
+        assert_eq!(calculate_time_weighted_cpu_percentage(&samples, 1.0, 2.0, 0.0), 100.0);
+        assert_eq!(calculate_average_cpu_percentage(&samples, 1.0), 100.0);
+    }
+
+    #[test]
+    fn test_time_weighted_edge_cases() {
+        let samples = vec![CpuSample::new(at(0), 50.0), CpuSample::new(at(0), 150.0)];
+
+        assert_eq!(calculate_time_weighted_cpu_percentage(&[], 4.0, 2.0, 60.0), 0.0);
+        assert_eq!(calculate_time_weighted_cpu_percentage(&samples, 0.0, 2.0, 60.0), 0.0);
+        // Identical timestamps and no nominal interval fall back to the mean
+        assert_eq!(calculate_time_weighted_cpu_percentage(&samples, 2.0, 0.0, 0.0), 50.0);
+    }
+
+    #[test]
+    fn test_normalize_cpu_samples() {
+        let now = Utc::now();
+        let samples = vec![
+            CpuSample::new(now, 50.0),
+            CpuSample::new(now, 200.0),
+            CpuSample::new(now, 0.0),
+        ];
+
+        assert_eq!(normalize_cpu_samples(&samples, 4.0), vec![12.5, 50.0, 0.0]);
+        assert_eq!(normalize_cpu_samples(&samples, 0.0), vec![0.0, 0.0, 0.0]);
+        assert!(normalize_cpu_samples(&[], 4.0).is_empty());
+    }
+
     #[test]
     fn test_cpu_sample_creation() {
         let now = Utc::now();
@@ -206,4 +826,136 @@ mod tests {
         
         assert_eq!(sample1, sample2);
     }
+
+    #[test]
+    fn test_cpu_stats_steady_process() {
+        let now = Utc::now();
+        let samples = vec![CpuSample::new(now, 40.0); 30];
+
+        // 40% on 4 cores = a steady 10% with no spread
+        let stats = calculate_cpu_stats(&samples, 4.0);
+        assert_eq!(stats.min, 10.0);
+        assert_eq!(stats.max, 10.0);
+        assert_eq!(stats.p50, 10.0);
+        assert_eq!(stats.p99, 10.0);
+        assert_eq!(stats.stddev, 0.0);
+    }
+
+    #[test]
+    fn test_cpu_stats_spiky_process() {
+        let now = Utc::now();
+        // Same 10% average as the steady process, but in spikes
+        let samples: Vec<CpuSample> = (0..30)
+            .map(|i| CpuSample::new(now, if i % 10 == 0 { 100.0 } else { 0.0 }))
+            .collect();
+
+        let stats = calculate_cpu_stats(&samples, 1.0);
+        assert_eq!(calculate_average_cpu_percentage(&samples, 1.0), 10.0);
+        assert_eq!(stats.min, 0.0);
+        assert_eq!(stats.p50, 0.0);
+        assert_eq!(stats.p95, 100.0);
+        assert_eq!(stats.max, 100.0);
+        assert_eq!(stats.stddev, 30.0);
+        assert_eq!(stats.last, 0.0);
+    }
+
+    #[test]
+    fn test_cpu_stats_percentiles_use_nearest_rank() {
+        let now = Utc::now();
+        // Out of order on purpose: 1..=100
+        let samples: Vec<CpuSample> = (1..=100).rev().map(|v| CpuSample::new(now, v as f32)).collect();
+
+        let stats = calculate_cpu_stats(&samples, 1.0);
+        assert_eq!(stats.p50, 50.0);
+        assert_eq!(stats.p95, 95.0);
+        assert_eq!(stats.p99, 99.0);
+        assert_eq!(stats.last, 1.0);
+    }
+
+    #[test]
+    fn test_cpu_stats_edge_cases() {
+        assert_eq!(calculate_cpu_stats(&[], 4.0), CpuStats::default());
+
+        let now = Utc::now();
+        let stats = calculate_cpu_stats(&[CpuSample::new(now, 50.0)], 2.0);
+        assert_eq!(stats.min, 25.0);
+        assert_eq!(stats.p99, 25.0);
+        assert_eq!(stats.last, 25.0);
+        assert_eq!(stats.stddev, 0.0);
+    }
+
+    #[test]
+    fn test_ewma_follows_the_signal_without_a_window() {
+        let mut ewma = Ewma::new(&CpuSample::new(at(0), 0.0));
+        for tick in 1..=30 {
+            ewma.update(&CpuSample::new(at(tick * 2), 100.0), 10.0);
+        }
+        // 60 seconds of load with a 10 second half-life: within 2% of the real usage
+        let busy = ewma.cpu_percentage(1.0);
+        assert!(busy > 98.0 && busy < 100.0, "{}", busy);
+
+        // A spike that ended 10 seconds ago has already lost half its weight,
+        // where a 60 second window would still report it in full
+        for tick in 31..=35 {
+            ewma.update(&CpuSample::new(at(tick * 2), 0.0), 10.0);
+        }
+        assert!((ewma.cpu_percentage(1.0) - busy / 2.0).abs() < 0.01);
+        assert_eq!(ewma.sample_count(), 36);
+    }
+
+    #[test]
+    fn test_ewma_weights_by_elapsed_time() {
+        let mut regular = Ewma::new(&CpuSample::new(at(0), 0.0));
+        regular.update(&CpuSample::new(at(2), 100.0), 4.0);
+        regular.update(&CpuSample::new(at(4), 100.0), 4.0);
+
+        // One late sample covering the same 4 seconds ends up at the same average
+        let mut stalled = Ewma::new(&CpuSample::new(at(0), 0.0));
+        stalled.update(&CpuSample::new(at(4), 100.0), 4.0);
+
+        assert_eq!(stalled.cpu_percentage(1.0), 50.0);
+        assert!((regular.cpu_percentage(1.0) - stalled.cpu_percentage(1.0)).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_ewma_edge_cases() {
+        let mut ewma = Ewma::new(&CpuSample::new(at(0), 80.0));
+        assert_eq!(ewma.cpu_percentage(0.0), 0.0);
+
+        // Without a half-life the average is simply the latest sample
+        ewma.update(&CpuSample::new(at(2), 20.0), 0.0);
+        assert_eq!(ewma.cpu_percentage(1.0), 20.0);
+
+        // A sample at the same instant carries no weight
+        ewma.update(&CpuSample::new(at(2), 100.0), 10.0);
+        assert_eq!(ewma.cpu_percentage(1.0), 20.0);
+
+        // The peak still counts every sample
+        assert_eq!(ewma.peak_cpu_percentage(2.0), 50.0);
+        assert_eq!(ewma.peak_cpu_percentage(0.0), 0.0);
+    }
+
+    #[test]
+    fn test_cpu_time_consumed() {
+        let samples = vec![
+            CpuTimeSample::new(at(0), 10.0),
+            CpuTimeSample::new(at(2), 10.5),
+            CpuTimeSample::new(at(4), 13.25),
+        ];
+        assert_eq!(calculate_cpu_time_consumed(&samples), 3.25);
+        assert_eq!(calculate_cpu_time_percentage(&samples, 1.0), Some(81.25));
+
+        assert_eq!(calculate_cpu_time_consumed(&samples[..1]), 0.0);
+        assert_eq!(calculate_cpu_time_consumed(&[]), 0.0);
+        assert_eq!(calculate_cpu_time_percentage(&[], 1.0), None);
+        assert_eq!(calculate_cpu_time_percentage(&samples, 0.0), None);
+    }
+
+    #[test]
+    fn test_cpu_time_counts_bursts_between_samples() {
+        // A process that burned 1.9 CPU-seconds between two refreshes but happened to be
+        // idle at both sampling instants is still fully accounted for
+        let samples = vec![CpuTimeSample::new(at(0), 0.0), CpuTimeSample::new(at(2), 1.9)];
+        assert_eq!(calculate_cpu_time_percentage(&samples, 1.0), Some(95.0));
+    }
 }
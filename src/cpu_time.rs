@@ -0,0 +1,199 @@
+use sysinfo::Pid;
+
+/// Clock ticks per second used by the CPU times in `/proc` (`USER_HZ`, 100 on all common Linux platforms)
+pub const USER_HZ: f64 = 100.0;
+
+/// Cumulative CPU time of one process, in clock ticks, as listed in `/proc/<pid>/stat`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ProcessCpuTimes {
+    pub user_ticks: u64,
+    pub system_ticks: u64,
+    /// CPU time of children that exited and were waited for
+    pub children_user_ticks: u64,
+    pub children_system_ticks: u64,
+}
+
+impl ProcessCpuTimes {
+    /// User and system CPU time of the process itself in seconds
+    pub fn own_secs(&self) -> f64 {
+        (self.user_ticks + self.system_ticks) as f64 / USER_HZ
+    }
+
+    /// User and system CPU time of the reaped children in seconds
+    pub fn children_secs(&self) -> f64 {
+        (self.children_user_ticks + self.children_system_ticks) as f64 / USER_HZ
+    }
+}
+
+/// Parse the contents of `/proc/<pid>/stat`
+///
+/// # Examples
+/// ```
+/// use process_shepherd::cpu_time::parse_process_stat;
+///
+/// let stat = "42 (my (odd) app) S 1 42 42 0 -1 4194560 500 0 0 0 250 50 30 20 20 0 1 0 100 0 0";
+/// let times = parse_process_stat(stat).unwrap();
+/// assert_eq!(times.user_ticks, 250);
+/// assert_eq!(times.system_ticks, 50);
+/// assert_eq!(times.own_secs(), 3.0);
+/// assert_eq!(times.children_secs(), 0.5);
+/// ```
+pub fn parse_process_stat(contents: &str) -> Option<ProcessCpuTimes> {
+    // The command name may contain spaces and parentheses, so the fields start after the last ')'
+    let fields: Vec<&str> = contents[contents.rfind(')')? + 1..].split_whitespace().collect();
+    // Fields 14 to 17 of the file; the state (field 3) is the first one after the name
+    let field = |number: usize| fields.get(number - 3)?.parse::<u64>().ok();
+
+    Some(ProcessCpuTimes {
+        user_ticks: field(14)?,
+        system_ticks: field(15)?,
+        children_user_ticks: field(16)?,
+        children_system_ticks: field(17)?,
+    })
+}
+
+/// Cumulative CPU time of the whole machine, in clock ticks summed over all cores
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SystemCpuTimes {
+    /// Time spent running anything: user, nice, system, irq, softirq and steal
+    pub busy_ticks: u64,
+    /// Busy time plus idle and iowait
+    pub total_ticks: u64,
+}
+
+impl SystemCpuTimes {
+    /// Busy time in CPU-seconds
+    pub fn busy_secs(&self) -> f64 {
+        self.busy_ticks as f64 / USER_HZ
+    }
+}
+
+/// Parse the aggregate `cpu` line of `/proc/stat`
+///
+/// # Examples
+/// ```
+/// use process_shepherd::cpu_time::parse_system_stat;
+///
+/// let stat = "cpu  100 20 30 800 40 5 5 0 0 0\ncpu0 50 10 15 400 20 2 3 0 0 0\n";
+/// let times = parse_system_stat(stat).unwrap();
+/// assert_eq!(times.busy_ticks, 160);
+/// assert_eq!(times.total_ticks, 1000);
+/// ```
+pub fn parse_system_stat(contents: &str) -> Option<SystemCpuTimes> {
+    let line = contents.lines().find(|line| line.starts_with("cpu "))?;
+    let values: Vec<u64> = line.split_whitespace().skip(1).map_while(|v| v.parse().ok()).collect();
+    // user nice system idle iowait irq softirq [steal]; guest time is already part of user
+    if values.len() < 7 {
+        return None;
+    }
+
+    let busy_ticks = values[0] + values[1] + values[2] + values[5] + values[6] + values.get(7).copied().unwrap_or(0);
+    Some(SystemCpuTimes {
+        busy_ticks,
+        total_ticks: busy_ticks + values[3] + values[4],
+    })
+}
+
+/// CPU-seconds consumed in the tracking window
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CpuTimeTotals {
+    /// Sum over every process seen in the window, including processes that have exited since
+    pub process_secs: f64,
+    /// Time the whole machine was busy, if known
+    pub busy_secs: Option<f64>,
+}
+
+impl CpuTimeTotals {
+    /// Share of the machine's busy time attributed to processes, in percent
+    ///
+    /// # Examples
+    /// ```
+    /// use process_shepherd::cpu_time::CpuTimeTotals;
+    ///
+    /// let totals = CpuTimeTotals { process_secs: 45.0, busy_secs: Some(50.0) };
+    /// assert_eq!(totals.accounted_percent(), Some(90.0));
+    /// assert_eq!(CpuTimeTotals::default().accounted_percent(), None);
+    /// ```
+    pub fn accounted_percent(&self) -> Option<f64> {
+        match self.busy_secs {
+            Some(busy_secs) if busy_secs > 0.0 => Some(self.process_secs / busy_secs * 100.0),
+            _ => None,
+        }
+    }
+}
+
+/// Read the cumulative CPU time of a process, `None` if unavailable (not Linux, or the process exited)
+#[cfg(target_os = "linux")]
+pub fn read_process_cpu_times(pid: Pid) -> Option<ProcessCpuTimes> {
+    let contents = std::fs::read_to_string(format!("/proc/{}/stat", pid.as_u32())).ok()?;
+    parse_process_stat(&contents)
+}
+
+/// Read the cumulative CPU time of a process, `None` if unavailable (not Linux, or the process exited)
+#[cfg(not(target_os = "linux"))]
+pub fn read_process_cpu_times(_pid: Pid) -> Option<ProcessCpuTimes> {
+    None
+}
+
+/// Read the cumulative CPU time of the machine, `None` if unavailable (not Linux)
+#[cfg(target_os = "linux")]
+pub fn read_system_cpu_times() -> Option<SystemCpuTimes> {
+    let contents = std::fs::read_to_string("/proc/stat").ok()?;
+    parse_system_stat(&contents)
+}
+
+/// Read the cumulative CPU time of the machine, `None` if unavailable (not Linux)
+#[cfg(not(target_os = "linux"))]
+pub fn read_system_cpu_times() -> Option<SystemCpuTimes> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_process_stat() {
+        let stat = "1234 (cargo) R 1 1234 1234 34816 1234 4194304 1000 2000 0 0 1520 380 12 8 20 0 4 0 5000 1000000 2000 \
+                    18446744073709551615 0 0 0 0 0 0 0 0 0 0 0 0 17 3 0 0 0 0 0";
+        let times = parse_process_stat(stat).unwrap();
+
+        assert_eq!(times.user_ticks, 1520);
+        assert_eq!(times.system_ticks, 380);
+        assert_eq!(times.children_user_ticks, 12);
+        assert_eq!(times.children_system_ticks, 8);
+        assert_eq!(times.own_secs(), 19.0);
+        assert_eq!(times.children_secs(), 0.2);
+    }
+
+    #[test]
+    fn test_parse_process_stat_rejects_malformed_input() {
+        assert_eq!(parse_process_stat(""), None);
+        assert_eq!(parse_process_stat("1 (app) S 1 2 3"), None);
+        assert_eq!(parse_process_stat("1 app S 1 1 1 0 -1 0 0 0 0 0 10 10 0 0"), None);
+    }
+
+    #[test]
+    fn test_parse_system_stat() {
+        let stat = "cpu  4705 150 1120 16250 520 30 12 40 0 0\ncpu0 2352 75 560 8125 260 15 6 20 0 0\nintr 1 2 3\n";
+        let times = parse_system_stat(stat).unwrap();
+
+        assert_eq!(times.busy_ticks, 4705 + 150 + 1120 + 30 + 12 + 40);
+        assert_eq!(times.total_ticks, times.busy_ticks + 16250 + 520);
+        assert_eq!(times.busy_secs(), 60.57);
+
+        // Old kernels have no steal column
+        assert_eq!(parse_system_stat("cpu  1 2 3 4 5 6 7\n").unwrap().busy_ticks, 19);
+        assert_eq!(parse_system_stat("cpu0 1 2 3 4 5 6 7\n"), None);
+        assert_eq!(parse_system_stat("cpu  1 2 3\n"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_read_own_cpu_times() {
+        assert!(read_process_cpu_times(Pid::from_u32(std::process::id())).is_some());
+        let system = read_system_cpu_times().unwrap();
+        assert!(system.busy_ticks > 0);
+        assert!(system.total_ticks >= system.busy_ticks);
+    }
+}
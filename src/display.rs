@@ -1,7 +1,11 @@
 use chrono::Utc;
 use console::Term;
-use sysinfo::Pid;
-use std::collections::HashMap;
+use crate::cpu_calculator::{CpuScale, CpuStats};
+use crate::cpu_time::CpuTimeTotals;
+use crate::grouping::{GroupBy, ProcessGroup};
+use crate::process_info::Trend;
+use crate::process_tree::TreeRow;
+use crate::system_summary::SystemSummary;
 use crate::ProcessInfo;
 
 // Display formatting constants
@@ -12,7 +16,18 @@ pub const CPU_PERCENT_WIDTH: usize = 6;
 pub const MEMORY_WIDTH: usize = 10;
 pub const TREND_SPACING_WIDTH: usize = 4; // 2 spaces + 1 trend indicator + 1 space before Details
 pub const EXTRA_INFO_WIDTH: usize = 30;
-pub const DISPLAY_SEPARATOR_WIDTH: usize = 94;
+pub const EXTRA_INFO_MIN_WIDTH: usize = 10; // Narrower details are left out
+pub const PROCESS_NAME_MIN_WIDTH: usize = 12; // Names are shortened down to this on narrow terminals
+pub const INSTANCE_COUNT_WIDTH: usize = 5;
+pub const CPU_STATS_WIDTH: usize = 35; // P50, P95, P99, Max and StdDev columns, each 1 space + CPU_PERCENT_WIDTH
+pub const CPU_SECONDS_WIDTH: usize = 9; // 1 space + 8 characters of CPU-seconds
+pub const FOCUS_HISTORY_SAMPLES: usize = 12; // Most recent samples listed per process in focus mode
+pub const CORE_BAR_WIDTH: usize = 10; // Characters inside the brackets of a per-core usage bar
+pub const SPARKLINE_MIN_WIDTH: usize = 8; // Narrower sparklines are left out (and "History" would not fit)
+pub const SPARKLINE_MAX_WIDTH: usize = 30; // One character per sample of the default window
+
+/// Characters of a sparkline from lowest to highest
+const SPARKLINE_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
 
 /// Truncate a string to a maximum length, adding ellipsis if needed
 pub fn truncate_string(s: &str, max_len: usize) -> String {
@@ -60,13 +75,464 @@ pub fn format_memory(bytes: u64) -> String {
 /// # Returns
 /// Trend indicator: "↑" (up), "↓" (down), " " (stable)
 pub fn calculate_trend_indicator(current: f32, previous: f32, threshold: f32) -> &'static str {
-    let diff = current - previous;
-    if diff > threshold {
-        "↑"  // Upward trend
-    } else if diff < -threshold {
-        "↓"  // Downward trend
-    } else {
-        " "  // Stable/no change
+    Trend::from_change(current, previous, threshold).indicator()
+}
+
+/// Describe the tracking settings shown in the display header
+///
+/// # Examples
+/// ```
+/// use process_shepherd::cpu_calculator::CpuScale;
+/// use process_shepherd::display::format_header_settings;
+///
+/// assert_eq!(
+///     format_header_settings(60, 2, 20, CpuScale::Machine),
+///     "Tracking window: 60 seconds | Sampling every 2 seconds | Top 20 processes | 100% = all cores"
+/// );
+/// ```
+pub fn format_header_settings(retention_seconds: i64, update_interval_secs: u64, top_n: usize, cpu_scale: CpuScale) -> String {
+    format!(
+        "Tracking window: {} seconds | Sampling every {} seconds | Top {} processes | {}",
+        retention_seconds,
+        update_interval_secs,
+        top_n,
+        cpu_scale.description()
+    )
+}
+
+/// Format the most recent samples as a sparkline, one character per sample
+///
+/// Samples are scaled to the highest one shown, so the shape of the history is visible for
+/// light and heavy processes alike. Shorter histories are padded on the left, keeping the
+/// latest sample at the right edge.
+///
+/// # Examples
+/// ```
+/// use process_shepherd::display::format_sparkline;
+///
+/// assert_eq!(format_sparkline(&[0.0, 5.0, 10.0, 20.0], 4), "▁▃▅█");
+/// assert_eq!(format_sparkline(&[0.0, 5.0, 10.0, 20.0], 2), "▅█");
+/// assert_eq!(format_sparkline(&[3.0, 3.0], 4), "  ██");
+/// ```
+pub fn format_sparkline(samples: &[f32], width: usize) -> String {
+    let recent = &samples[samples.len().saturating_sub(width)..];
+    let highest = recent.iter().copied().fold(0.0, f32::max);
+    let top_level = (SPARKLINE_LEVELS.len() - 1) as f32;
+    let line: String = recent
+        .iter()
+        .map(|sample| {
+            let level = if highest > 0.0 { (sample / highest * top_level).round().max(0.0) as usize } else { 0 };
+            SPARKLINE_LEVELS[level.min(SPARKLINE_LEVELS.len() - 1)]
+        })
+        .collect();
+    format!("{:>width$}", line)
+}
+
+/// Width of a sparkline column that fits the terminal next to the rest of the table
+///
+/// # Arguments
+/// * `terminal_width` - Width of the terminal in characters
+/// * `table_width` - Width of the table without the sparkline, including the column separator
+///
+/// # Returns
+/// Up to [`SPARKLINE_MAX_WIDTH`] characters, or 0 to leave the sparkline out if fewer than
+/// [`SPARKLINE_MIN_WIDTH`] are left
+///
+/// # Examples
+/// ```
+/// use process_shepherd::display::sparkline_width;
+///
+/// assert_eq!(sparkline_width(200, 95), 30);
+/// assert_eq!(sparkline_width(110, 95), 15);
+/// assert_eq!(sparkline_width(80, 95), 0);
+/// ```
+pub fn sparkline_width(terminal_width: usize, table_width: usize) -> usize {
+    match terminal_width.saturating_sub(table_width).min(SPARKLINE_MAX_WIDTH) {
+        width if width < SPARKLINE_MIN_WIDTH => 0,
+        width => width,
+    }
+}
+
+/// Format a usage bar filled in proportion to a percentage
+///
+/// # Examples
+/// ```
+/// use process_shepherd::display::format_usage_bar;
+///
+/// assert_eq!(format_usage_bar(42.0, 10), "[||||      ]");
+/// assert_eq!(format_usage_bar(150.0, 4), "[||||]");
+/// ```
+pub fn format_usage_bar(percent: f32, width: usize) -> String {
+    let filled = ((percent.clamp(0.0, 100.0) / 100.0) * width as f32).round() as usize;
+    format!("[{}{}]", "|".repeat(filled), " ".repeat(width - filled))
+}
+
+/// Format the per-core usage bars, as many cores per line as fit the width
+///
+/// # Examples
+/// ```
+/// use process_shepherd::display::format_core_bars;
+///
+/// assert_eq!(format_core_bars(&[100.0, 4.0], 94), vec!["  0 [||||||||||] 100%    1 [          ]   4%"]);
+/// assert_eq!(format_core_bars(&[100.0, 4.0], 30).len(), 2);
+/// ```
+pub fn format_core_bars(core_percents: &[f32], width: usize) -> Vec<String> {
+    let cells: Vec<String> = core_percents
+        .iter()
+        .enumerate()
+        .map(|(i, percent)| format!("{:>3} {} {:>3.0}%", i, format_usage_bar(*percent, CORE_BAR_WIDTH), percent))
+        .collect();
+    let cell_width = cells.first().map_or(1, |cell| cell.chars().count() + 2);
+    // The last cell of a line needs no separator
+    let per_line = ((width + 2) / cell_width).max(1);
+
+    cells.chunks(per_line).map(|line| line.join("  ")).collect()
+}
+
+/// Format the whole-machine summary shown above the ranking, with the per-core bars fitted to `width`
+///
+/// # Returns
+/// A line with CPU, load, memory and process count, a line splitting the CPU between the
+/// listed processes and everything else, and the per-core usage bars
+pub fn format_system_summary(summary: &SystemSummary, width: usize) -> Vec<String> {
+    let stats = &summary.stats;
+    let load = match stats.load_average {
+        Some([one, five, fifteen]) => format!(" | Load: {:.2} {:.2} {:.2}", one, five, fifteen),
+        None => String::new(),
+    };
+    let mut lines = vec![
+        format!(
+            "CPU: {:.1}% busy{} | Memory: {} / {} | Swap: {} / {} | Processes: {}",
+            stats.cpu_percent,
+            load,
+            format_memory(stats.memory_used_bytes),
+            format_memory(stats.memory_total_bytes),
+            format_memory(stats.swap_used_bytes),
+            format_memory(stats.swap_total_bytes),
+            summary.process_count
+        ),
+        format!(
+            "CPU of the machine: listed {} processes {:.1}% | everything else {:.1}%",
+            summary.listed_count,
+            summary.listed_cpu_percent,
+            summary.other_cpu_percent()
+        ),
+    ];
+    lines.extend(format_core_bars(&stats.core_percents, width));
+    lines
+}
+
+/// Column headings of the optional CPU distribution columns
+pub fn format_cpu_stats_header() -> String {
+    format!(
+        " {:>w$} {:>w$} {:>w$} {:>w$} {:>w$}",
+        "P50", "P95", "P99", "Max", "StdDev",
+        w = CPU_PERCENT_WIDTH
+    )
+}
+
+/// Optional CPU distribution columns of one process
+///
+/// # Examples
+/// ```
+/// use process_shepherd::cpu_calculator::CpuStats;
+/// use process_shepherd::display::{format_cpu_stats, format_cpu_stats_header, CPU_STATS_WIDTH};
+///
+/// let stats = CpuStats { p50: 1.0, p95: 40.0, p99: 98.5, max: 100.0, stddev: 12.25, ..CpuStats::default() };
+/// assert_eq!(format_cpu_stats(&stats), "   1.00  40.00  98.50 100.00  12.25");
+/// assert_eq!(format_cpu_stats(&stats).len(), CPU_STATS_WIDTH);
+/// assert_eq!(format_cpu_stats_header().len(), CPU_STATS_WIDTH);
+/// ```
+pub fn format_cpu_stats(stats: &CpuStats) -> String {
+    format!(
+        " {:>w$.2} {:>w$.2} {:>w$.2} {:>w$.2} {:>w$.2}",
+        stats.p50, stats.p95, stats.p99, stats.max, stats.stddev,
+        w = CPU_PERCENT_WIDTH
+    )
+}
+
+/// Optional column with the CPU-seconds a process consumed in the window
+///
+/// # Examples
+/// ```
+/// use process_shepherd::display::{format_cpu_seconds, CPU_SECONDS_WIDTH};
+///
+/// assert_eq!(format_cpu_seconds(Some(12.345)), "    12.35");
+/// assert_eq!(format_cpu_seconds(None), "        -");
+/// assert_eq!(format_cpu_seconds(None).len(), CPU_SECONDS_WIDTH);
+/// ```
+pub fn format_cpu_seconds(cpu_seconds: Option<f64>) -> String {
+    match cpu_seconds {
+        Some(secs) => format!(" {:>w$.2}", secs, w = CPU_SECONDS_WIDTH - 1),
+        None => format!(" {:>w$}", "-", w = CPU_SECONDS_WIDTH - 1),
+    }
+}
+
+/// Details column of a process, prefixed with when it exited for exited processes kept in the ranking
+///
+/// # Examples
+/// ```
+/// use chrono::{TimeZone, Utc};
+/// use process_shepherd::display::format_details;
+/// use process_shepherd::ProcessInfo;
+/// use sysinfo::Pid;
+///
+/// let info = ProcessInfo::new("gcc".to_string(), Pid::from_u32(7), 90.0, 0, "main.c".to_string());
+/// assert_eq!(format_details(&info), "main.c");
+///
+/// let info = info.with_exited_at(Some(Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 4).unwrap()));
+/// assert_eq!(format_details(&info), "exited at 12:00:04 main.c");
+/// ```
+pub fn format_details(info: &ProcessInfo) -> String {
+    match info.exited_at {
+        Some(exited_at) if info.extra_info.is_empty() => format!("exited at {}", exited_at.format("%H:%M:%S")),
+        Some(exited_at) => format!("exited at {} {}", exited_at.format("%H:%M:%S"), info.extra_info),
+        None => info.extra_info.clone(),
+    }
+}
+
+/// Width of the terminal in characters, `None` if the output is not a terminal
+pub fn terminal_width(term: &Term) -> Option<usize> {
+    term.size_checked().map(|(_, columns)| columns as usize)
+}
+
+/// Print a line and return the number of terminal rows it takes (more than one if it wraps),
+/// so the next refresh moves the cursor back over all of them
+pub fn print_line(line: &str, terminal_width: Option<usize>) -> usize {
+    println!("{}", line);
+    match terminal_width {
+        Some(width) if width > 0 => console::measure_text_width(line).max(1).div_ceil(width),
+        _ => 1,
+    }
+}
+
+/// A table whose columns can be dropped and names shortened to fit the terminal
+trait FitToWidth {
+    /// Width of a table line in characters
+    fn line_width(&self) -> usize;
+
+    fn name_width_mut(&mut self) -> &mut usize;
+
+    /// Drop columns in the given order until the line fits, then shorten the names
+    ///
+    /// # Returns
+    /// The room left on the line
+    fn shrink_to(&mut self, available: usize, drops: &[fn(&mut Self)]) -> usize {
+        for drop in drops {
+            if self.line_width() <= available {
+                break;
+            }
+            drop(self);
+        }
+        let too_wide = self.line_width().saturating_sub(available);
+        let name_width = self.name_width_mut();
+        *name_width -= too_wide.min(name_width.saturating_sub(PROCESS_NAME_MIN_WIDTH));
+        available.saturating_sub(self.line_width())
+    }
+}
+
+/// Columns of the process table fitted to the terminal width
+///
+/// Every requested column is shown at its usual width when there is room. On narrower
+/// terminals the least important columns are dropped first: the CPU distribution, the
+/// CPU-seconds of reaped children, the CPU-seconds, memory, details and finally the PID; then
+/// the names are shortened. The sample history only gets the room left over, and Details
+/// takes everything past the history's full width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColumnLayout {
+    pub name_width: usize,
+    pub show_pid: bool,
+    pub show_cpu_stats: bool,
+    pub show_cpu_seconds: bool,
+    pub show_children_cpu_seconds: bool,
+    /// Width of the sample history sparkline, 0 if left out
+    pub history_width: usize,
+    pub show_memory: bool,
+    /// Width of the details, 0 if left out
+    pub details_width: usize,
+}
+
+impl ColumnLayout {
+    /// Fit the requested columns to the terminal
+    ///
+    /// # Arguments
+    /// * `terminal_width` - Width of the terminal, `None` if not writing to one (every column at its usual width)
+    /// * `show_cpu_stats` - Whether the CPU distribution columns are wanted
+    /// * `show_cpu_seconds` - Whether the CPU-seconds column is wanted
+    /// * `show_children_cpu_seconds` - Whether the CPU-seconds of reaped children are wanted
+    /// * `show_history` - Whether the sample history is wanted (the moving average keeps no samples)
+    ///
+    /// # Examples
+    /// ```
+    /// use process_shepherd::display::{ColumnLayout, EXTRA_INFO_WIDTH};
+    ///
+    /// let wide = ColumnLayout::fit(Some(200), true, false, false, true);
+    /// assert!(wide.show_cpu_stats);
+    /// assert_eq!(wide.width(), 199);
+    /// assert!(wide.details_width > EXTRA_INFO_WIDTH);
+    ///
+    /// let narrow = ColumnLayout::fit(Some(80), true, false, false, true);
+    /// assert!(!narrow.show_cpu_stats);
+    /// assert!(narrow.width() < 80);
+    /// ```
+    pub fn fit(
+        terminal_width: Option<usize>,
+        show_cpu_stats: bool,
+        show_cpu_seconds: bool,
+        show_children_cpu_seconds: bool,
+        show_history: bool,
+    ) -> Self {
+        let mut layout = Self {
+            name_width: PROCESS_NAME_DISPLAY_WIDTH,
+            show_pid: true,
+            show_cpu_stats,
+            show_cpu_seconds,
+            show_children_cpu_seconds,
+            history_width: 0,
+            show_memory: true,
+            details_width: EXTRA_INFO_MIN_WIDTH,
+        };
+        // Writing into the last column would wrap the cursor onto the next row
+        let Some(available) = terminal_width.map(|width| width.saturating_sub(1)) else {
+            if show_history {
+                layout.history_width = SPARKLINE_MAX_WIDTH;
+            }
+            layout.details_width = EXTRA_INFO_WIDTH;
+            return layout;
+        };
+
+        let drops: [fn(&mut Self); 6] = [
+            |layout| layout.show_cpu_stats = false,
+            |layout| layout.show_children_cpu_seconds = false,
+            |layout| layout.show_cpu_seconds = false,
+            |layout| layout.show_memory = false,
+            |layout| layout.details_width = 0,
+            |layout| layout.show_pid = false,
+        ];
+        // Share the room left: details up to their usual width, then the history, then details again
+        let mut spare = layout.shrink_to(available, &drops);
+        if layout.details_width > 0 {
+            let grow = spare.min(EXTRA_INFO_WIDTH - layout.details_width);
+            layout.details_width += grow;
+            spare -= grow;
+        }
+        // The history column is preceded by a space
+        let history_width = spare.saturating_sub(1).min(SPARKLINE_MAX_WIDTH);
+        if show_history && history_width >= SPARKLINE_MIN_WIDTH {
+            layout.history_width = history_width;
+            spare -= history_width + 1;
+        }
+        if layout.details_width > 0 {
+            layout.details_width += spare;
+        }
+        layout
+    }
+
+    /// Width of a table line in characters
+    pub fn width(&self) -> usize {
+        // Line number, name, CPU % and trend are always shown
+        let mut width = LINE_NUMBER_WIDTH + 1 + self.name_width + 1 + CPU_PERCENT_WIDTH + TREND_SPACING_WIDTH - 1;
+        if self.show_pid {
+            width += PID_WIDTH + 1;
+        }
+        if self.show_cpu_stats {
+            width += CPU_STATS_WIDTH;
+        }
+        if self.show_cpu_seconds {
+            width += CPU_SECONDS_WIDTH;
+        }
+        if self.show_children_cpu_seconds {
+            width += CPU_SECONDS_WIDTH;
+        }
+        if self.history_width > 0 {
+            width += self.history_width + 1;
+        }
+        if self.show_memory {
+            width += MEMORY_WIDTH + 1;
+        }
+        if self.details_width > 0 {
+            width += self.details_width + 1;
+        }
+        width
+    }
+
+    /// Column headings
+    pub fn format_header(&self, cpu_scale: CpuScale) -> String {
+        let mut line = format!("{:>LINE_NUMBER_WIDTH$} {:<w$}", "", "Process Name", w = self.name_width);
+        if self.show_pid {
+            line.push_str(&format!(" {:<PID_WIDTH$}", "PID"));
+        }
+        line.push_str(&format!(" {:>CPU_PERCENT_WIDTH$}", cpu_scale.column_label()));
+        if self.show_cpu_stats {
+            line.push_str(&format_cpu_stats_header());
+        }
+        if self.show_cpu_seconds {
+            line.push_str(&format!(" {:>w$}", "CPU s", w = CPU_SECONDS_WIDTH - 1));
+        }
+        if self.show_children_cpu_seconds {
+            line.push_str(&format!(" {:>w$}", "Child s", w = CPU_SECONDS_WIDTH - 1));
+        }
+        if self.history_width > 0 {
+            line.push_str(&format!(" {:<w$}", "History", w = self.history_width));
+        }
+        line.push_str(&" ".repeat(TREND_SPACING_WIDTH - 1));
+        if self.show_memory {
+            line.push_str(&format!(" {:>MEMORY_WIDTH$}", "Memory"));
+        }
+        if self.details_width > 0 {
+            line.push_str(&format!(" {:<w$}", "Details", w = self.details_width));
+        }
+        line
+    }
+
+    /// One process of the ranking
+    pub fn format_row(&self, rank: usize, info: &ProcessInfo) -> String {
+        let mut line = format!(
+            "{:>LINE_NUMBER_WIDTH$} {:<w$}",
+            rank,
+            truncate_string(&info.name, self.name_width),
+            w = self.name_width
+        );
+        if self.show_pid {
+            line.push_str(&format!(" {:<PID_WIDTH$}", info.pid.as_u32()));
+        }
+        line.push_str(&format!(" {:>CPU_PERCENT_WIDTH$.2}", info.cpu_percent));
+        if self.show_cpu_stats {
+            line.push_str(&format_cpu_stats(&info.cpu_stats));
+        }
+        if self.show_cpu_seconds {
+            line.push_str(&format_cpu_seconds(info.cpu_seconds));
+        }
+        if self.show_children_cpu_seconds {
+            line.push_str(&format_cpu_seconds(info.children_cpu_seconds));
+        }
+        if self.history_width > 0 {
+            line.push_str(&format!(" {}", format_sparkline(&info.cpu_samples, self.history_width)));
+        }
+        // No previous data shows no trend
+        let trend_indicator = info.trend().map_or(" ", |trend| trend.indicator());
+        line.push_str(&format!("  {}", trend_indicator));
+        if self.show_memory {
+            line.push_str(&format!(" {:>MEMORY_WIDTH$}", format_memory(info.memory_bytes)));
+        }
+        if self.details_width > 0 {
+            line.push_str(&format!(
+                " {:<w$}",
+                truncate_string(&format_details(info), self.details_width),
+                w = self.details_width
+            ));
+        }
+        line
+    }
+}
+
+impl FitToWidth for ColumnLayout {
+    fn line_width(&self) -> usize {
+        self.width()
+    }
+
+    fn name_width_mut(&mut self) -> &mut usize {
+        &mut self.name_width
     }
 }
 
@@ -74,20 +540,29 @@ pub fn calculate_trend_indicator(current: f32, previous: f32, threshold: f32) ->
 ///
 /// # Arguments
 /// * `term` - Terminal reference for cursor control
-/// * `results` - Vector of ProcessInfo sorted by CPU usage
+/// * `results` - Vector of ProcessInfo sorted by CPU usage, with previous CPU percentages for trends
+/// * `system` - Whole-machine summary shown above the table, if known
 /// * `retention_seconds` - Tracking window size in seconds
-/// * `previous_cpu_burn` - Map of previous CPU percentages for trend calculation
+/// * `update_interval_secs` - Time between two samples in seconds
 /// * `top_n` - Number of top processes to display
+/// * `cpu_scale` - What 100% means in the CPU percentages
+/// * `sort_description` - How the processes are sorted (e.g. "CPU % ▼")
+/// * `show_cpu_stats` - Whether to add the CPU distribution columns after "CPU %"
 /// * `last_output_lines` - Number of lines from the previous output (for clearing)
 ///
 /// # Returns
 /// The number of lines output (to be used for next refresh)
+#[allow(clippy::too_many_arguments)]
 pub fn display_top_processes(
     term: &Term,
     results: &[ProcessInfo],
+    system: Option<&SystemSummary>,
     retention_seconds: i64,
-    previous_cpu_burn: &HashMap<Pid, f32>,
+    update_interval_secs: u64,
     top_n: usize,
+    cpu_scale: CpuScale,
+    sort_description: &str,
+    show_cpu_stats: bool,
     last_output_lines: usize,
 ) -> usize {
     // Move cursor to home position and overwrite (don't clear the screen)
@@ -98,53 +573,584 @@ pub fn display_top_processes(
         let _ = term.clear_to_end_of_screen();
     }
 
+    // Columns are fitted to the terminal on every refresh, so resizing takes effect right away
+    let terminal_width = terminal_width(term);
+    let layout = ColumnLayout::fit(
+        terminal_width,
+        show_cpu_stats,
+        // CPU time accounting reports the CPU-seconds consumed in the window...
+        results.iter().any(|info| info.cpu_seconds.is_some()),
+        // ...and the CPU-seconds of the children each process reaped, if attributed to it
+        results.iter().any(|info| info.children_cpu_seconds.is_some()),
+        // ...and the moving average keeps no samples to draw a history from
+        results.iter().any(|info| !info.cpu_samples.is_empty()),
+    );
+
     let mut line_count = 0;
 
-    println!("=== Process Shepherd - CPU Usage Tracker ===");
-    line_count += 1;
-    println!("Tracking window: {} seconds", retention_seconds);
-    line_count += 1;
-    println!("Timestamp: {}", Utc::now().format("%Y-%m-%d %H:%M:%S"));
-    line_count += 1;
-    println!();
-    line_count += 1;
-    println!(
-        "{:>LINE_NUMBER_WIDTH$} {:<PROCESS_NAME_DISPLAY_WIDTH$} {:<PID_WIDTH$} {:>CPU_PERCENT_WIDTH$}{:TREND_SPACING_WIDTH$}{:>MEMORY_WIDTH$} {:<EXTRA_INFO_WIDTH$}",
-        "", "Process Name", "PID", "CPU %", "", "Memory", "Details"
+    line_count += print_line("=== Process Shepherd - CPU Usage Tracker ===", terminal_width);
+    line_count += print_line(&format_header_settings(retention_seconds, update_interval_secs, top_n, cpu_scale), terminal_width);
+    line_count += print_line(
+        &format!("Timestamp: {} | Sorted by: {}", Utc::now().format("%Y-%m-%d %H:%M:%S"), sort_description),
+        terminal_width,
     );
-    line_count += 1;
-    println!("{}", "=".repeat(DISPLAY_SEPARATOR_WIDTH));
-    line_count += 1;
+    for line in system.map(|summary| format_system_summary(summary, layout.width())).unwrap_or_default() {
+        line_count += print_line(&line, terminal_width);
+    }
+    line_count += print_line("", terminal_width);
+    line_count += print_line(&layout.format_header(cpu_scale), terminal_width);
+    line_count += print_line(&"=".repeat(layout.width()), terminal_width);
 
     for (i, info) in results.iter().take(top_n).enumerate() {
-        // Calculate trend indicator
-        let trend_indicator = if let Some(prev_cpu_percent) = previous_cpu_burn.get(&info.pid) {
-            calculate_trend_indicator(info.cpu_percent, *prev_cpu_percent, 0.1)
+        let line = layout.format_row(i + 1, info);
+        // Exited processes kept in the ranking are dimmed
+        if info.exited_at.is_some() {
+            line_count += print_line(&console::style(line).dim().to_string(), terminal_width);
         } else {
-            " "  // No previous data
+            line_count += print_line(&line, terminal_width);
+        }
+    }
+
+    if results.is_empty() {
+        line_count += print_line("No process data available yet. Collecting samples...", terminal_width);
+    }
+
+    line_count
+}
+
+/// Columns of the grouped view fitted to the terminal width
+///
+/// On narrow terminals memory is dropped first, then the member PIDs and the instance
+/// count; then the names are shortened. The PIDs take all the room left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GroupLayout {
+    pub name_width: usize,
+    pub show_count: bool,
+    pub show_memory: bool,
+    /// Width of the member PIDs, 0 if left out
+    pub pids_width: usize,
+}
+
+impl GroupLayout {
+    /// Fit the grouped view to the terminal
+    ///
+    /// # Arguments
+    /// * `terminal_width` - Width of the terminal, `None` if not writing to one (every column at its usual width)
+    pub fn fit(terminal_width: Option<usize>) -> Self {
+        let mut layout = Self {
+            name_width: PROCESS_NAME_DISPLAY_WIDTH,
+            show_count: true,
+            show_memory: true,
+            pids_width: EXTRA_INFO_MIN_WIDTH,
         };
+        let Some(available) = terminal_width.map(|width| width.saturating_sub(1)) else {
+            layout.pids_width = EXTRA_INFO_WIDTH;
+            return layout;
+        };
+
+        let drops: [fn(&mut Self); 3] = [
+            |layout| layout.show_memory = false,
+            |layout| layout.pids_width = 0,
+            |layout| layout.show_count = false,
+        ];
+        let spare = layout.shrink_to(available, &drops);
+        if layout.pids_width > 0 {
+            layout.pids_width += spare;
+        }
+        layout
+    }
+
+    /// Width of a table line in characters
+    pub fn width(&self) -> usize {
+        // Line number, name and CPU % are always shown
+        let mut width = LINE_NUMBER_WIDTH + 1 + self.name_width + 1 + CPU_PERCENT_WIDTH;
+        if self.show_count {
+            width += INSTANCE_COUNT_WIDTH + 1;
+        }
+        if self.show_memory {
+            width += MEMORY_WIDTH + 1;
+        }
+        if self.pids_width > 0 {
+            width += self.pids_width + 1;
+        }
+        width
+    }
+
+    /// Column headings
+    pub fn format_header(&self, group_by: GroupBy, cpu_scale: CpuScale) -> String {
+        let mut line = format!("{:>LINE_NUMBER_WIDTH$} {:<w$}", "", group_by.label(), w = self.name_width);
+        if self.show_count {
+            line.push_str(&format!(" {:>INSTANCE_COUNT_WIDTH$}", "Count"));
+        }
+        line.push_str(&format!(" {:>CPU_PERCENT_WIDTH$}", cpu_scale.column_label()));
+        if self.show_memory {
+            line.push_str(&format!(" {:>MEMORY_WIDTH$}", "Memory"));
+        }
+        if self.pids_width > 0 {
+            line.push_str(&format!(" {:<w$}", "PIDs", w = self.pids_width));
+        }
+        line
+    }
 
-        // Format the output with all columns
-        let name_display = truncate_string(&info.name, PROCESS_NAME_DISPLAY_WIDTH);
-        let memory_display = format_memory(info.memory_bytes);
-        let extra_display = truncate_string(&info.extra_info, EXTRA_INFO_WIDTH);
-
-        println!(
-            "{:>LINE_NUMBER_WIDTH$} {:<PROCESS_NAME_DISPLAY_WIDTH$} {:<PID_WIDTH$} {:>CPU_PERCENT_WIDTH$.2}  {} {:>MEMORY_WIDTH$} {:<EXTRA_INFO_WIDTH$}",
-            i + 1,
-            name_display,
-            info.pid.as_u32(),
-            info.cpu_percent,
-            trend_indicator,
-            memory_display,
-            extra_display,
+    /// One application group: instance count, combined CPU and memory, and member PIDs
+    pub fn format_row(&self, rank: usize, group: &ProcessGroup) -> String {
+        let mut line = format!(
+            "{:>LINE_NUMBER_WIDTH$} {:<w$}",
+            rank,
+            truncate_string(&group.key, self.name_width),
+            w = self.name_width
         );
-        line_count += 1;
+        if self.show_count {
+            line.push_str(&format!(" {:>INSTANCE_COUNT_WIDTH$}", group.instance_count()));
+        }
+        line.push_str(&format!(" {:>CPU_PERCENT_WIDTH$.2}", group.cpu_percent));
+        if self.show_memory {
+            line.push_str(&format!(" {:>MEMORY_WIDTH$}", format_memory(group.memory_bytes)));
+        }
+        if self.pids_width > 0 {
+            let pids: Vec<String> = group.pids.iter().map(|pid| pid.as_u32().to_string()).collect();
+            line.push_str(&format!(
+                " {:<w$}",
+                truncate_string(&pids.join(", "), self.pids_width),
+                w = self.pids_width
+            ));
+        }
+        line
+    }
+}
+
+impl FitToWidth for GroupLayout {
+    fn line_width(&self) -> usize {
+        self.width()
+    }
+
+    fn name_width_mut(&mut self) -> &mut usize {
+        &mut self.name_width
+    }
+}
+
+/// Display processes aggregated into application groups
+///
+/// # Arguments
+/// * `term` - Terminal reference for cursor control
+/// * `groups` - Groups sorted by rank
+/// * `group_by` - How the processes were grouped
+/// * `retention_seconds` - Tracking window size in seconds
+/// * `update_interval_secs` - Time between two samples in seconds
+/// * `top_n` - Number of groups shown
+/// * `cpu_scale` - What 100% means in the CPU percentages
+/// * `sort_description` - How the groups are sorted (e.g. "CPU % ▼")
+/// * `last_output_lines` - Number of lines from the previous output (for clearing)
+///
+/// # Returns
+/// The number of lines output (to be used for next refresh)
+#[allow(clippy::too_many_arguments)]
+pub fn display_process_groups(
+    term: &Term,
+    groups: &[ProcessGroup],
+    group_by: GroupBy,
+    retention_seconds: i64,
+    update_interval_secs: u64,
+    top_n: usize,
+    cpu_scale: CpuScale,
+    sort_description: &str,
+    last_output_lines: usize,
+) -> usize {
+    if last_output_lines > 0 {
+        let _ = term.move_cursor_up(last_output_lines);
+        let _ = term.clear_to_end_of_screen();
+    }
+
+    let terminal_width = terminal_width(term);
+    let layout = GroupLayout::fit(terminal_width);
+    let mut line_count = 0;
+
+    line_count += print_line(&format!("=== Process Shepherd - Grouped by {} ===", group_by.as_str()), terminal_width);
+    line_count += print_line(&format_header_settings(retention_seconds, update_interval_secs, top_n, cpu_scale), terminal_width);
+    line_count += print_line(&format!("Timestamp: {} | Sorted by: {}", Utc::now().format("%Y-%m-%d %H:%M:%S"), sort_description), terminal_width);
+    line_count += print_line("", terminal_width);
+    line_count += print_line(&layout.format_header(group_by, cpu_scale), terminal_width);
+    line_count += print_line(&"=".repeat(layout.width()), terminal_width);
+
+    for (i, group) in groups.iter().enumerate() {
+        line_count += print_line(&layout.format_row(i + 1, group), terminal_width);
+    }
+
+    if groups.is_empty() {
+        line_count += print_line("No process data available yet. Collecting samples...", terminal_width);
+    }
+
+    line_count
+}
+
+/// Format the per-user totals panel shown below the ranking
+///
+/// # Returns
+/// A blank separator line, a title, the column headings and one line per user
+pub fn format_user_summary(users: &[ProcessGroup], cpu_scale: CpuScale, terminal_width: Option<usize>) -> Vec<String> {
+    let layout = GroupLayout::fit(terminal_width);
+    let mut lines = vec![
+        String::new(),
+        "Per-user totals (all tracked processes)".to_string(),
+        layout.format_header(GroupBy::User, cpu_scale),
+    ];
+    lines.extend(users.iter().enumerate().map(|(i, user)| layout.format_row(i + 1, user)));
+    lines
+}
+
+/// Print the per-user totals panel
+///
+/// # Returns
+/// The number of lines output
+pub fn display_user_summary(term: &Term, users: &[ProcessGroup], cpu_scale: CpuScale) -> usize {
+    let terminal_width = terminal_width(term);
+    format_user_summary(users, cpu_scale, terminal_width)
+        .iter()
+        .map(|line| print_line(line, terminal_width))
+        .sum()
+}
+
+/// Format the CPU time totals shown below the ranking
+///
+/// # Examples
+/// ```
+/// use process_shepherd::cpu_time::CpuTimeTotals;
+/// use process_shepherd::display::format_cpu_time_totals;
+///
+/// let totals = CpuTimeTotals { process_secs: 45.0, busy_secs: Some(50.0) };
+/// assert_eq!(
+///     format_cpu_time_totals(&totals)[1],
+///     "CPU time in window: processes 45.00 s | machine busy 50.00 s | 90.0% accounted"
+/// );
+/// ```
+pub fn format_cpu_time_totals(totals: &CpuTimeTotals) -> Vec<String> {
+    let summary = match (totals.busy_secs, totals.accounted_percent()) {
+        (Some(busy_secs), Some(accounted)) => format!(
+            "CPU time in window: processes {:.2} s | machine busy {:.2} s | {:.1}% accounted",
+            totals.process_secs, busy_secs, accounted
+        ),
+        (Some(busy_secs), None) => format!(
+            "CPU time in window: processes {:.2} s | machine busy {:.2} s",
+            totals.process_secs, busy_secs
+        ),
+        (None, _) => format!("CPU time in window: processes {:.2} s", totals.process_secs),
+    };
+    vec![String::new(), summary]
+}
+
+/// Print the CPU time totals
+///
+/// # Returns
+/// The number of lines output
+pub fn display_cpu_time_totals(term: &Term, totals: &CpuTimeTotals) -> usize {
+    let terminal_width = terminal_width(term);
+    format_cpu_time_totals(totals)
+        .iter()
+        .map(|line| print_line(line, terminal_width))
+        .sum()
+}
+
+/// Columns of the tree view fitted to the terminal width
+///
+/// On narrow terminals the process's own memory is dropped first, then the memory of its
+/// subtree, the details and the PID; then the names are shortened. CPU and subtree CPU are
+/// always shown, and the details take all the room left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TreeLayout {
+    pub name_width: usize,
+    pub show_pid: bool,
+    pub show_memory: bool,
+    pub show_subtree_memory: bool,
+    /// Width of the details, 0 if left out
+    pub details_width: usize,
+}
+
+impl TreeLayout {
+    /// Fit the tree view to the terminal
+    ///
+    /// # Arguments
+    /// * `terminal_width` - Width of the terminal, `None` if not writing to one (every column at its usual width)
+    pub fn fit(terminal_width: Option<usize>) -> Self {
+        let mut layout = Self {
+            name_width: PROCESS_NAME_DISPLAY_WIDTH,
+            show_pid: true,
+            show_memory: true,
+            show_subtree_memory: true,
+            details_width: EXTRA_INFO_MIN_WIDTH,
+        };
+        let Some(available) = terminal_width.map(|width| width.saturating_sub(1)) else {
+            layout.details_width = EXTRA_INFO_WIDTH;
+            return layout;
+        };
+
+        let drops: [fn(&mut Self); 4] = [
+            |layout| layout.show_memory = false,
+            |layout| layout.show_subtree_memory = false,
+            |layout| layout.details_width = 0,
+            |layout| layout.show_pid = false,
+        ];
+        let spare = layout.shrink_to(available, &drops);
+        if layout.details_width > 0 {
+            layout.details_width += spare;
+        }
+        layout
+    }
+
+    /// Width of a table line in characters
+    pub fn width(&self) -> usize {
+        // Name, CPU % and subtree CPU % are always shown
+        let mut width = self.name_width + 1 + CPU_PERCENT_WIDTH + 1 + CPU_PERCENT_WIDTH;
+        if self.show_pid {
+            width += PID_WIDTH + 1;
+        }
+        if self.show_memory {
+            // Set apart from the CPU columns by two spaces
+            width += MEMORY_WIDTH + 2;
+        }
+        if self.show_subtree_memory {
+            width += MEMORY_WIDTH + 1;
+        }
+        if self.details_width > 0 {
+            width += self.details_width + 1;
+        }
+        width
+    }
+
+    /// Column headings
+    pub fn format_header(&self, cpu_scale: CpuScale) -> String {
+        let mut line = format!("{:<w$}", "Process Tree", w = self.name_width);
+        if self.show_pid {
+            line.push_str(&format!(" {:<PID_WIDTH$}", "PID"));
+        }
+        line.push_str(&format!(" {:>CPU_PERCENT_WIDTH$} {:>CPU_PERCENT_WIDTH$}", cpu_scale.column_label(), "Tree %"));
+        if self.show_memory {
+            line.push_str(&format!("  {:>MEMORY_WIDTH$}", "Memory"));
+        }
+        if self.show_subtree_memory {
+            line.push_str(&format!(" {:>MEMORY_WIDTH$}", "Tree Mem"));
+        }
+        if self.details_width > 0 {
+            line.push_str(&format!(" {:<w$}", "Details", w = self.details_width));
+        }
+        line
+    }
+
+    /// One row of the tree: own and subtree CPU and memory side by side
+    pub fn format_row(&self, row: &TreeRow) -> String {
+        let mut line = format!("{:<w$}", truncate_string(&row.display_name(), self.name_width), w = self.name_width);
+        if self.show_pid {
+            line.push_str(&format!(" {:<PID_WIDTH$}", row.info.pid.as_u32()));
+        }
+        line.push_str(&format!(
+            " {:>CPU_PERCENT_WIDTH$.2} {:>CPU_PERCENT_WIDTH$.2}",
+            row.info.cpu_percent, row.subtree_cpu_percent
+        ));
+        if self.show_memory {
+            line.push_str(&format!("  {:>MEMORY_WIDTH$}", format_memory(row.info.memory_bytes)));
+        }
+        if self.show_subtree_memory {
+            line.push_str(&format!(" {:>MEMORY_WIDTH$}", format_memory(row.subtree_memory_bytes)));
+        }
+        if self.details_width > 0 {
+            line.push_str(&format!(
+                " {:<w$}",
+                truncate_string(&format_details(&row.info), self.details_width),
+                w = self.details_width
+            ));
+        }
+        line
+    }
+}
+
+impl FitToWidth for TreeLayout {
+    fn line_width(&self) -> usize {
+        self.width()
+    }
+
+    fn name_width_mut(&mut self) -> &mut usize {
+        &mut self.name_width
+    }
+}
+
+/// Display processes nested under their parents with subtree totals
+///
+/// # Arguments
+/// * `term` - Terminal reference for cursor control
+/// * `rows` - Flattened process tree
+/// * `retention_seconds` - Tracking window size in seconds
+/// * `update_interval_secs` - Time between two samples in seconds
+/// * `top_n` - Number of root processes shown
+/// * `cpu_scale` - What 100% means in the CPU percentages
+/// * `sort_description` - How each level is sorted (e.g. "CPU % ▼")
+/// * `last_output_lines` - Number of lines from the previous output (for clearing)
+///
+/// # Returns
+/// The number of lines output (to be used for next refresh)
+#[allow(clippy::too_many_arguments)]
+pub fn display_process_tree(
+    term: &Term,
+    rows: &[TreeRow],
+    retention_seconds: i64,
+    update_interval_secs: u64,
+    top_n: usize,
+    cpu_scale: CpuScale,
+    sort_description: &str,
+    last_output_lines: usize,
+) -> usize {
+    if last_output_lines > 0 {
+        let _ = term.move_cursor_up(last_output_lines);
+        let _ = term.clear_to_end_of_screen();
+    }
+
+    let terminal_width = terminal_width(term);
+    let layout = TreeLayout::fit(terminal_width);
+    let mut line_count = 0;
+
+    line_count += print_line("=== Process Shepherd - Process Tree ===", terminal_width);
+    line_count += print_line(&format_header_settings(retention_seconds, update_interval_secs, top_n, cpu_scale), terminal_width);
+    line_count += print_line(&format!("Timestamp: {} | Sorted by: {}", Utc::now().format("%Y-%m-%d %H:%M:%S"), sort_description), terminal_width);
+    line_count += print_line("", terminal_width);
+    line_count += print_line(&layout.format_header(cpu_scale), terminal_width);
+    line_count += print_line(&"=".repeat(layout.width()), terminal_width);
+
+    for row in rows {
+        line_count += print_line(&layout.format_row(row), terminal_width);
+    }
+
+    if rows.is_empty() {
+        line_count += print_line("No process data available yet. Collecting samples...", terminal_width);
+    }
+
+    line_count
+}
+
+/// Format the most recent CPU samples, oldest first, marking older samples that were left out
+///
+/// # Examples
+/// ```
+/// use process_shepherd::display::format_sample_history;
+///
+/// assert_eq!(format_sample_history(&[1.0, 2.5, 30.25], 5), "1.0 2.5 30.2");
+/// assert_eq!(format_sample_history(&[1.0, 2.5, 30.25], 2), "... 2.5 30.2");
+/// ```
+pub fn format_sample_history(samples: &[f32], max_samples: usize) -> String {
+    let skipped = samples.len().saturating_sub(max_samples);
+    let recent: Vec<String> = samples[skipped..].iter().map(|cpu| format!("{:.1}", cpu)).collect();
+
+    if skipped > 0 {
+        format!("... {}", recent.join(" "))
+    } else {
+        recent.join(" ")
+    }
+}
+
+/// Describe a single focused process over several lines
+///
+/// The details and the command line are shortened to fit lines of `width` characters.
+///
+/// # Returns
+/// A title line followed by indented CPU statistics, memory and details, command line and sample history
+pub fn format_focus_details(rank: usize, info: &ProcessInfo, width: usize) -> Vec<String> {
+    let trend_indicator = info.trend().map_or(" ", |trend| trend.indicator());
+    // The moving average keeps no samples to describe, only its running peak
+    let latest = info.cpu_samples.last().map_or(String::new(), |latest| format!(" | latest {:.2}%", latest));
+
+    let mut lines = vec![
+        format!("{:>LINE_NUMBER_WIDTH$} {} (PID {}) {}", rank, info.name, info.pid.as_u32(), trend_indicator),
+        format!(
+            "   CPU: avg {:.2}%{} | peak {:.2}% | samples {}",
+            info.cpu_percent, latest, info.peak_cpu_percent, info.sample_count
+        ),
+    ];
+    let has_samples = !info.cpu_samples.is_empty();
+    if has_samples {
+        lines.push(format!(
+            "   Spread: min {:.2}% | p50 {:.2}% | p95 {:.2}% | p99 {:.2}% | stddev {:.2}",
+            info.cpu_stats.min, info.cpu_stats.p50, info.cpu_stats.p95, info.cpu_stats.p99, info.cpu_stats.stddev
+        ));
+    }
+    if let Some(exited_at) = info.exited_at {
+        lines[0].push_str(&format!(" [exited at {}]", exited_at.format("%H:%M:%S")));
+    }
+    if let Some(cpu_seconds) = info.cpu_seconds {
+        lines[1].push_str(&format!(" | {:.2} CPU-seconds", cpu_seconds));
+    }
+    if let Some(children_cpu_seconds) = info.children_cpu_seconds {
+        lines[1].push_str(&format!(" (+{:.2} of reaped children)", children_cpu_seconds));
+    }
+
+    if info.extra_info.is_empty() {
+        lines.push(format!("   Memory: {}", format_memory(info.memory_bytes)));
+    } else {
+        let prefix = format!("   Memory: {} | Details: ", format_memory(info.memory_bytes));
+        let details_width = width.saturating_sub(prefix.chars().count());
+        lines.push(format!("{}{}", prefix, truncate_string(&info.extra_info, details_width)));
+    }
+
+    if !info.command_line.is_empty() {
+        let prefix = "   Command: ";
+        lines.push(format!("{}{}", prefix, truncate_string(&info.command_line, width.saturating_sub(prefix.len()))));
+    }
+
+    if has_samples {
+        lines.push(format!(
+            "   History %: {}",
+            format_sample_history(&info.cpu_samples, FOCUS_HISTORY_SAMPLES)
+        ));
+    }
+
+    lines
+}
+
+/// Display the focused processes with their per-sample detail
+///
+/// # Arguments
+/// * `term` - Terminal reference for cursor control
+/// * `results` - Focused processes as returned by [`crate::ProcessTracker::rank_processes`]
+/// * `retention_seconds` - Tracking window size in seconds
+/// * `update_interval_secs` - Time between two samples in seconds
+/// * `top_n` - Number of top processes to display
+/// * `cpu_scale` - What 100% means in the CPU percentages
+/// * `sort_description` - How the processes are sorted (e.g. "CPU % ▼")
+/// * `last_output_lines` - Number of lines from the previous output (for clearing)
+///
+/// # Returns
+/// The number of lines output (to be used for next refresh)
+#[allow(clippy::too_many_arguments)]
+pub fn display_focused_processes(
+    term: &Term,
+    results: &[ProcessInfo],
+    retention_seconds: i64,
+    update_interval_secs: u64,
+    top_n: usize,
+    cpu_scale: CpuScale,
+    sort_description: &str,
+    last_output_lines: usize,
+) -> usize {
+    if last_output_lines > 0 {
+        let _ = term.move_cursor_up(last_output_lines);
+        let _ = term.clear_to_end_of_screen();
+    }
+
+    let terminal_width = terminal_width(term);
+    // Lines are as wide as the process table would be
+    let width = ColumnLayout::fit(terminal_width, false, false, false, true).width();
+    let mut line_count = 0;
+
+    line_count += print_line("=== Process Shepherd - Focus Mode ===", terminal_width);
+    line_count += print_line(&format_header_settings(retention_seconds, update_interval_secs, top_n, cpu_scale), terminal_width);
+    line_count += print_line(&format!("Timestamp: {} | Sorted by: {}", Utc::now().format("%Y-%m-%d %H:%M:%S"), sort_description), terminal_width);
+    line_count += print_line(&"=".repeat(width), terminal_width);
+
+    for (i, info) in results.iter().take(top_n).enumerate() {
+        for line in format_focus_details(i + 1, info, width) {
+            line_count += print_line(&line, terminal_width);
+        }
+        line_count += print_line("", terminal_width);
     }
 
     if results.is_empty() {
-        println!("No process data available yet. Collecting samples...");
-        line_count += 1;
+        line_count += print_line("No focused process is running. Waiting for it to appear...", terminal_width);
     }
 
     line_count
@@ -246,6 +1252,433 @@ mod tests {
         assert_eq!(indicator, "↑");
     }
 
+    #[test]
+    fn test_format_header_settings_custom_values() {
+        assert_eq!(
+            format_header_settings(900, 15, 5, CpuScale::Machine),
+            "Tracking window: 900 seconds | Sampling every 15 seconds | Top 5 processes | 100% = all cores"
+        );
+        assert_eq!(
+            format_header_settings(10, 1, 50, CpuScale::Core),
+            "Tracking window: 10 seconds | Sampling every 1 seconds | Top 50 processes | 100% = one core"
+        );
+    }
+
+    #[test]
+    fn test_format_sample_history() {
+        assert_eq!(format_sample_history(&[], 5), "");
+        assert_eq!(format_sample_history(&[12.345], 5), "12.3");
+        assert_eq!(format_sample_history(&[1.0, 2.0, 3.0, 4.0], 3), "... 2.0 3.0 4.0");
+        assert_eq!(format_sample_history(&[1.0, 2.0], 0), "... ");
+    }
+
+    #[test]
+    fn test_format_focus_details() {
+        use sysinfo::Pid;
+
+        let info = ProcessInfo::new("postgres".to_string(), Pid::from_u32(100), 12.5, 1024 * 1024 * 64, String::new())
+            .with_previous_cpu_percent(Some(5.0))
+            .with_sample_count(3)
+            .with_peak_cpu_percent(20.0)
+            .with_command_line("postgres -D /var/lib/pgsql".to_string())
+            .with_cpu_samples(vec![7.5, 20.0, 10.0])
+            .with_cpu_stats(CpuStats { min: 7.5, p50: 10.0, p95: 20.0, p99: 20.0, max: 20.0, stddev: 5.4, last: 10.0 });
+
+        let lines = format_focus_details(1, &info, 94);
+
+        assert_eq!(lines[0], " 1 postgres (PID 100) ↑");
+        assert_eq!(lines[1], "   CPU: avg 12.50% | latest 10.00% | peak 20.00% | samples 3");
+        assert_eq!(lines[2], "   Spread: min 7.50% | p50 10.00% | p95 20.00% | p99 20.00% | stddev 5.40");
+        assert_eq!(lines[3], "   Memory: 64 MB");
+        assert_eq!(lines[4], "   Command: postgres -D /var/lib/pgsql");
+        assert_eq!(lines[5], "   History %: 7.5 20.0 10.0");
+    }
+
+    #[test]
+    fn test_format_focus_details_with_extra_info_and_no_command_line() {
+        use sysinfo::Pid;
+
+        let info = ProcessInfo::new("app".to_string(), Pid::from_u32(7), 0.0, 512, "Main Window".to_string())
+            .with_cpu_samples(vec![0.0]);
+        let lines = format_focus_details(2, &info, 94);
+
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[3], "   Memory: 512 B | Details: Main Window");
+        assert_eq!(lines[4], "   History %: 0.0");
+    }
+
+    #[test]
+    fn test_format_focus_details_without_samples() {
+        use sysinfo::Pid;
+
+        // The moving average keeps only a running peak
+        let info = ProcessInfo::new("app".to_string(), Pid::from_u32(7), 5.0, 512, String::new())
+            .with_sample_count(40)
+            .with_peak_cpu_percent(90.0);
+        let lines = format_focus_details(1, &info, 94);
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1], "   CPU: avg 5.00% | peak 90.00% | samples 40");
+        assert_eq!(lines[2], "   Memory: 512 B");
+    }
+
+    #[test]
+    fn test_format_focus_details_with_cpu_time() {
+        use sysinfo::Pid;
+
+        let info = ProcessInfo::new("make".to_string(), Pid::from_u32(9), 80.0, 512, String::new())
+            .with_cpu_seconds(Some(1.5))
+            .with_children_cpu_seconds(Some(46.5));
+        let lines = format_focus_details(1, &info, 94);
+
+        assert_eq!(
+            lines[1],
+            "   CPU: avg 80.00% | peak 0.00% | samples 0 | 1.50 CPU-seconds (+46.50 of reaped children)"
+        );
+    }
+
+    #[test]
+    fn test_format_tree_row_aligns_with_header() {
+        use crate::process_tree::{build_forest, flatten_forest};
+        use std::collections::HashSet;
+        use sysinfo::Pid;
+
+        let forest = build_forest(vec![
+            ProcessInfo::new("browser".to_string(), Pid::from_u32(10), 1.0, 1024, String::new()),
+            ProcessInfo::new("tab".to_string(), Pid::from_u32(11), 2.5, 1024, "news".to_string())
+                .with_parent(Some(Pid::from_u32(10))),
+        ]);
+        let rows = flatten_forest(&forest, &HashSet::new());
+
+        let layout = TreeLayout::fit(None);
+        let header = layout.format_header(CpuScale::Machine);
+        let parent = layout.format_row(&rows[0]);
+        let child = layout.format_row(&rows[1]);
+
+        assert!(parent.starts_with("browser "));
+        assert!(child.starts_with("└─ tab "));
+        assert!(parent.contains("  1.00   3.50"));
+        assert!(parent.contains("1 KB       2 KB"));
+        assert!(child.trim_end().ends_with("news"));
+        assert_eq!(header.find("PID"), parent.find("10 "));
+    }
+
+    #[test]
+    fn test_format_group_row_aligns_with_header() {
+        use sysinfo::Pid;
+
+        let group = ProcessGroup {
+            key: "chrome".to_string(),
+            cpu_percent: 12.5,
+            memory_bytes: 1024 * 1024 * 300,
+            pids: vec![Pid::from_u32(10), Pid::from_u32(11)],
+        };
+
+        let layout = GroupLayout::fit(None);
+        let header = layout.format_header(GroupBy::Name, CpuScale::Machine);
+        let row = layout.format_row(1, &group);
+
+        assert!(header.contains("Application"));
+        assert!(row.starts_with(" 1 chrome "));
+        assert!(row.contains("    2  12.50     300 MB 10, 11"));
+        assert_eq!(header.find("Count").map(|i| i + "Count".len()), row.find("    2 ").map(|i| i + 5));
+    }
+
+    #[test]
+    fn test_format_user_summary() {
+        use sysinfo::Pid;
+
+        let users = vec![
+            ProcessGroup {
+                key: "ci".to_string(),
+                cpu_percent: 80.0,
+                memory_bytes: 2048,
+                pids: vec![Pid::from_u32(1), Pid::from_u32(2)],
+            },
+            ProcessGroup {
+                key: "alice".to_string(),
+                cpu_percent: 0.5,
+                memory_bytes: 1024,
+                pids: vec![Pid::from_u32(3)],
+            },
+        ];
+
+        let lines = format_user_summary(&users, CpuScale::Machine, None);
+        assert_eq!(lines.len(), 5);
+        assert!(lines[0].is_empty());
+        assert!(lines[2].contains("User"));
+        assert!(lines[3].starts_with(" 1 ci "));
+        assert!(lines[4].starts_with(" 2 alice "));
+        assert_eq!(format_user_summary(&[], CpuScale::Machine, Some(80)).len(), 3);
+    }
+
+    #[test]
+    fn test_format_sparkline_shapes() {
+        // Steady, spiking and ramping processes
+        assert_eq!(format_sparkline(&[12.0; 6], 6), "██████");
+        assert_eq!(format_sparkline(&[1.0, 1.0, 90.0, 1.0, 1.0, 1.0], 6), "▁▁█▁▁▁");
+        assert_eq!(format_sparkline(&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0], 8), "▁▂▃▄▅▆▇█");
+    }
+
+    #[test]
+    fn test_format_sparkline_edge_cases() {
+        assert_eq!(format_sparkline(&[], 3), "   ");
+        assert_eq!(format_sparkline(&[0.0, 0.0], 2), "▁▁");
+        assert_eq!(format_sparkline(&[5.0, 10.0], 0), "");
+        // Only the most recent samples are scaled
+        assert_eq!(format_sparkline(&[100.0, 1.0, 2.0], 2), "▅█");
+        assert_eq!(format_sparkline(&[1.0; 40], SPARKLINE_MAX_WIDTH).chars().count(), SPARKLINE_MAX_WIDTH);
+    }
+
+    #[test]
+    fn test_sparkline_width_bounds() {
+        assert_eq!(sparkline_width(95 + SPARKLINE_MIN_WIDTH, 95), SPARKLINE_MIN_WIDTH);
+        assert_eq!(sparkline_width(95 + SPARKLINE_MIN_WIDTH - 1, 95), 0);
+        assert_eq!(sparkline_width(0, 95), 0);
+        assert_eq!(sparkline_width(usize::MAX, 95), SPARKLINE_MAX_WIDTH);
+    }
+
+    fn layout_row_info() -> ProcessInfo {
+        ProcessInfo::new("a-rather-long-process-name-here".to_string(), sysinfo::Pid::from_u32(4242), 12.5, 300 * 1024 * 1024, "/usr/bin/app --serve".to_string())
+            .with_previous_cpu_percent(Some(10.0))
+            .with_cpu_samples(vec![5.0, 10.0, 20.0])
+            .with_cpu_seconds(Some(3.5))
+            .with_children_cpu_seconds(Some(1.0))
+    }
+
+    #[test]
+    fn test_column_layout_without_terminal() {
+        let layout = ColumnLayout::fit(None, true, true, false, true);
+
+        assert_eq!(layout.name_width, PROCESS_NAME_DISPLAY_WIDTH);
+        assert!(layout.show_pid && layout.show_cpu_stats && layout.show_cpu_seconds && layout.show_memory);
+        assert!(!layout.show_children_cpu_seconds);
+        assert_eq!(layout.history_width, SPARKLINE_MAX_WIDTH);
+        assert_eq!(layout.details_width, EXTRA_INFO_WIDTH);
+    }
+
+    #[test]
+    fn test_column_layout_wide_terminal() {
+        let layout = ColumnLayout::fit(Some(300), true, true, true, true);
+
+        assert!(layout.show_cpu_stats && layout.show_cpu_seconds && layout.show_children_cpu_seconds);
+        assert_eq!(layout.history_width, SPARKLINE_MAX_WIDTH);
+        // Details take the rest, leaving the last column free
+        assert_eq!(layout.width(), 299);
+    }
+
+    #[test]
+    fn test_column_layout_without_history() {
+        // The details take the room of the history
+        let layout = ColumnLayout::fit(Some(300), true, true, true, false);
+        assert_eq!(layout.history_width, 0);
+        assert_eq!(layout.width(), 299);
+
+        assert_eq!(ColumnLayout::fit(None, true, true, true, false).history_width, 0);
+    }
+
+    #[test]
+    fn test_column_layout_drop_order() {
+        let full = ColumnLayout::fit(None, true, true, true, true);
+        let without_history = full.width() - full.history_width - 1 - (EXTRA_INFO_WIDTH - EXTRA_INFO_MIN_WIDTH);
+
+        let layout = ColumnLayout::fit(Some(without_history), true, true, true, true);
+        assert!(!layout.show_cpu_stats);
+        assert!(layout.show_children_cpu_seconds && layout.show_cpu_seconds);
+
+        let layout = ColumnLayout::fit(Some(80), true, true, true, true);
+        assert!(!layout.show_cpu_stats && !layout.show_children_cpu_seconds && !layout.show_cpu_seconds);
+        assert!(layout.show_memory && layout.show_pid);
+        assert_eq!(layout.width(), 79);
+
+        let layout = ColumnLayout::fit(Some(50), true, true, true, true);
+        assert!(!layout.show_memory);
+        assert_eq!(layout.details_width, 0);
+        assert!(!layout.show_pid);
+        assert_eq!(layout.name_width, PROCESS_NAME_DISPLAY_WIDTH);
+        // The room left is enough for a short history
+        assert_eq!(layout.history_width, SPARKLINE_MIN_WIDTH);
+        assert_eq!(layout.width(), 49);
+    }
+
+    #[test]
+    fn test_column_layout_shortens_names_last() {
+        let layout = ColumnLayout::fit(Some(30), true, true, true, true);
+
+        assert_eq!(layout.name_width, PROCESS_NAME_DISPLAY_WIDTH - 11);
+        assert_eq!(layout.width(), 29);
+        // Names are never shortened below the minimum, even if the line wraps
+        assert_eq!(ColumnLayout::fit(Some(5), false, false, false, true).name_width, PROCESS_NAME_MIN_WIDTH);
+    }
+
+    #[test]
+    fn test_column_layout_rows_match_width() {
+        let info = layout_row_info();
+        for terminal_width in [None, Some(40), Some(60), Some(80), Some(120), Some(200)] {
+            let layout = ColumnLayout::fit(terminal_width, true, true, true, true);
+            let row = layout.format_row(1, &info);
+            let header = layout.format_header(CpuScale::Machine);
+
+            assert_eq!(row.chars().count(), layout.width(), "row at {:?}", terminal_width);
+            assert_eq!(header.chars().count(), layout.width(), "header at {:?}", terminal_width);
+            assert!(row.contains('↑'));
+        }
+    }
+
+    #[test]
+    fn test_group_layout_fits_terminal() {
+        use sysinfo::Pid;
+
+        let group = ProcessGroup {
+            key: "chrome".to_string(),
+            cpu_percent: 12.5,
+            memory_bytes: 1024,
+            pids: (1..40).map(Pid::from_u32).collect(),
+        };
+        for terminal_width in [None, Some(30), Some(50), Some(60), Some(80), Some(200)] {
+            let layout = GroupLayout::fit(terminal_width);
+            let row = layout.format_row(1, &group);
+            let header = layout.format_header(GroupBy::Name, CpuScale::Machine);
+
+            assert_eq!(row.chars().count(), layout.width(), "row at {:?}", terminal_width);
+            assert_eq!(header.chars().count(), layout.width(), "header at {:?}", terminal_width);
+            if let Some(terminal_width) = terminal_width {
+                assert!(layout.width() < terminal_width.max(PROCESS_NAME_MIN_WIDTH + 12));
+            }
+        }
+
+        let wide = GroupLayout::fit(Some(200));
+        assert_eq!(wide.width(), 199);
+        assert!(wide.pids_width > EXTRA_INFO_WIDTH);
+
+        let narrow = GroupLayout::fit(Some(60));
+        assert!(!narrow.show_memory && narrow.show_count);
+        assert!(narrow.pids_width >= EXTRA_INFO_MIN_WIDTH);
+        assert_eq!(narrow.width(), 59);
+
+        let layout = GroupLayout::fit(Some(50));
+        assert_eq!(layout.pids_width, 0);
+        assert!(layout.show_count);
+    }
+
+    #[test]
+    fn test_tree_layout_drop_order() {
+        let wide = TreeLayout::fit(Some(200));
+        assert!(wide.show_pid && wide.show_memory && wide.show_subtree_memory);
+        assert_eq!(wide.width(), 199);
+
+        let layout = TreeLayout::fit(Some(80));
+        assert!(!layout.show_memory && layout.show_subtree_memory);
+        assert!(layout.details_width >= EXTRA_INFO_MIN_WIDTH);
+        assert_eq!(layout.width(), 79);
+
+        let layout = TreeLayout::fit(Some(55));
+        assert!(!layout.show_subtree_memory);
+        assert_eq!(layout.details_width, 0);
+        assert!(layout.show_pid);
+
+        let layout = TreeLayout::fit(Some(30));
+        assert!(!layout.show_pid);
+        assert_eq!(layout.name_width, PROCESS_NAME_DISPLAY_WIDTH - 12);
+        assert_eq!(layout.width(), 29);
+    }
+
+    #[test]
+    fn test_tree_layout_rows_match_width() {
+        use crate::process_tree::{build_forest, flatten_forest};
+        use std::collections::HashSet;
+        use sysinfo::Pid;
+
+        let forest = build_forest(vec![
+            ProcessInfo::new("browser".to_string(), Pid::from_u32(10), 1.0, 1024, "x".repeat(300)),
+            ProcessInfo::new("tab".to_string(), Pid::from_u32(11), 2.5, 1024, String::new())
+                .with_parent(Some(Pid::from_u32(10))),
+        ]);
+        let rows = flatten_forest(&forest, &HashSet::new());
+        for terminal_width in [None, Some(40), Some(60), Some(80), Some(120), Some(200)] {
+            let layout = TreeLayout::fit(terminal_width);
+            let header = layout.format_header(CpuScale::Machine);
+
+            assert_eq!(header.chars().count(), layout.width(), "header at {:?}", terminal_width);
+            for row in &rows {
+                assert_eq!(layout.format_row(row).chars().count(), layout.width(), "row at {:?}", terminal_width);
+            }
+        }
+    }
+
+    #[test]
+    fn test_format_focus_details_fits_width() {
+        use sysinfo::Pid;
+
+        let info = ProcessInfo::new("java".to_string(), Pid::from_u32(7), 1.0, 512, "y".repeat(200))
+            .with_command_line("z".repeat(200))
+            .with_cpu_samples(vec![1.0]);
+        let lines = format_focus_details(1, &info, 60);
+
+        assert_eq!(lines[3].chars().count(), 60);
+        assert_eq!(lines[4].chars().count(), 60);
+        assert!(lines[4].ends_with("zz..."));
+    }
+
+    #[test]
+    fn test_print_line_counts_wrapped_rows() {
+        assert_eq!(print_line("", Some(80)), 1);
+        assert_eq!(print_line(&"x".repeat(80), Some(80)), 1);
+        assert_eq!(print_line(&"x".repeat(81), Some(80)), 2);
+        assert_eq!(print_line(&"x".repeat(200), None), 1);
+        // Styling does not take up room
+        assert_eq!(print_line(&console::style("x".repeat(80)).dim().force_styling(true).to_string(), Some(80)), 1);
+    }
+
+    #[test]
+    fn test_format_system_summary() {
+        use crate::system_summary::SystemStats;
+
+        let summary = SystemSummary {
+            stats: SystemStats {
+                cpu_percent: 37.5,
+                core_percents: vec![90.0, 10.0, 50.0, 0.0, 0.0],
+                load_average: Some([1.5, 0.75, 0.25]),
+                memory_used_bytes: 512 * 1024 * 1024,
+                memory_total_bytes: 2 * 1024 * 1024 * 1024,
+                swap_used_bytes: 0,
+                swap_total_bytes: 1024 * 1024 * 1024,
+            },
+            process_count: 312,
+            listed_count: 20,
+            listed_cpu_percent: 30.0,
+        };
+
+        let lines = format_system_summary(&summary, 94);
+        assert_eq!(
+            lines[0],
+            "CPU: 37.5% busy | Load: 1.50 0.75 0.25 | Memory: 512 MB / 2.0 GB | Swap: 0 B / 1.0 GB | Processes: 312"
+        );
+        assert_eq!(lines[1], "CPU of the machine: listed 20 processes 30.0% | everything else 7.5%");
+        // Four cores fit on a line
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[2],
+            "  0 [||||||||| ]  90%    1 [|         ]  10%    2 [|||||     ]  50%    3 [          ]   0%"
+        );
+        assert!(lines[2].len() <= 94);
+        assert_eq!(lines[3], "  4 [          ]   0%");
+    }
+
+    #[test]
+    fn test_format_system_summary_without_load_average() {
+        let summary = SystemSummary {
+            stats: Default::default(),
+            process_count: 3,
+            listed_count: 0,
+            listed_cpu_percent: 0.0,
+        };
+
+        let lines = format_system_summary(&summary, 94);
+        assert!(!lines[0].contains("Load"));
+        assert_eq!(lines.len(), 2);
+    }
+
     #[test]
     fn test_format_memory_bytes() {
         assert_eq!(format_memory(512), "512 B");
@@ -0,0 +1,781 @@
+use crate::cpu_calculator::{CpuScale, CpuStats};
+use crate::cpu_time::CpuTimeTotals;
+use crate::grouping::{GroupBy, ProcessGroup};
+use crate::ProcessInfo;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Column names of the CSV recording, in order
+///
+/// `raw_cpu_usage` is the latest sample as reported by the OS, before normalizing to the `cpu_scale`
+pub const CSV_HEADER: &str = "timestamp,pid,name,raw_cpu_usage,avg_cpu_percent,memory_bytes,details,cpu_scale";
+
+/// Column names appended to [`CSV_HEADER`] when recording the CPU distribution, in order
+pub const CSV_CPU_STATS_COLUMNS: &str =
+    "cpu_min_percent,cpu_max_percent,cpu_p50_percent,cpu_p95_percent,cpu_p99_percent,cpu_stddev,cpu_last_percent";
+
+/// One ranked process in machine-readable output
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProcessRecord {
+    pub rank: usize,
+    pub name: String,
+    pub pid: u32,
+    pub cpu_percent: f32,
+    pub memory_bytes: u64,
+    pub extra_info: String,
+    /// "up", "down", "stable", or `null` if there is no previous measurement
+    pub trend: Option<&'static str>,
+    /// User running the process, empty if unknown
+    pub user: String,
+    /// Distribution of the CPU percentages in the window, only present with `--cpu-stats`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_stats: Option<CpuStatsRecord>,
+    /// CPU-seconds consumed in the window, only present with `--accounting`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_seconds: Option<f64>,
+    /// CPU-seconds of children reaped in the window, only present with `--reaped-children`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub children_cpu_seconds: Option<f64>,
+    /// When the process exited, only present for exited processes kept with `--keep-exited`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exited_at: Option<DateTime<Utc>>,
+}
+
+impl ProcessRecord {
+    pub fn from_info(rank: usize, info: &ProcessInfo) -> Self {
+        Self {
+            rank,
+            name: info.name.clone(),
+            pid: info.pid.as_u32(),
+            cpu_percent: info.cpu_percent,
+            memory_bytes: info.memory_bytes,
+            extra_info: info.extra_info.clone(),
+            trend: info.trend().map(|trend| trend.as_str()),
+            user: info.user.clone(),
+            cpu_stats: None,
+            cpu_seconds: info.cpu_seconds,
+            children_cpu_seconds: info.children_cpu_seconds,
+            exited_at: info.exited_at,
+        }
+    }
+}
+
+/// Distribution of the CPU percentages of one process in machine-readable output
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct CpuStatsRecord {
+    pub min: f32,
+    pub max: f32,
+    pub p50: f32,
+    pub p95: f32,
+    pub p99: f32,
+    pub stddev: f32,
+    pub last: f32,
+}
+
+impl CpuStatsRecord {
+    pub fn from_stats(stats: &CpuStats) -> Self {
+        Self {
+            min: stats.min,
+            max: stats.max,
+            p50: stats.p50,
+            p95: stats.p95,
+            p99: stats.p99,
+            stddev: stats.stddev,
+            last: stats.last,
+        }
+    }
+}
+
+/// The ranked process list of one tick in machine-readable output
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TickReport {
+    pub timestamp: DateTime<Utc>,
+    pub window_seconds: i64,
+    pub interval_seconds: u64,
+    /// What 100% means in `cpu_percent`, as accepted by `--cpu-scale`
+    pub cpu_scale: &'static str,
+    pub processes: Vec<ProcessRecord>,
+    /// Per-user totals over every tracked process, only present with `--user-summary`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub users: Option<Vec<UserRecord>>,
+    /// CPU time consumed in the window, only present with `--accounting`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_time: Option<CpuTimeRecord>,
+}
+
+impl TickReport {
+    /// Build a report from processes already sorted by rank
+    pub fn new(
+        timestamp: DateTime<Utc>,
+        window_seconds: i64,
+        interval_seconds: u64,
+        cpu_scale: CpuScale,
+        results: &[ProcessInfo],
+    ) -> Self {
+        Self {
+            timestamp,
+            window_seconds,
+            interval_seconds,
+            cpu_scale: cpu_scale.as_str(),
+            processes: results
+                .iter()
+                .enumerate()
+                .map(|(i, info)| ProcessRecord::from_info(i + 1, info))
+                .collect(),
+            users: None,
+            cpu_time: None,
+        }
+    }
+
+    /// Attach the CPU distribution of each process, from the same results the report was built from
+    pub fn with_cpu_stats(mut self, results: &[ProcessInfo]) -> Self {
+        for (record, info) in self.processes.iter_mut().zip(results) {
+            record.cpu_stats = Some(CpuStatsRecord::from_stats(&info.cpu_stats));
+        }
+        self
+    }
+
+    /// Attach per-user totals (as returned by [`crate::ProcessTracker::user_summary`])
+    pub fn with_users(mut self, users: &[ProcessGroup]) -> Self {
+        self.users = Some(users.iter().map(UserRecord::from_group).collect());
+        self
+    }
+
+    /// Attach the CPU time totals (as returned by [`crate::ProcessTracker::cpu_time_totals`])
+    pub fn with_cpu_time(mut self, totals: &CpuTimeTotals) -> Self {
+        self.cpu_time = Some(CpuTimeRecord::from_totals(totals));
+        self
+    }
+}
+
+/// CPU-seconds consumed in the window by all processes and by the whole machine
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct CpuTimeRecord {
+    pub process_seconds: f64,
+    /// `null` if the machine's busy time is unknown
+    pub busy_seconds: Option<f64>,
+}
+
+impl CpuTimeRecord {
+    pub fn from_totals(totals: &CpuTimeTotals) -> Self {
+        Self {
+            process_seconds: totals.process_secs,
+            busy_seconds: totals.busy_secs,
+        }
+    }
+}
+
+/// Total CPU, memory and process count of one user in machine-readable output
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UserRecord {
+    pub user: String,
+    pub processes: usize,
+    pub cpu_percent: f32,
+    pub memory_bytes: u64,
+}
+
+impl UserRecord {
+    pub fn from_group(group: &ProcessGroup) -> Self {
+        Self {
+            user: group.key.clone(),
+            processes: group.instance_count(),
+            cpu_percent: group.cpu_percent,
+            memory_bytes: group.memory_bytes,
+        }
+    }
+}
+
+/// One ranked application group in machine-readable output
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GroupRecord {
+    pub rank: usize,
+    pub group: String,
+    pub cpu_percent: f32,
+    pub memory_bytes: u64,
+    pub instances: usize,
+    pub pids: Vec<u32>,
+}
+
+impl GroupRecord {
+    pub fn from_group(rank: usize, group: &ProcessGroup) -> Self {
+        Self {
+            rank,
+            group: group.key.clone(),
+            cpu_percent: group.cpu_percent,
+            memory_bytes: group.memory_bytes,
+            instances: group.instance_count(),
+            pids: group.pids.iter().map(|pid| pid.as_u32()).collect(),
+        }
+    }
+}
+
+/// The ranked application groups of one tick in machine-readable output
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GroupReport {
+    pub timestamp: DateTime<Utc>,
+    pub window_seconds: i64,
+    pub interval_seconds: u64,
+    /// What 100% means in `cpu_percent`, as accepted by `--cpu-scale`
+    pub cpu_scale: &'static str,
+    /// Grouping mode as accepted by `--group-by`
+    pub group_by: &'static str,
+    pub groups: Vec<GroupRecord>,
+}
+
+impl GroupReport {
+    /// Build a report from groups already sorted by rank
+    pub fn new(
+        timestamp: DateTime<Utc>,
+        window_seconds: i64,
+        interval_seconds: u64,
+        cpu_scale: CpuScale,
+        group_by: GroupBy,
+        groups: &[ProcessGroup],
+    ) -> Self {
+        Self {
+            timestamp,
+            window_seconds,
+            interval_seconds,
+            cpu_scale: cpu_scale.as_str(),
+            group_by: group_by.as_str(),
+            groups: groups
+                .iter()
+                .enumerate()
+                .map(|(i, group)| GroupRecord::from_group(i + 1, group))
+                .collect(),
+        }
+    }
+}
+
+/// A single process record with the tick context, emitted as one NDJSON line
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct NdjsonRecord<'a> {
+    timestamp: DateTime<Utc>,
+    window_seconds: i64,
+    interval_seconds: u64,
+    cpu_scale: &'static str,
+    #[serde(flatten)]
+    process: &'a ProcessRecord,
+}
+
+/// A single group record with the tick context, emitted as one NDJSON line
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct GroupNdjsonRecord<'a> {
+    timestamp: DateTime<Utc>,
+    window_seconds: i64,
+    interval_seconds: u64,
+    cpu_scale: &'static str,
+    group_by: &'static str,
+    #[serde(flatten)]
+    group: &'a GroupRecord,
+}
+
+/// Write the report as one pretty-printed JSON document followed by a newline
+///
+/// Consecutive ticks form a stream of JSON documents that `jq` reads one by one.
+/// Works for both [`TickReport`] and [`GroupReport`].
+pub fn write_json<W: Write, T: Serialize>(writer: &mut W, report: &T) -> io::Result<()> {
+    serde_json::to_writer_pretty(&mut *writer, report)?;
+    writeln!(writer)?;
+    writer.flush()
+}
+
+/// Write the report as newline-delimited JSON, one line per process
+pub fn write_ndjson<W: Write>(writer: &mut W, report: &TickReport) -> io::Result<()> {
+    for process in &report.processes {
+        let record = NdjsonRecord {
+            timestamp: report.timestamp,
+            window_seconds: report.window_seconds,
+            interval_seconds: report.interval_seconds,
+            cpu_scale: report.cpu_scale,
+            process,
+        };
+        serde_json::to_writer(&mut *writer, &record)?;
+        writeln!(writer)?;
+    }
+    writer.flush()
+}
+
+/// Write the group report as newline-delimited JSON, one line per group
+pub fn write_group_ndjson<W: Write>(writer: &mut W, report: &GroupReport) -> io::Result<()> {
+    for group in &report.groups {
+        let record = GroupNdjsonRecord {
+            timestamp: report.timestamp,
+            window_seconds: report.window_seconds,
+            interval_seconds: report.interval_seconds,
+            cpu_scale: report.cpu_scale,
+            group_by: report.group_by,
+            group,
+        };
+        serde_json::to_writer(&mut *writer, &record)?;
+        writeln!(writer)?;
+    }
+    writer.flush()
+}
+
+/// Quote a CSV field if it contains a separator, quote or line break
+///
+/// # Examples
+/// ```
+/// use process_shepherd::export::csv_escape;
+///
+/// assert_eq!(csv_escape("plain"), "plain");
+/// assert_eq!(csv_escape("a, b"), "\"a, b\"");
+/// assert_eq!(csv_escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+/// ```
+pub fn csv_escape(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// Write one CSV row per process for a single tick
+pub fn write_csv_rows<W: Write>(
+    writer: &mut W,
+    timestamp: DateTime<Utc>,
+    results: &[ProcessInfo],
+    cpu_scale: CpuScale,
+) -> io::Result<()> {
+    write_rows(writer, timestamp, results, cpu_scale, false)
+}
+
+/// Write one CSV row per process for a single tick, followed by the [`CSV_CPU_STATS_COLUMNS`]
+pub fn write_csv_rows_with_cpu_stats<W: Write>(
+    writer: &mut W,
+    timestamp: DateTime<Utc>,
+    results: &[ProcessInfo],
+    cpu_scale: CpuScale,
+) -> io::Result<()> {
+    write_rows(writer, timestamp, results, cpu_scale, true)
+}
+
+fn write_rows<W: Write>(
+    writer: &mut W,
+    timestamp: DateTime<Utc>,
+    results: &[ProcessInfo],
+    cpu_scale: CpuScale,
+    cpu_stats: bool,
+) -> io::Result<()> {
+    let timestamp = timestamp.format("%Y-%m-%d %H:%M:%S").to_string();
+    for info in results {
+        write!(
+            writer,
+            "{},{},{},{:.2},{:.2},{},{},{}",
+            timestamp,
+            info.pid.as_u32(),
+            csv_escape(&info.name),
+            info.latest_cpu_usage,
+            info.cpu_percent,
+            info.memory_bytes,
+            csv_escape(&info.extra_info),
+            cpu_scale,
+        )?;
+        if cpu_stats {
+            let stats = &info.cpu_stats;
+            write!(
+                writer,
+                ",{:.2},{:.2},{:.2},{:.2},{:.2},{:.2},{:.2}",
+                stats.min, stats.max, stats.p50, stats.p95, stats.p99, stats.stddev, stats.last
+            )?;
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// Appends ranked processes to a CSV file, one row per process per tick
+pub struct CsvRecorder {
+    writer: BufWriter<File>,
+    cpu_scale: CpuScale,
+    cpu_stats: bool,
+}
+
+impl CsvRecorder {
+    /// Open the file for appending, writing the header if the file is new or empty
+    ///
+    /// Fails if the file already has a different header, so rows are never
+    /// appended under columns they don't match.
+    pub fn open(path: &Path, cpu_scale: CpuScale) -> io::Result<Self> {
+        Self::open_with_columns(path, cpu_scale, false)
+    }
+
+    /// Like [`CsvRecorder::open`], but also record the [`CSV_CPU_STATS_COLUMNS`]
+    pub fn open_with_cpu_stats(path: &Path, cpu_scale: CpuScale) -> io::Result<Self> {
+        Self::open_with_columns(path, cpu_scale, true)
+    }
+
+    fn open_with_columns(path: &Path, cpu_scale: CpuScale, cpu_stats: bool) -> io::Result<Self> {
+        let header = if cpu_stats {
+            format!("{},{}", CSV_HEADER, CSV_CPU_STATS_COLUMNS)
+        } else {
+            CSV_HEADER.to_string()
+        };
+
+        let file = OpenOptions::new().create(true).read(true).append(true).open(path)?;
+        if file.metadata()?.len() == 0 {
+            let mut writer = BufWriter::new(file);
+            writeln!(writer, "{}", header)?;
+            writer.flush()?;
+            return Ok(Self { writer, cpu_scale, cpu_stats });
+        }
+
+        let mut reader = BufReader::new(file);
+        let mut existing_header = String::new();
+        reader.read_line(&mut existing_header)?;
+        let existing_header = existing_header.trim_end_matches(['\r', '\n']);
+        if existing_header != header {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("existing header '{}' does not match the expected '{}'", existing_header, header),
+            ));
+        }
+        Ok(Self { writer: BufWriter::new(reader.into_inner()), cpu_scale, cpu_stats })
+    }
+
+    /// Append the rows of one tick and flush them to disk
+    pub fn record(&mut self, timestamp: DateTime<Utc>, results: &[ProcessInfo]) -> io::Result<()> {
+        write_rows(&mut self.writer, timestamp, results, self.cpu_scale, self.cpu_stats)?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::Value;
+    use std::fs;
+    use sysinfo::Pid;
+
+    fn sample_report() -> TickReport {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let results = vec![
+            ProcessInfo::new("busy".to_string(), Pid::from_u32(10), 42.5, 2048, "--work".to_string())
+                .with_previous_cpu_percent(Some(30.0)),
+            ProcessInfo::new("idle".to_string(), Pid::from_u32(20), 1.5, 1024, String::new()),
+        ];
+        TickReport::new(timestamp, 60, 2, CpuScale::Machine, &results)
+    }
+
+    #[test]
+    fn test_tick_report_assigns_ranks_and_trends() {
+        let report = sample_report();
+
+        assert_eq!(report.processes.len(), 2);
+        assert_eq!(report.processes[0].rank, 1);
+        assert_eq!(report.processes[0].trend, Some("up"));
+        assert_eq!(report.processes[1].rank, 2);
+        assert_eq!(report.processes[1].trend, None);
+    }
+
+    #[test]
+    fn test_write_json_produces_single_document() {
+        let mut output = Vec::new();
+        write_json(&mut output, &sample_report()).unwrap();
+
+        let text = String::from_utf8(output).unwrap();
+        let value: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(value["timestamp"], "2024-01-01T12:00:00Z");
+        assert_eq!(value["window_seconds"], 60);
+        assert_eq!(value["interval_seconds"], 2);
+        assert_eq!(value["processes"][0]["name"], "busy");
+        assert_eq!(value["processes"][0]["pid"], 10);
+        assert_eq!(value["processes"][0]["cpu_percent"], 42.5);
+        assert_eq!(value["processes"][0]["memory_bytes"], 2048);
+        assert_eq!(value["processes"][0]["extra_info"], "--work");
+        assert_eq!(value["processes"][0]["trend"], "up");
+        assert_eq!(value["processes"][1]["trend"], Value::Null);
+    }
+
+    #[test]
+    fn test_tick_report_users_only_when_attached() {
+        use crate::grouping::group_processes;
+
+        let mut output = Vec::new();
+        write_json(&mut output, &sample_report()).unwrap();
+        let value: Value = serde_json::from_slice(&output).unwrap();
+        assert!(value.get("users").is_none());
+        assert_eq!(value["processes"][0]["user"], "");
+
+        let results = vec![
+            ProcessInfo::new("cc1".to_string(), Pid::from_u32(1), 40.0, 100, String::new()).with_user("ci".to_string()),
+            ProcessInfo::new("ld".to_string(), Pid::from_u32(2), 10.0, 50, String::new()).with_user("ci".to_string()),
+        ];
+        let report = sample_report().with_users(&group_processes(&results, GroupBy::User));
+
+        let mut output = Vec::new();
+        write_json(&mut output, &report).unwrap();
+        let value: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(value["users"][0]["user"], "ci");
+        assert_eq!(value["users"][0]["processes"], 2);
+        assert_eq!(value["users"][0]["cpu_percent"], 50.0);
+        assert_eq!(value["users"][0]["memory_bytes"], 150);
+    }
+
+    #[test]
+    fn test_write_ndjson_one_line_per_process() {
+        let mut output = Vec::new();
+        write_ndjson(&mut output, &sample_report()).unwrap();
+
+        let text = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+
+        let first: Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(first["timestamp"], "2024-01-01T12:00:00Z");
+        assert_eq!(first["window_seconds"], 60);
+        assert_eq!(first["cpu_scale"], "machine");
+        assert_eq!(first["rank"], 1);
+        assert_eq!(first["name"], "busy");
+
+        let second: Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(second["rank"], 2);
+        assert_eq!(second["pid"], 20);
+    }
+
+    #[test]
+    fn test_write_ndjson_empty_report_writes_nothing() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let report = TickReport::new(timestamp, 60, 2, CpuScale::Machine, &[]);
+        let mut output = Vec::new();
+        write_ndjson(&mut output, &report).unwrap();
+
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_output_contains_no_terminal_control_codes() {
+        let mut output = Vec::new();
+        write_json(&mut output, &sample_report()).unwrap();
+        write_ndjson(&mut output, &sample_report()).unwrap();
+
+        assert!(!output.contains(&0x1b));
+    }
+
+    fn sample_group_report() -> GroupReport {
+        use crate::grouping::group_processes;
+
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let results = vec![
+            ProcessInfo::new("chrome".to_string(), Pid::from_u32(11), 10.0, 100, String::new()),
+            ProcessInfo::new("chrome".to_string(), Pid::from_u32(10), 5.0, 200, String::new()),
+        ];
+        GroupReport::new(timestamp, 60, 2, CpuScale::Machine, GroupBy::Name, &group_processes(&results, GroupBy::Name))
+    }
+
+    #[test]
+    fn test_write_group_json() {
+        let mut output = Vec::new();
+        write_json(&mut output, &sample_group_report()).unwrap();
+
+        let value: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(value["group_by"], "name");
+        assert_eq!(value["groups"][0]["rank"], 1);
+        assert_eq!(value["groups"][0]["group"], "chrome");
+        assert_eq!(value["groups"][0]["cpu_percent"], 15.0);
+        assert_eq!(value["groups"][0]["memory_bytes"], 300);
+        assert_eq!(value["groups"][0]["instances"], 2);
+        assert_eq!(value["groups"][0]["pids"], serde_json::json!([10, 11]));
+    }
+
+    #[test]
+    fn test_write_group_ndjson_carries_tick_context() {
+        let mut output = Vec::new();
+        write_group_ndjson(&mut output, &sample_group_report()).unwrap();
+
+        let text = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 1);
+
+        let line: Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(line["timestamp"], "2024-01-01T12:00:00Z");
+        assert_eq!(line["group_by"], "name");
+        assert_eq!(line["cpu_scale"], "machine");
+        assert_eq!(line["group"], "chrome");
+        assert_eq!(line["instances"], 2);
+    }
+
+    #[test]
+    fn test_csv_escape_plain_field_is_unchanged() {
+        assert_eq!(csv_escape("firefox"), "firefox");
+        assert_eq!(csv_escape(""), "");
+    }
+
+    #[test]
+    fn test_csv_escape_quotes_special_characters() {
+        assert_eq!(csv_escape("Inbox, 3 unread"), "\"Inbox, 3 unread\"");
+        assert_eq!(csv_escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_escape("line1\nline2"), "\"line1\nline2\"");
+    }
+
+    #[test]
+    fn test_write_csv_rows_formats_columns() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let results = vec![
+            ProcessInfo::new("cargo".to_string(), Pid::from_u32(42), 12.5, 4096, "build, --release".to_string())
+                .with_latest_cpu_usage(150.0),
+        ];
+        let mut output = Vec::new();
+        write_csv_rows(&mut output, timestamp, &results, CpuScale::Machine).unwrap();
+
+        // The latest sample stays raw, so it is named apart from the normalized average
+        assert!(CSV_HEADER.starts_with("timestamp,pid,name,raw_cpu_usage,avg_cpu_percent,"));
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "2024-01-01 12:00:00,42,cargo,150.00,12.50,4096,\"build, --release\",machine\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_recorder_writes_header_once_and_appends() {
+        let path = std::env::temp_dir().join(format!("process-shepherd-test-{}.csv", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let results = vec![ProcessInfo::new("a".to_string(), Pid::from_u32(1), 5.0, 10, String::new())];
+
+        CsvRecorder::open(&path, CpuScale::Machine).unwrap().record(timestamp, &results).unwrap();
+        CsvRecorder::open(&path, CpuScale::Machine).unwrap().record(timestamp, &results).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(lines[1], lines[2]);
+    }
+
+    fn spiky_process() -> ProcessInfo {
+        ProcessInfo::new("spiky".to_string(), Pid::from_u32(7), 10.0, 512, String::new()).with_cpu_stats(CpuStats {
+            min: 0.0,
+            max: 100.0,
+            p50: 0.0,
+            p95: 100.0,
+            p99: 100.0,
+            stddev: 30.0,
+            last: 0.0,
+        })
+    }
+
+    #[test]
+    fn test_tick_report_cpu_stats_only_when_attached() {
+        let mut output = Vec::new();
+        write_json(&mut output, &sample_report()).unwrap();
+        let value: Value = serde_json::from_slice(&output).unwrap();
+        assert!(value["processes"][0].get("cpu_stats").is_none());
+
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let results = vec![spiky_process()];
+        let report = TickReport::new(timestamp, 60, 2, CpuScale::Machine, &results).with_cpu_stats(&results);
+
+        let mut output = Vec::new();
+        write_ndjson(&mut output, &report).unwrap();
+        let line: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(line["cpu_percent"], 10.0);
+        assert_eq!(line["cpu_stats"]["p50"], 0.0);
+        assert_eq!(line["cpu_stats"]["p95"], 100.0);
+        assert_eq!(line["cpu_stats"]["max"], 100.0);
+        assert_eq!(line["cpu_stats"]["stddev"], 30.0);
+    }
+
+    #[test]
+    fn test_process_record_exited_at() {
+        let exited_at = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 4).unwrap();
+        let results = vec![
+            ProcessInfo::new("gcc".to_string(), Pid::from_u32(7), 90.0, 0, String::new()).with_exited_at(Some(exited_at)),
+        ];
+        let report = TickReport::new(exited_at, 60, 2, CpuScale::Machine, &results);
+
+        let mut output = Vec::new();
+        write_ndjson(&mut output, &report).unwrap();
+        let line: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(line["exited_at"], "2024-01-01T12:00:04Z");
+
+        let mut output = Vec::new();
+        write_json(&mut output, &sample_report()).unwrap();
+        let value: Value = serde_json::from_slice(&output).unwrap();
+        assert!(value["processes"][0].get("exited_at").is_none());
+    }
+
+    #[test]
+    fn test_tick_report_cpu_time_only_with_accounting() {
+        let mut output = Vec::new();
+        write_json(&mut output, &sample_report()).unwrap();
+        let value: Value = serde_json::from_slice(&output).unwrap();
+        assert!(value.get("cpu_time").is_none());
+        assert!(value["processes"][0].get("cpu_seconds").is_none());
+
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let results = vec![
+            ProcessInfo::new("cargo".to_string(), Pid::from_u32(42), 25.0, 4096, String::new())
+                .with_cpu_seconds(Some(30.0))
+                .with_children_cpu_seconds(Some(12.0)),
+        ];
+        let totals = CpuTimeTotals { process_secs: 30.0, busy_secs: Some(32.5) };
+        let report = TickReport::new(timestamp, 60, 2, CpuScale::Machine, &results).with_cpu_time(&totals);
+
+        let mut output = Vec::new();
+        write_json(&mut output, &report).unwrap();
+        let value: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(value["processes"][0]["cpu_seconds"], 30.0);
+        assert_eq!(value["processes"][0]["children_cpu_seconds"], 12.0);
+        assert_eq!(value["cpu_time"]["process_seconds"], 30.0);
+        assert_eq!(value["cpu_time"]["busy_seconds"], 32.5);
+    }
+
+    #[test]
+    fn test_write_csv_rows_with_cpu_stats() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let mut output = Vec::new();
+        write_csv_rows_with_cpu_stats(&mut output, timestamp, &[spiky_process()], CpuScale::Core).unwrap();
+
+        let text = String::from_utf8(output).unwrap();
+        assert_eq!(
+            text,
+            "2024-01-01 12:00:00,7,spiky,0.00,10.00,512,,core,0.00,100.00,0.00,100.00,100.00,30.00,0.00\n"
+        );
+        let header = format!("{},{}", CSV_HEADER, CSV_CPU_STATS_COLUMNS);
+        assert_eq!(text.trim_end().split(',').count(), header.split(',').count());
+    }
+
+    #[test]
+    fn test_csv_recorder_with_cpu_stats_header() {
+        let path = std::env::temp_dir().join(format!("process-shepherd-stats-test-{}.csv", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+
+        CsvRecorder::open_with_cpu_stats(&path, CpuScale::Machine).unwrap().record(timestamp, &[spiky_process()]).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines[0], format!("{},{}", CSV_HEADER, CSV_CPU_STATS_COLUMNS));
+        assert!(lines[1].ends_with(",30.00,0.00"));
+    }
+
+    #[test]
+    fn test_csv_recorder_rejects_mismatched_header() {
+        let path = std::env::temp_dir().join(format!("process-shepherd-header-test-{}.csv", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let results = vec![ProcessInfo::new("a".to_string(), Pid::from_u32(1), 5.0, 10, String::new())];
+
+        CsvRecorder::open(&path, CpuScale::Machine).unwrap().record(timestamp, &results).unwrap();
+        let with_stats = CsvRecorder::open_with_cpu_stats(&path, CpuScale::Machine);
+
+        // A capture from before the cpu_scale column was added
+        fs::write(&path, "timestamp,pid,name,cpu_usage,avg_cpu_percent,memory_bytes,details\n").unwrap();
+        let old_capture = CsvRecorder::open(&path, CpuScale::Machine);
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(with_stats.err().map(|e| e.kind()), Some(io::ErrorKind::InvalidData));
+        assert_eq!(old_capture.err().map(|e| e.kind()), Some(io::ErrorKind::InvalidData));
+        assert_eq!(contents.lines().count(), 1);
+    }
+}
@@ -0,0 +1,285 @@
+use regex::{Regex, RegexBuilder};
+use std::fmt;
+use std::str::FromStr;
+
+/// Prefix marking a pattern as a regular expression instead of a glob
+pub const REGEX_PREFIX: &str = "re:";
+
+/// A glob or regular expression matched against process name, command line and details
+///
+/// - `re:<regex>` is a regular expression that may match anywhere in a field
+/// - A glob with wildcards (`*`, `?`, `[...]`) must match a whole field, ignoring case
+/// - Plain text without wildcards matches anywhere in a field, ignoring case
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    source: String,
+    regex: Regex,
+}
+
+impl Pattern {
+    /// Parse a pattern from its command-line form
+    ///
+    /// # Examples
+    /// ```
+    /// use process_shepherd::filter::Pattern;
+    ///
+    /// let glob = Pattern::parse("chrome*").unwrap();
+    /// assert!(glob.is_match("Chrome.exe"));
+    /// assert!(!glob.is_match("google-chrome"));
+    ///
+    /// let text = Pattern::parse("webpack").unwrap();
+    /// assert!(text.is_match("node ./node_modules/.bin/webpack --watch"));
+    ///
+    /// let regex = Pattern::parse(r"re:^rustc?$").unwrap();
+    /// assert!(regex.is_match("rustc"));
+    /// assert!(!regex.is_match("rustdoc"));
+    /// ```
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        if pattern.is_empty() {
+            return Err("Pattern must not be empty".to_string());
+        }
+
+        let regex = if let Some(expression) = pattern.strip_prefix(REGEX_PREFIX) {
+            Regex::new(expression).map_err(|e| format!("Invalid regex '{}': {}", expression, e))?
+        } else if pattern.contains(['*', '?', '[']) {
+            RegexBuilder::new(&glob_to_regex(pattern))
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| format!("Invalid glob '{}': {}", pattern, e))?
+        } else {
+            RegexBuilder::new(&regex::escape(pattern))
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))?
+        };
+
+        Ok(Self {
+            source: pattern.to_string(),
+            regex,
+        })
+    }
+
+    /// The pattern as it was given
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
+
+    /// Whether the pattern matches any of the fields
+    pub fn matches_any(&self, fields: &[&str]) -> bool {
+        fields.iter().any(|field| self.is_match(field))
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Pattern::parse(s)
+    }
+}
+
+/// Translate a glob into an anchored regular expression
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    let mut in_class = false;
+
+    for c in glob.chars() {
+        match c {
+            '[' if !in_class => {
+                in_class = true;
+                regex.push('[');
+            }
+            ']' if in_class => {
+                in_class = false;
+                regex.push(']');
+            }
+            '!' if in_class && regex.ends_with('[') => regex.push('^'),
+            '\\' if in_class => regex.push_str("\\\\"),
+            _ if in_class => regex.push(c),
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            _ => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+
+    regex.push('$');
+    regex
+}
+
+/// Include/exclude rules deciding which processes are ranked
+///
+/// A process is kept when it matches at least one include pattern (or there are none),
+/// matches no exclude pattern, and matches the interactive query if one is set.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProcessFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    query: Option<Pattern>,
+}
+
+impl ProcessFilter {
+    pub fn new(include: Vec<Pattern>, exclude: Vec<Pattern>) -> Self {
+        Self {
+            include,
+            exclude,
+            query: None,
+        }
+    }
+
+    pub fn include(&self) -> &[Pattern] {
+        &self.include
+    }
+
+    pub fn exclude(&self) -> &[Pattern] {
+        &self.exclude
+    }
+
+    /// Interactive search entered in the live view, applied on top of include/exclude
+    pub fn query(&self) -> Option<&Pattern> {
+        self.query.as_ref()
+    }
+
+    pub fn set_query(&mut self, query: Option<Pattern>) {
+        self.query = query;
+    }
+
+    /// Whether the filter lets every process through
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty() && self.query.is_none()
+    }
+
+    /// Decide whether a process with these fields is kept
+    ///
+    /// # Arguments
+    /// * `name` - Process name
+    /// * `command_line` - Full command line
+    /// * `details` - Details shown in the table (e.g. window titles)
+    pub fn matches(&self, name: &str, command_line: &str, details: &str) -> bool {
+        let fields = [name, command_line, details];
+
+        let included = self.include.is_empty() || self.include.iter().any(|p| p.matches_any(&fields));
+        let excluded = self.exclude.iter().any(|p| p.matches_any(&fields));
+        let queried = match &self.query {
+            Some(query) => query.matches_any(&fields),
+            None => true,
+        };
+
+        included && !excluded && queried
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(s: &str) -> Pattern {
+        Pattern::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_plain_text_matches_substring_ignoring_case() {
+        let p = pattern("Firefox");
+        assert!(p.is_match("firefox.exe"));
+        assert!(p.is_match("/usr/lib/firefox/firefox -contentproc"));
+        assert!(!p.is_match("chrome"));
+    }
+
+    #[test]
+    fn test_glob_matches_whole_field() {
+        let p = pattern("*.exe");
+        assert!(p.is_match("notepad.EXE"));
+        assert!(!p.is_match("notepad.exe.bak"));
+
+        let p = pattern("rust?");
+        assert!(p.is_match("rustc"));
+        assert!(!p.is_match("rustdoc"));
+    }
+
+    #[test]
+    fn test_glob_character_classes() {
+        let p = pattern("node[0-9]");
+        assert!(p.is_match("node7"));
+        assert!(!p.is_match("nodex"));
+
+        let p = pattern("node[!0-9]");
+        assert!(p.is_match("nodex"));
+        assert!(!p.is_match("node7"));
+    }
+
+    #[test]
+    fn test_glob_escapes_regex_characters() {
+        let p = pattern("a.b+c*");
+        assert!(p.is_match("a.b+c-d"));
+        assert!(!p.is_match("axb+c"));
+    }
+
+    #[test]
+    fn test_regex_pattern() {
+        let p = pattern(r"re:--port[= ]\d+");
+        assert!(p.is_match("server --port=8080"));
+        assert!(!p.is_match("server --port=x"));
+        assert_eq!(p.as_str(), r"re:--port[= ]\d+");
+    }
+
+    #[test]
+    fn test_invalid_patterns_are_rejected() {
+        assert!(Pattern::parse("").is_err());
+        assert!(Pattern::parse("re:(unclosed").is_err());
+        assert!(Pattern::parse("[unclosed").is_err());
+    }
+
+    #[test]
+    fn test_empty_filter_keeps_everything() {
+        let filter = ProcessFilter::default();
+        assert!(filter.is_empty());
+        assert!(filter.matches("anything", "", ""));
+    }
+
+    #[test]
+    fn test_include_matches_name_command_line_or_details() {
+        let filter = ProcessFilter::new(vec![pattern("webpack")], Vec::new());
+
+        assert!(filter.matches("webpack", "", ""));
+        assert!(filter.matches("node", "node webpack --watch", ""));
+        assert!(filter.matches("node", "", "webpack dev server"));
+        assert!(!filter.matches("node", "node server.js", "api"));
+    }
+
+    #[test]
+    fn test_exclude_wins_over_include() {
+        let filter = ProcessFilter::new(vec![pattern("chrome*")], vec![pattern("*--type=gpu*")]);
+
+        assert!(filter.matches("chrome", "chrome --type=renderer", ""));
+        assert!(!filter.matches("chrome", "chrome --type=gpu-process", ""));
+        assert!(!filter.matches("firefox", "", ""));
+    }
+
+    #[test]
+    fn test_query_narrows_filter() {
+        let mut filter = ProcessFilter::new(Vec::new(), vec![pattern("idle")]);
+        filter.set_query(Some(pattern("cargo")));
+
+        assert!(!filter.is_empty());
+        assert!(filter.matches("cargo", "", ""));
+        assert!(!filter.matches("rustc", "", ""));
+
+        filter.set_query(None);
+        assert!(filter.matches("rustc", "", ""));
+    }
+}
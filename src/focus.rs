@@ -0,0 +1,167 @@
+use crate::filter::Pattern;
+use crate::process_source::ProcessSnapshot;
+use std::collections::{HashMap, HashSet};
+use sysinfo::Pid;
+
+/// Processes selected with `--pid` / `--name` for focus mode
+///
+/// When a focus is set the tracker keeps history only for matching processes
+/// (and optionally their descendants) and reports them regardless of the CPU threshold.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FocusSet {
+    pids: Vec<Pid>,
+    names: Vec<Pattern>,
+    include_descendants: bool,
+}
+
+impl FocusSet {
+    pub fn new(pids: Vec<Pid>, names: Vec<Pattern>) -> Self {
+        Self {
+            pids,
+            names,
+            include_descendants: false,
+        }
+    }
+
+    /// Also focus on every process descending from a matching process
+    pub fn with_descendants(mut self, include_descendants: bool) -> Self {
+        self.include_descendants = include_descendants;
+        self
+    }
+
+    pub fn pids(&self) -> &[Pid] {
+        &self.pids
+    }
+
+    pub fn names(&self) -> &[Pattern] {
+        &self.names
+    }
+
+    pub fn include_descendants(&self) -> bool {
+        self.include_descendants
+    }
+
+    /// Whether no focus is set, i.e. every process is tracked
+    pub fn is_empty(&self) -> bool {
+        self.pids.is_empty() && self.names.is_empty()
+    }
+
+    /// Whether a single process matches by PID or name, ignoring descendants
+    pub fn matches(&self, snapshot: &ProcessSnapshot) -> bool {
+        self.pids.contains(&snapshot.pid) || self.names.iter().any(|p| p.is_match(&snapshot.name))
+    }
+
+    /// PIDs of the processes in focus among the given snapshots
+    ///
+    /// # Examples
+    /// ```
+    /// use process_shepherd::filter::Pattern;
+    /// use process_shepherd::focus::FocusSet;
+    /// use process_shepherd::process_source::ProcessSnapshot;
+    /// use sysinfo::Pid;
+    ///
+    /// let processes = vec![
+    ///     ProcessSnapshot::new(Pid::from_u32(10), "nginx", 1.0),
+    ///     ProcessSnapshot::new(Pid::from_u32(11), "worker", 1.0).with_parent(Pid::from_u32(10)),
+    ///     ProcessSnapshot::new(Pid::from_u32(20), "bash", 1.0),
+    /// ];
+    ///
+    /// let focus = FocusSet::new(Vec::new(), vec![Pattern::parse("nginx").unwrap()]).with_descendants(true);
+    /// let focused = focus.resolve(&processes);
+    /// assert!(focused.contains(&Pid::from_u32(10)));
+    /// assert!(focused.contains(&Pid::from_u32(11)));
+    /// assert!(!focused.contains(&Pid::from_u32(20)));
+    /// ```
+    pub fn resolve(&self, processes: &[ProcessSnapshot]) -> HashSet<Pid> {
+        let mut focused: HashSet<Pid> = processes
+            .iter()
+            .filter(|snapshot| self.matches(snapshot))
+            .map(|snapshot| snapshot.pid)
+            .collect();
+
+        if self.include_descendants {
+            let mut children: HashMap<Pid, Vec<Pid>> = HashMap::new();
+            for snapshot in processes {
+                if let Some(parent) = snapshot.parent {
+                    children.entry(parent).or_default().push(snapshot.pid);
+                }
+            }
+
+            let mut pending: Vec<Pid> = focused.iter().copied().collect();
+            while let Some(pid) = pending.pop() {
+                for child in children.get(&pid).into_iter().flatten() {
+                    // The set guards against cycles from PID reuse
+                    if focused.insert(*child) {
+                        pending.push(*child);
+                    }
+                }
+            }
+        }
+
+        focused
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(pid: u32, name: &str, parent: Option<u32>) -> ProcessSnapshot {
+        let snapshot = ProcessSnapshot::new(Pid::from_u32(pid), name, 0.0);
+        match parent {
+            Some(parent) => snapshot.with_parent(Pid::from_u32(parent)),
+            None => snapshot,
+        }
+    }
+
+    fn pids(focused: &HashSet<Pid>) -> Vec<u32> {
+        let mut pids: Vec<u32> = focused.iter().map(|pid| pid.as_u32()).collect();
+        pids.sort();
+        pids
+    }
+
+    fn tree() -> Vec<ProcessSnapshot> {
+        vec![
+            process(1, "init", None),
+            process(100, "postgres", Some(1)),
+            process(101, "postgres", Some(100)),
+            process(102, "autovacuum", Some(100)),
+            process(103, "psql", Some(102)),
+            process(200, "bash", Some(1)),
+        ]
+    }
+
+    #[test]
+    fn test_empty_focus() {
+        let focus = FocusSet::default();
+        assert!(focus.is_empty());
+        assert!(focus.resolve(&tree()).is_empty());
+    }
+
+    #[test]
+    fn test_focus_by_pid() {
+        let focus = FocusSet::new(vec![Pid::from_u32(200), Pid::from_u32(999)], Vec::new());
+        assert!(!focus.is_empty());
+        assert_eq!(pids(&focus.resolve(&tree())), vec![200]);
+    }
+
+    #[test]
+    fn test_focus_by_name_matches_every_instance() {
+        let focus = FocusSet::new(Vec::new(), vec![Pattern::parse("postgres").unwrap()]);
+        assert_eq!(pids(&focus.resolve(&tree())), vec![100, 101]);
+    }
+
+    #[test]
+    fn test_focus_with_descendants_walks_whole_subtree() {
+        let focus = FocusSet::new(vec![Pid::from_u32(100)], Vec::new()).with_descendants(true);
+        assert!(focus.include_descendants());
+        assert_eq!(pids(&focus.resolve(&tree())), vec![100, 101, 102, 103]);
+    }
+
+    #[test]
+    fn test_focus_descendants_survive_parent_cycles() {
+        let processes = vec![process(1, "a", Some(2)), process(2, "b", Some(1))];
+        let focus = FocusSet::new(vec![Pid::from_u32(1)], Vec::new()).with_descendants(true);
+        assert_eq!(pids(&focus.resolve(&processes)), vec![1, 2]);
+    }
+}
@@ -0,0 +1,281 @@
+use crate::sorting::{SortKey, SortOrder};
+use crate::ProcessInfo;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use sysinfo::Pid;
+
+/// Group key used when the user running a process is unknown
+pub const UNKNOWN_USER: &str = "(unknown)";
+
+/// Property used to aggregate processes into application groups
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    /// Process name, e.g. all `chrome` processes
+    Name,
+    /// Executable path, falling back to the name when the path is unknown
+    Exe,
+    /// User running the process
+    User,
+    /// Window owned by the process or its parent, falling back to the name for processes without windows
+    ParentWindow,
+}
+
+impl GroupBy {
+    /// All grouping modes, in the order they are listed in help texts
+    pub const ALL: [GroupBy; 4] = [GroupBy::Name, GroupBy::Exe, GroupBy::User, GroupBy::ParentWindow];
+
+    /// Name accepted on the command line
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GroupBy::Name => "name",
+            GroupBy::Exe => "exe",
+            GroupBy::User => "user",
+            GroupBy::ParentWindow => "parent-window",
+        }
+    }
+
+    /// Human-readable column label
+    pub fn label(self) -> &'static str {
+        match self {
+            GroupBy::Name => "Application",
+            GroupBy::Exe => "Executable",
+            GroupBy::User => "User",
+            GroupBy::ParentWindow => "Window",
+        }
+    }
+
+    /// Group key of a single process
+    pub fn key_of(self, info: &ProcessInfo) -> String {
+        let key = match self {
+            GroupBy::Name => &info.name,
+            GroupBy::Exe if info.exe.is_empty() => &info.name,
+            GroupBy::Exe => &info.exe,
+            GroupBy::User if info.user.is_empty() => UNKNOWN_USER,
+            GroupBy::User => &info.user,
+            GroupBy::ParentWindow if info.window_title.is_empty() => &info.name,
+            GroupBy::ParentWindow => &info.window_title,
+        };
+        key.to_string()
+    }
+}
+
+impl fmt::Display for GroupBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for GroupBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        GroupBy::ALL
+            .into_iter()
+            .find(|group_by| group_by.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names: Vec<&str> = GroupBy::ALL.iter().map(|group_by| group_by.as_str()).collect();
+                format!("'{}' is not a valid grouping (expected one of: {})", s, names.join(", "))
+            })
+    }
+}
+
+/// Processes sharing a group key, with their combined CPU and memory
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessGroup {
+    pub key: String,
+    /// Sum of the members' average CPU percentages over the tracking window
+    pub cpu_percent: f32,
+    /// Sum of the members' memory in bytes
+    pub memory_bytes: u64,
+    /// Member PIDs in ascending order
+    pub pids: Vec<Pid>,
+}
+
+impl ProcessGroup {
+    /// Number of processes in the group
+    pub fn instance_count(&self) -> usize {
+        self.pids.len()
+    }
+}
+
+/// Aggregate processes into groups, ordered by group key
+///
+/// # Examples
+/// ```
+/// use process_shepherd::grouping::{group_processes, GroupBy};
+/// use process_shepherd::ProcessInfo;
+/// use sysinfo::Pid;
+///
+/// let processes = vec![
+///     ProcessInfo::new("chrome".to_string(), Pid::from_u32(10), 5.0, 100, String::new()),
+///     ProcessInfo::new("chrome".to_string(), Pid::from_u32(11), 2.5, 50, String::new()),
+///     ProcessInfo::new("rustc".to_string(), Pid::from_u32(20), 40.0, 300, String::new()),
+/// ];
+///
+/// let groups = group_processes(&processes, GroupBy::Name);
+/// assert_eq!(groups[0].key, "chrome");
+/// assert_eq!(groups[0].cpu_percent, 7.5);
+/// assert_eq!(groups[0].memory_bytes, 150);
+/// assert_eq!(groups[0].instance_count(), 2);
+/// ```
+pub fn group_processes(processes: &[ProcessInfo], group_by: GroupBy) -> Vec<ProcessGroup> {
+    let mut groups: BTreeMap<String, ProcessGroup> = BTreeMap::new();
+
+    for info in processes {
+        let key = group_by.key_of(info);
+        let group = groups.entry(key.clone()).or_insert_with(|| ProcessGroup {
+            key,
+            cpu_percent: 0.0,
+            memory_bytes: 0,
+            pids: Vec::new(),
+        });
+        group.cpu_percent += info.cpu_percent;
+        group.memory_bytes += info.memory_bytes;
+        group.pids.push(info.pid);
+    }
+
+    groups
+        .into_values()
+        .map(|mut group| {
+            group.pids.sort();
+            group
+        })
+        .collect()
+}
+
+/// Compare two groups by the key in ascending order
+///
+/// Groups have no trend or peak, so those keys rank by CPU percentage.
+fn compare_by_key(a: &ProcessGroup, b: &ProcessGroup, key: SortKey) -> Ordering {
+    match key {
+        SortKey::Memory => a.memory_bytes.cmp(&b.memory_bytes),
+        SortKey::Pid => a.pids.first().cmp(&b.pids.first()),
+        SortKey::Name => a.key.to_lowercase().cmp(&b.key.to_lowercase()),
+        SortKey::Cpu | SortKey::Trend | SortKey::PeakCpu => {
+            a.cpu_percent.partial_cmp(&b.cpu_percent).unwrap_or(Ordering::Equal)
+        }
+    }
+}
+
+/// Sort groups in place by the key and order
+///
+/// Ties are broken by CPU percentage (descending) and then group key.
+pub fn sort_groups(groups: &mut [ProcessGroup], key: SortKey, order: SortOrder) {
+    groups.sort_by(|a, b| {
+        let primary = match order {
+            SortOrder::Ascending => compare_by_key(a, b, key),
+            SortOrder::Descending => compare_by_key(b, a, key),
+        };
+        primary
+            .then_with(|| b.cpu_percent.partial_cmp(&a.cpu_percent).unwrap_or(Ordering::Equal))
+            .then_with(|| a.key.cmp(&b.key))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(pid: u32, name: &str, cpu: f32, memory: u64) -> ProcessInfo {
+        ProcessInfo::new(name.to_string(), Pid::from_u32(pid), cpu, memory, String::new())
+    }
+
+    fn keys(groups: &[ProcessGroup]) -> Vec<&str> {
+        groups.iter().map(|g| g.key.as_str()).collect()
+    }
+
+    #[test]
+    fn test_group_by_parse_and_display() {
+        assert_eq!("name".parse::<GroupBy>(), Ok(GroupBy::Name));
+        assert_eq!("EXE".parse::<GroupBy>(), Ok(GroupBy::Exe));
+        assert_eq!("parent-window".parse::<GroupBy>(), Ok(GroupBy::ParentWindow));
+        assert_eq!(GroupBy::User.to_string(), "user");
+
+        let err = "host".parse::<GroupBy>().unwrap_err();
+        assert!(err.contains("name, exe, user, parent-window"));
+    }
+
+    #[test]
+    fn test_group_by_name_sums_members() {
+        let processes = vec![
+            info(12, "node", 10.0, 100),
+            info(3, "node", 15.0, 200),
+            info(7, "code", 1.0, 1000),
+        ];
+
+        let groups = group_processes(&processes, GroupBy::Name);
+        assert_eq!(keys(&groups), vec!["code", "node"]);
+        assert_eq!(groups[1].cpu_percent, 25.0);
+        assert_eq!(groups[1].memory_bytes, 300);
+        assert_eq!(groups[1].pids, vec![Pid::from_u32(3), Pid::from_u32(12)]);
+    }
+
+    #[test]
+    fn test_group_by_exe_falls_back_to_name() {
+        let processes = vec![
+            info(1, "python3", 1.0, 0).with_exe("/usr/bin/python3.12".to_string()),
+            info(2, "python", 1.0, 0).with_exe("/usr/bin/python3.12".to_string()),
+            info(3, "kthreadd", 1.0, 0),
+        ];
+
+        let groups = group_processes(&processes, GroupBy::Exe);
+        assert_eq!(keys(&groups), vec!["/usr/bin/python3.12", "kthreadd"]);
+        assert_eq!(groups[0].instance_count(), 2);
+    }
+
+    #[test]
+    fn test_group_by_user_marks_unknown() {
+        let processes = vec![
+            info(1, "a", 1.0, 0).with_user("alice".to_string()),
+            info(2, "b", 1.0, 0),
+            info(3, "c", 1.0, 0).with_user("alice".to_string()),
+        ];
+
+        let groups = group_processes(&processes, GroupBy::User);
+        assert_eq!(keys(&groups), vec![UNKNOWN_USER, "alice"]);
+        assert_eq!(groups[1].instance_count(), 2);
+    }
+
+    #[test]
+    fn test_group_by_parent_window() {
+        let processes = vec![
+            info(1, "firefox", 5.0, 0).with_window_title("News - Firefox".to_string()),
+            info(2, "firefox-tab", 20.0, 0).with_window_title("News - Firefox".to_string()),
+            info(3, "sshd", 1.0, 0),
+        ];
+
+        let groups = group_processes(&processes, GroupBy::ParentWindow);
+        assert_eq!(keys(&groups), vec!["News - Firefox", "sshd"]);
+        assert_eq!(groups[0].cpu_percent, 25.0);
+    }
+
+    #[test]
+    fn test_sort_groups() {
+        let processes = vec![
+            info(1, "a", 5.0, 300),
+            info(2, "b", 20.0, 100),
+            info(3, "b", 20.0, 100),
+            info(4, "c", 30.0, 50),
+        ];
+        let mut groups = group_processes(&processes, GroupBy::Name);
+
+        sort_groups(&mut groups, SortKey::Cpu, SortOrder::Descending);
+        assert_eq!(keys(&groups), vec!["b", "c", "a"]);
+
+        sort_groups(&mut groups, SortKey::Memory, SortOrder::Descending);
+        assert_eq!(keys(&groups), vec!["a", "b", "c"]);
+
+        sort_groups(&mut groups, SortKey::Name, SortOrder::Descending);
+        assert_eq!(keys(&groups), vec!["c", "b", "a"]);
+
+        sort_groups(&mut groups, SortKey::Trend, SortOrder::Ascending);
+        assert_eq!(keys(&groups), vec!["a", "c", "b"]);
+    }
+
+    #[test]
+    fn test_group_empty() {
+        assert!(group_processes(&[], GroupBy::Name).is_empty());
+    }
+}
@@ -1,7 +1,22 @@
 pub mod cpu_calculator;
+pub mod cpu_time;
 pub mod display;
+pub mod export;
+pub mod filter;
+pub mod focus;
+pub mod grouping;
+pub mod process_details;
 pub mod process_info;
+pub mod process_source;
+pub mod process_tracker;
+pub mod process_tree;
+pub mod prometheus;
+pub mod sorting;
+pub mod system_summary;
+pub mod tui;
 pub mod window_info;
 
 // Re-export for convenience
 pub use process_info::ProcessInfo;
+pub use process_source::{ProcessKey, ProcessSource, ProcessSnapshot, ScriptedSource, SysinfoSource};
+pub use process_tracker::{ProcessTracker, ProcessTrackerBuilder};
@@ -1,24 +1,142 @@
-mod cpu_calculator;
-mod display;
-mod window_info;
-
-use chrono::Utc;
-use clap::Parser;
-use console::Term;
-use cpu_calculator::{calculate_average_cpu_percentage, CpuSample};
-use process_shepherd::ProcessInfo;
-use std::collections::HashMap;
+use clap::error::ErrorKind;
+use clap::{ArgGroup, CommandFactory, Parser, ValueEnum};
+use process_shepherd::cpu_calculator::{AveragingMode, CpuScale};
+use process_shepherd::export::{self, CsvRecorder, GroupReport, TickReport};
+use process_shepherd::filter::{Pattern, ProcessFilter};
+use process_shepherd::focus::FocusSet;
+use process_shepherd::grouping::GroupBy;
+use process_shepherd::sorting::{SortKey, SortOrder};
+use process_shepherd::prometheus::{self, HostMetrics, MetricsServer};
+use process_shepherd::process_tracker::{
+    DEFAULT_EWMA_HALF_LIFE_SECS, DEFAULT_RETENTION_SECS, DEFAULT_TOP_N, DEFAULT_UPDATE_INTERVAL_SECS,
+};
+use process_shepherd::{tui, ProcessInfo, ProcessTracker};
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process;
 use std::thread;
 use std::time::Duration;
-use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+use sysinfo::Pid;
+
+/// How each tick's ranking is written to stdout
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    /// Human-readable table that refreshes in place
+    Table,
+    /// One pretty-printed JSON document per tick
+    Json,
+    /// One JSON line per process per tick
+    Ndjson,
+    /// Full-screen interactive view
+    Tui,
+    /// Nothing on stdout (headless, e.g. with --prometheus-listen or --csv)
+    None,
+}
 
 /// Process Shepherd - Track CPU usage per process
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
+#[command(group(ArgGroup::new("focus").multiple(true)))]
 struct Args {
     /// Minimum CPU percentage threshold to display processes (default: 1.0)
     #[arg(long = "cpu-threshold", default_value_t = 1.0, value_parser = validate_threshold)]
     cpu_threshold: f32,
+
+    /// Seconds between two CPU samples (default: 2)
+    #[arg(long = "interval", default_value_t = DEFAULT_UPDATE_INTERVAL_SECS, value_parser = validate_interval)]
+    interval: u64,
+
+    /// Length of the tracking window in seconds, a multiple of the interval (default: 60)
+    #[arg(long = "window", default_value_t = DEFAULT_RETENTION_SECS, value_parser = validate_window)]
+    window: i64,
+
+    /// What 100% means in CPU percentages: machine (all cores) or core (one core) (default: machine)
+    #[arg(long = "cpu-scale", value_name = "SCALE", default_value_t = CpuScale::Machine)]
+    cpu_scale: CpuScale,
+
+    /// How samples are averaged: mean, time-weighted by the time between samples, or ewma (default: mean)
+    #[arg(long = "averaging", value_name = "MODE", default_value_t = AveragingMode::Mean)]
+    averaging: AveragingMode,
+
+    /// Seconds after which a sample has lost half its weight with --averaging ewma (default: 10)
+    #[arg(long = "half-life", value_name = "SECONDS", value_parser = validate_half_life)]
+    half_life: Option<f32>,
+
+    /// Rank by the CPU-seconds each process consumed in the window, counted from its cumulative
+    /// CPU time instead of sampled (Linux only; elsewhere the sampled average is kept)
+    #[arg(long = "accounting")]
+    accounting: bool,
+
+    /// With --accounting, add the CPU time of exited children a process waited for to its own,
+    /// so short-lived children that never appear in a sample (compilers, test runners) are counted.
+    /// A child that was itself sampled before it exited counts twice: in its own row and in its parent's
+    #[arg(long = "reaped-children", requires = "accounting")]
+    reaped_children: bool,
+
+    /// Keep processes that exited in the ranking, marked with their exit time, until their samples leave the window
+    #[arg(long = "keep-exited")]
+    keep_exited: bool,
+
+    /// Number of top processes to display (default: 20)
+    #[arg(long = "top", default_value_t = DEFAULT_TOP_N, value_parser = validate_top)]
+    top: usize,
+
+    /// Output format (default: table)
+    #[arg(long = "output", value_enum, default_value_t = OutputFormat::Table)]
+    output: OutputFormat,
+
+    /// Sort column: cpu, memory, pid, name, trend or peak (default: cpu)
+    #[arg(long = "sort", default_value_t = SortKey::Cpu)]
+    sort: SortKey,
+
+    /// Sort direction: asc or desc (default: desc for measurements, asc for pid and name)
+    #[arg(long = "sort-order")]
+    sort_order: Option<SortOrder>,
+
+    /// Only show processes whose name, command line or details match (glob, or regex with "re:"; repeatable)
+    #[arg(long = "include", value_name = "PATTERN")]
+    include: Vec<Pattern>,
+
+    /// Hide processes whose name, command line or details match (glob, or regex with "re:"; repeatable)
+    #[arg(long = "exclude", value_name = "PATTERN")]
+    exclude: Vec<Pattern>,
+
+    /// Focus on this process ID: track only focused processes and show them regardless of the threshold (repeatable)
+    #[arg(long = "pid", value_name = "PID", group = "focus")]
+    pid: Vec<u32>,
+
+    /// Focus on processes whose name matches (glob, or regex with "re:"; repeatable)
+    #[arg(long = "name", value_name = "PATTERN", group = "focus")]
+    name: Vec<Pattern>,
+
+    /// Also focus on all descendants of the processes selected with --pid or --name
+    #[arg(long = "with-children", requires = "focus")]
+    with_children: bool,
+
+    /// Nest child processes under their parents with subtree CPU and memory totals (table and tui output)
+    #[arg(long = "tree")]
+    tree: bool,
+
+    /// Aggregate processes into application groups: name, exe, user or parent-window (table, json and ndjson output)
+    #[arg(long = "group-by", value_name = "MODE", conflicts_with = "tree")]
+    group_by: Option<GroupBy>,
+
+    /// Show total CPU, memory and process count per user alongside the ranking (table, tui, json and Prometheus)
+    #[arg(long = "user-summary")]
+    user_summary: bool,
+
+    /// Add CPU distribution statistics (P50, P95, P99, max, standard deviation) to the table, tui and every export
+    #[arg(long = "cpu-stats")]
+    cpu_stats: bool,
+
+    /// Append one row per displayed process per tick to this CSV file
+    #[arg(long = "csv", value_name = "FILE")]
+    csv: Option<PathBuf>,
+
+    /// Serve Prometheus metrics on http://<ADDR>/metrics (e.g. 127.0.0.1:9184)
+    #[arg(long = "prometheus-listen", value_name = "ADDR")]
+    prometheus_listen: Option<SocketAddr>,
 }
 
 /// Validate that the CPU threshold is within a reasonable range
@@ -34,279 +152,375 @@ fn validate_threshold(s: &str) -> Result<f32, String> {
     }
 }
 
+/// Validate that the sampling interval is a positive number of seconds
+fn validate_interval(s: &str) -> Result<u64, String> {
+    let value: u64 = s.parse().map_err(|_| format!("'{}' is not a valid number of seconds", s))?;
 
-/// Tracks CPU usage history for processes
-struct ProcessTracker {
-    system: System,
-    history: HashMap<Pid, Vec<CpuSample>>,
-    retention_seconds: i64,
-    last_output_lines: usize,
-    previous_cpu_burn: HashMap<Pid, f32>,
-    cpu_count: f32,
-    window_titles_cache: HashMap<u32, Vec<String>>,
-    cpu_threshold: f32,
+    if value == 0 {
+        Err("Interval must be at least 1 second".to_string())
+    } else if value > 3600 {
+        Err("Interval must be <= 3600 seconds (one hour)".to_string())
+    } else {
+        Ok(value)
+    }
 }
 
-impl ProcessTracker {
-    fn new(retention_seconds: i64, cpu_threshold: f32) -> Self {
-        let system = System::new_all();
-        // Get CPU count - System::new_all() already initializes CPU info
-        // Use max(1) to prevent division by zero
-        let cpu_count = (system.cpus().len() as f32).max(1.0);
+/// Validate that the tracking window is a positive number of seconds
+fn validate_window(s: &str) -> Result<i64, String> {
+    let value: i64 = s.parse().map_err(|_| format!("'{}' is not a valid number of seconds", s))?;
 
-        Self {
-            system,
-            history: HashMap::new(),
-            retention_seconds,
-            last_output_lines: 0,
-            previous_cpu_burn: HashMap::new(),
-            cpu_count,
-            window_titles_cache: HashMap::new(),
-            cpu_threshold,
-        }
+    if value <= 0 {
+        Err("Window must be at least 1 second".to_string())
+    } else if value > 86_400 {
+        Err("Window must be <= 86400 seconds (one day)".to_string())
+    } else {
+        Ok(value)
     }
+}
 
-    /// Update process information and record CPU usage samples
-    fn update(&mut self) {
-        self.system.refresh_processes_specifics(
-            ProcessesToUpdate::All,
-            true,
-            ProcessRefreshKind::new().with_cpu().with_memory(),
-        );
-
-        // Refresh window titles cache once per update (only on Windows)
-        self.window_titles_cache = window_info::get_all_window_titles();
+/// Validate that the EWMA half-life is a positive number of seconds
+fn validate_half_life(s: &str) -> Result<f32, String> {
+    let value: f32 = s.parse().map_err(|_| format!("'{}' is not a valid number of seconds", s))?;
 
-        let now = Utc::now();
+    if value.is_nan() || value <= 0.0 {
+        Err("Half-life must be greater than 0 seconds".to_string())
+    } else if value > 86_400.0 {
+        Err("Half-life must be <= 86400 seconds (one day)".to_string())
+    } else {
+        Ok(value)
+    }
+}
 
-        // Collect current CPU usage for all processes
-        for (pid, process) in self.system.processes() {
-            let sample = CpuSample::new(now, process.cpu_usage());
-            self.history.entry(*pid).or_default().push(sample);
+/// Validate that the options depending on the averaging mode fit it
+fn validate_averaging_options(
+    averaging: AveragingMode,
+    half_life: Option<f32>,
+    cpu_stats: bool,
+    accounting: bool,
+) -> Result<(), String> {
+    if accounting && averaging != AveragingMode::Mean {
+        return Err(format!("--accounting replaces the sampled average and cannot be used with --averaging {}", averaging));
+    }
+    if averaging == AveragingMode::Ewma {
+        if cpu_stats {
+            return Err("--cpu-stats needs the samples of the window and cannot be used with --averaging ewma".to_string());
         }
+    } else if half_life.is_some() {
+        return Err("--half-life is only used with --averaging ewma".to_string());
+    }
+    Ok(())
+}
 
-        // Clean up old samples and remove dead processes
-        let cutoff_time = now - chrono::Duration::seconds(self.retention_seconds);
-        self.history.retain(|pid, samples| {
-            // Remove samples older than retention period
-            samples.retain(|s| s.timestamp >= cutoff_time);
+/// Validate that the number of displayed processes is positive
+fn validate_top(s: &str) -> Result<usize, String> {
+    let value: usize = s.parse().map_err(|_| format!("'{}' is not a valid number", s))?;
 
-            // Keep the entry only if there are samples and the process still exists
-            !samples.is_empty() && self.system.process(*pid).is_some()
-        });
+    if value == 0 {
+        Err("Top must be at least 1".to_string())
+    } else {
+        Ok(value)
     }
+}
 
-    /// Calculate average CPU percentage for each process in the retention window
-    fn calculate_cpu_burn(&self) -> Vec<ProcessInfo> {
-        let mut results = Vec::new();
+/// Validate that the tracking window holds a whole number of sampling intervals
+fn validate_window_and_interval(window: i64, interval: u64) -> Result<(), String> {
+    if window < interval as i64 {
+        Err(format!("Window ({}s) must not be shorter than the interval ({}s)", window, interval))
+    } else if window % interval as i64 != 0 {
+        Err(format!("Window ({}s) must be a multiple of the interval ({}s)", window, interval))
+    } else {
+        Ok(())
+    }
+}
 
-        for (pid, samples) in &self.history {
-            if samples.is_empty() {
-                continue;
-            }
+/// Open the CSV file, with the CPU distribution columns if requested
+fn open_csv_recorder(path: &Path, cpu_scale: CpuScale, cpu_stats: bool) -> io::Result<CsvRecorder> {
+    if cpu_stats {
+        CsvRecorder::open_with_cpu_stats(path, cpu_scale)
+    } else {
+        CsvRecorder::open(path, cpu_scale)
+    }
+}
 
-            // Use the cpu_calculator module for the calculation
-            let avg_cpu_percentage = calculate_average_cpu_percentage(samples, self.cpu_count);
+/// Exports that run alongside the chosen output format
+struct Exports {
+    csv_recorder: Option<CsvRecorder>,
+    metrics_server: Option<MetricsServer>,
+    user_summary: bool,
+    cpu_stats: bool,
+}
 
-            // Filter out processes below the configured CPU threshold
-            if avg_cpu_percentage < self.cpu_threshold {
-                continue;
-            }
+impl Exports {
+    /// Open the CSV file and start the metrics server requested on the command line,
+    /// exiting with an error message if either cannot be set up
+    fn open(args: &Args) -> Self {
+        let csv_recorder = match &args.csv {
+            Some(path) => match open_csv_recorder(path, args.cpu_scale, args.cpu_stats) {
+                Ok(recorder) => Some(recorder),
+                Err(e) => {
+                    eprintln!("Cannot open CSV file '{}': {}", path.display(), e);
+                    process::exit(1);
+                }
+            },
+            None => None,
+        };
+
+        let metrics_server = match args.prometheus_listen {
+            Some(addr) => match MetricsServer::start(addr) {
+                Ok(server) => {
+                    eprintln!("Serving Prometheus metrics on http://{}/metrics", server.local_addr());
+                    Some(server)
+                }
+                Err(e) => {
+                    eprintln!("Cannot listen on {}: {}", addr, e);
+                    process::exit(1);
+                }
+            },
+            None => None,
+        };
 
-            if let Some(process) = self.system.process(*pid) {
-                let name = process.name().to_string_lossy().to_string();
-                let memory_bytes = process.memory();
-                
-                // Extract additional information to distinguish multiple instances
-                let extra_info = self.extract_extra_info(process);
-                
-                results.push(ProcessInfo::new(
-                    name,
-                    *pid,
-                    avg_cpu_percentage,
-                    memory_bytes,
-                    extra_info,
-                ));
-            }
+        Self {
+            csv_recorder,
+            metrics_server,
+            user_summary: args.user_summary,
+            cpu_stats: args.cpu_stats,
         }
-
-        // Sort by CPU percentage (descending)
-        results.sort_by(|a, b| b.cpu_percent.partial_cmp(&a.cpu_percent).unwrap_or(std::cmp::Ordering::Equal));
-        results
     }
-    
-    /// Extract additional information from a process to help distinguish multiple instances
-    /// This includes window titles (on Windows), command line arguments, working directory, and memory usage
-    fn extract_extra_info(&self, process: &sysinfo::Process) -> String {
-        let pid = process.pid().as_u32();
-
-        // First priority: Check for window titles (Windows only)
-        // Check this process's window titles first
-        if let Some(titles) = self.window_titles_cache.get(&pid) {
-            if !titles.is_empty() {
-                // Join multiple window titles with " | "
-                let titles_str = titles.join(" | ");
-                if !titles_str.trim().is_empty() {
-                    return titles_str;
-                }
+
+    /// Record the ranking of one tick
+    fn record(&mut self, tracker: &ProcessTracker, results: &[ProcessInfo]) {
+        let timestamp = tracker.last_update().unwrap_or_else(chrono::Utc::now);
+
+        if let Some(recorder) = &mut self.csv_recorder {
+            if let Err(e) = recorder.record(timestamp, results) {
+                eprintln!("Stopped CSV recording: {}", e);
+                self.csv_recorder = None;
             }
         }
 
-        // If this process has no windows, check parent process
-        // This is useful for multi-process applications like Firefox where
-        // content processes don't own windows but their parent does
-        if let Some(parent_pid) = process.parent() {
-            if let Some(titles) = self.window_titles_cache.get(&parent_pid.as_u32()) {
-                if !titles.is_empty() {
-                    let titles_str = titles.join(" | ");
-                    if !titles_str.trim().is_empty() {
-                        return titles_str;
-                    }
-                }
+        if let Some(server) = &self.metrics_server {
+            let host = HostMetrics {
+                cpu_count: tracker.cpu_count(),
+                cpu_scale: tracker.cpu_scale(),
+                window_seconds: tracker.retention_seconds(),
+                interval_seconds: tracker.update_interval().as_secs(),
+                tracked_processes: tracker.tracked_process_count(),
+            };
+            let mut metrics = prometheus::render_metrics(results, &host);
+            if self.cpu_stats {
+                metrics.push_str(&prometheus::render_cpu_stats_metrics(results));
             }
-        }
-        
-        // Second priority: Command line arguments
-        let cmd = process.cmd();
-        if !cmd.is_empty() {
-            // Skip the first argument (usually the executable path)
-            // and take the next 1-2 meaningful arguments
-            let meaningful_args: Vec<String> = cmd.iter()
-                .skip(1)
-                .take(2)
-                .map(|arg| arg.to_string_lossy().to_string())
-                .collect();
-            
-            if !meaningful_args.is_empty() {
-                return meaningful_args.join(" ");
+            if let Some(totals) = tracker.cpu_time_totals() {
+                metrics.push_str(&prometheus::render_cpu_time_metrics(results, &totals));
             }
+            if self.user_summary {
+                metrics.push_str(&prometheus::render_user_metrics(&tracker.user_summary()));
+            }
+            server.update(metrics);
         }
-        
-        // Third priority: Working directory
-        if let Some(cwd) = process.cwd() {
-            return format!("({})", cwd.display());
-        }
-        
-        // Last resort: Empty string
-        String::new()
     }
+}
 
-    /// Display the top N processes by CPU usage percentage
-    fn display_top_processes(&mut self, top_n: usize) {
-        let results = self.calculate_cpu_burn();
+/// Validate that the output format can show the process tree
+fn validate_tree_output(tree: bool, output: OutputFormat) -> Result<(), String> {
+    if tree && !matches!(output, OutputFormat::Table | OutputFormat::Tui) {
+        return Err("--tree is only supported with --output table or tui".to_string());
+    }
+    Ok(())
+}
 
-        let term = Term::stdout();
+/// Validate that the output format can show application groups
+fn validate_group_by_output(group_by: Option<GroupBy>, output: OutputFormat) -> Result<(), String> {
+    if group_by.is_some() && !matches!(output, OutputFormat::Table | OutputFormat::Json | OutputFormat::Ndjson) {
+        return Err("--group-by is only supported with --output table, json or ndjson".to_string());
+    }
+    Ok(())
+}
 
-        // Build current CPU percentage map for trend calculation
-        let mut current_cpu_burn = HashMap::new();
-        for info in &results {
-            current_cpu_burn.insert(info.pid, info.cpu_percent);
-        }
+/// Processes selected with --pid, --name and --with-children
+fn focus_from_args(args: &Args) -> FocusSet {
+    FocusSet::new(args.pid.iter().map(|pid| Pid::from_u32(*pid)).collect(), args.name.clone())
+        .with_descendants(args.with_children)
+}
 
-        // Use display module to render the output with terminal handling
-        self.last_output_lines = display::display_top_processes(
-            &term,
-            &results,
-            self.retention_seconds,
-            &self.previous_cpu_burn,
-            top_n,
-            self.last_output_lines,
-        );
+/// Describe the focused processes for the startup banner
+fn describe_focus(focus: &FocusSet) -> String {
+    let mut parts: Vec<String> = focus.pids().iter().map(|pid| format!("PID {}", pid)).collect();
+    parts.extend(focus.names().iter().map(|name| format!("\"{}\"", name)));
 
-        // Update previous CPU burn for next trend calculation
-        self.previous_cpu_burn = current_cpu_burn;
+    let mut description = parts.join(", ");
+    if focus.include_descendants() {
+        description.push_str(" and their descendants");
     }
+    description
 }
 
+fn main() {
+    let args = Args::parse();
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    if let Err(message) = validate_window_and_interval(args.window, args.interval)
+        .and_then(|_| validate_tree_output(args.tree, args.output))
+        .and_then(|_| validate_group_by_output(args.group_by, args.output))
+        .and_then(|_| validate_averaging_options(args.averaging, args.half_life, args.cpu_stats, args.accounting))
+    {
+        Args::command().error(ErrorKind::ArgumentConflict, message).exit();
+    }
 
-    #[test]
-    fn test_trend_calculation() {
-        // Test that trend indicators are correctly determined
-        let current: f32 = 1.0;
-        let previous: f32 = 0.5;
-        let diff = current - previous;
-        
-        // Should be upward trend
-        assert!(diff > 0.1);
-        
-        let current: f32 = 0.5;
-        let previous: f32 = 1.0;
-        let diff = current - previous;
-        
-        // Should be downward trend
-        assert!(diff < -0.1);
-        
-        let current: f32 = 1.0;
-        let previous: f32 = 1.05;
-        let diff = current - previous;
-        
-        // Should be stable
-        assert!(diff.abs() <= 0.1);
+    let mut exports = Exports::open(&args);
+
+    let half_life = args.half_life.unwrap_or(DEFAULT_EWMA_HALF_LIFE_SECS);
+
+    let focus = focus_from_args(&args);
+
+    // Machine-readable output must not be mixed with the banner
+    if args.output == OutputFormat::Table {
+        println!("Process Shepherd - Starting CPU tracking...");
+        if focus.is_empty() {
+            println!("Monitoring CPU usage across all processes.");
+        } else {
+            println!("Focusing on {}.", describe_focus(&focus));
+        }
+        println!("CPU threshold: {:.1}%", args.cpu_threshold);
+        if args.cpu_scale == CpuScale::Core {
+            println!("CPU scale: core (100% = one core)");
+        }
+        println!("Sampling every {} seconds over a {} second window, showing top {}.", args.interval, args.window, args.top);
+        match args.averaging {
+            AveragingMode::Mean => {}
+            AveragingMode::Ewma => println!("Averaging: ewma with a {} second half-life", half_life),
+            averaging => println!("Averaging: {}", averaging),
+        }
+        if args.accounting {
+            println!("Accounting: CPU-seconds consumed in the window, from cumulative CPU time");
+            if args.reaped_children {
+                println!("Including the CPU time of reaped children in their parent's.");
+            }
+        }
+        println!("Press Ctrl+C to exit.\n");
     }
 
-    #[test]
-    fn test_filter_processes_below_threshold() {
-        // Test that processes below the threshold are filtered out
-        let threshold = 1.0;
-        let _tracker = ProcessTracker::new(60, threshold);
-        
-        // Mock data: processes with various CPU percentages
-        // In a real scenario, these would be calculated from actual process data
-        // For this test, we're verifying the filtering logic
-        
-        // Process with 0.5% CPU should be filtered
-        let cpu_below_threshold = 0.5f32;
-        assert!(cpu_below_threshold < threshold, "CPU below threshold should be less than threshold");
-        
-        // Process with exactly 1% CPU should be included
-        let cpu_at_threshold = 1.0f32;
-        assert!(cpu_at_threshold >= threshold, "CPU at threshold should be >= threshold");
-        
-        // Process with 1.5% CPU should be included
-        let cpu_above_threshold = 1.5f32;
-        assert!(cpu_above_threshold >= threshold, "CPU above threshold should be >= threshold");
+    let mut tracker = ProcessTracker::builder()
+        .retention_seconds(args.window)
+        .cpu_threshold(args.cpu_threshold)
+        .update_interval_secs(args.interval)
+        .averaging(args.averaging)
+        .ewma_half_life_secs(half_life)
+        .cpu_time_accounting(args.accounting)
+        .reaped_children(args.reaped_children)
+        .keep_exited(args.keep_exited)
+        .cpu_scale(args.cpu_scale)
+        .cpu_stats_columns(args.cpu_stats)
+        .top_n(args.top)
+        .sort(args.sort, args.sort_order.unwrap_or(args.sort.default_order()))
+        .filter(ProcessFilter::new(args.include.clone(), args.exclude.clone()))
+        .focus(focus)
+        .build();
+
+    // Initial refresh to populate process list
+    tracker.update();
+    thread::sleep(Duration::from_secs(1));
+
+    if args.output == OutputFormat::Tui {
+        let options = tui::ViewOptions {
+            tree: args.tree,
+            user_summary: args.user_summary,
+            cpu_stats: args.cpu_stats,
+        };
+        if let Err(e) = tui::run(&mut tracker, &options, |tracker, results| exports.record(tracker, results)) {
+            eprintln!("Terminal error: {}", e);
+            process::exit(1);
+        }
+        return;
     }
 
-    #[test]
-    fn test_filter_edge_cases() {
-        // Test edge cases for the CPU filter
-        let threshold = 1.0;
-        
-        // Just below threshold
-        let cpu = 0.99f32;
-        assert!(cpu < threshold, "0.99% should be filtered");
-        
-        // Exactly at threshold
-        let cpu = 1.0f32;
-        assert!(cpu >= threshold, "1.0% should be included");
-        
-        // Just above threshold
-        let cpu = 1.01f32;
-        assert!(cpu >= threshold, "1.01% should be included");
+    loop {
+        tracker.update();
+        let results = tracker.rank_processes();
+        let timestamp = tracker.last_update().unwrap_or_else(chrono::Utc::now);
+
+        exports.record(&tracker, &results);
+
+        let written = match (args.output, args.group_by) {
+            (OutputFormat::Table, Some(group_by)) => {
+                let groups = tracker.process_groups(group_by);
+                tracker.display_process_groups(&groups, group_by);
+                Ok(())
+            }
+            (OutputFormat::Json, Some(group_by)) => {
+                let groups = tracker.process_groups(group_by);
+                let report = GroupReport::new(timestamp, args.window, args.interval, args.cpu_scale, group_by, &groups);
+                export::write_json(&mut io::stdout().lock(), &report)
+            }
+            (OutputFormat::Ndjson, Some(group_by)) => {
+                let groups = tracker.process_groups(group_by);
+                let report = GroupReport::new(timestamp, args.window, args.interval, args.cpu_scale, group_by, &groups);
+                export::write_group_ndjson(&mut io::stdout().lock(), &report)
+            }
+            (output, _) => write_processes(output, &args, &mut tracker, &results, timestamp),
+        };
+
+        if let (Some(totals), OutputFormat::Table) = (tracker.cpu_time_totals(), args.output) {
+            tracker.display_cpu_time_totals(&totals);
+        }
+        if args.user_summary && args.output == OutputFormat::Table {
+            let users = tracker.user_summary();
+            tracker.display_user_summary(&users);
+        }
+
+        // Stop quietly when the consumer goes away (e.g. `| head`)
+        if written.is_err() {
+            return;
+        }
+
+        thread::sleep(tracker.update_interval());
     }
+}
 
-    #[test]
-    fn test_custom_threshold() {
-        // Test that custom thresholds work correctly
-        let threshold_5 = 5.0;
-        let _tracker = ProcessTracker::new(60, threshold_5);
-        
-        // Process with 3% CPU should be filtered with 5% threshold
-        let cpu_below = 3.0f32;
-        assert!(cpu_below < threshold_5, "3% should be filtered with 5% threshold");
-        
-        // Process with 5% CPU should be included
-        let cpu_at = 5.0f32;
-        assert!(cpu_at >= threshold_5, "5% should be included with 5% threshold");
-        
-        // Process with 7% CPU should be included
-        let cpu_above = 7.0f32;
-        assert!(cpu_above >= threshold_5, "7% should be included with 5% threshold");
+/// Write the per-process ranking of one tick in the output format
+fn write_processes(
+    output: OutputFormat,
+    args: &Args,
+    tracker: &mut ProcessTracker,
+    results: &[ProcessInfo],
+    timestamp: chrono::DateTime<chrono::Utc>,
+) -> io::Result<()> {
+    match output {
+        OutputFormat::Table if args.tree => {
+            let tree = tracker.process_tree();
+            tracker.display_process_tree(&tree);
+            Ok(())
+        }
+        OutputFormat::Table => {
+            tracker.display_top_processes(results);
+            Ok(())
+        }
+        OutputFormat::Json => {
+            let mut report = TickReport::new(timestamp, args.window, args.interval, args.cpu_scale, results);
+            if args.cpu_stats {
+                report = report.with_cpu_stats(results);
+            }
+            if args.user_summary {
+                report = report.with_users(&tracker.user_summary());
+            }
+            if let Some(totals) = tracker.cpu_time_totals() {
+                report = report.with_cpu_time(&totals);
+            }
+            export::write_json(&mut io::stdout().lock(), &report)
+        }
+        OutputFormat::Ndjson => {
+            let mut report = TickReport::new(timestamp, args.window, args.interval, args.cpu_scale, results);
+            if args.cpu_stats {
+                report = report.with_cpu_stats(results);
+            }
+            export::write_ndjson(&mut io::stdout().lock(), &report)
+        }
+        OutputFormat::Tui | OutputFormat::None => Ok(()),
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
     #[test]
     fn test_validate_threshold_valid() {
@@ -324,30 +538,230 @@ mod tests {
         assert!(validate_threshold("150.0").is_err());
         assert!(validate_threshold("not_a_number").is_err());
     }
-}
 
-fn main() {
-    let args = Args::parse();
-    
-    println!("Process Shepherd - Starting CPU tracking...");
-    println!("Monitoring CPU usage across all processes.");
-    println!("CPU threshold: {:.1}%", args.cpu_threshold);
-    println!("Press Ctrl+C to exit.\n");
+    #[test]
+    fn test_validate_interval() {
+        assert_eq!(validate_interval("1"), Ok(1));
+        assert_eq!(validate_interval("10"), Ok(10));
+        assert!(validate_interval("0").is_err());
+        assert!(validate_interval("-2").is_err());
+        assert!(validate_interval("3601").is_err());
+        assert!(validate_interval("fast").is_err());
+    }
 
-    const UPDATE_INTERVAL_SECS: u64 = 2; // Sample every 2 seconds
-    const RETENTION_SECS: i64 = 60; // Track last 60 seconds
-    const TOP_N: usize = 20; // Display top 20 processes
+    #[test]
+    fn test_validate_window() {
+        assert_eq!(validate_window("10"), Ok(10));
+        assert_eq!(validate_window("900"), Ok(900));
+        assert!(validate_window("0").is_err());
+        assert!(validate_window("-60").is_err());
+        assert!(validate_window("86401").is_err());
+        assert!(validate_window("1m").is_err());
+    }
 
-    let mut tracker = ProcessTracker::new(RETENTION_SECS, args.cpu_threshold);
+    #[test]
+    fn test_validate_top() {
+        assert_eq!(validate_top("1"), Ok(1));
+        assert_eq!(validate_top("50"), Ok(50));
+        assert!(validate_top("0").is_err());
+        assert!(validate_top("-5").is_err());
+    }
 
-    // Initial refresh to populate process list
-    tracker.update();
-    thread::sleep(Duration::from_secs(1));
+    #[test]
+    fn test_validate_window_and_interval() {
+        assert!(validate_window_and_interval(60, 2).is_ok());
+        assert!(validate_window_and_interval(10, 10).is_ok());
+        assert!(validate_window_and_interval(900, 5).is_ok());
+        assert!(validate_window_and_interval(61, 2).is_err());
+        assert!(validate_window_and_interval(5, 10).is_err());
+    }
 
-    loop {
-        tracker.update();
-        tracker.display_top_processes(TOP_N);
+    #[test]
+    fn test_validate_tree_output() {
+        assert!(validate_tree_output(false, OutputFormat::Json).is_ok());
+        assert!(validate_tree_output(true, OutputFormat::Table).is_ok());
+        assert!(validate_tree_output(true, OutputFormat::Tui).is_ok());
+        assert!(validate_tree_output(true, OutputFormat::Json).is_err());
+        assert!(validate_tree_output(true, OutputFormat::None).is_err());
+    }
+
+    #[test]
+    fn test_validate_group_by_output() {
+        assert!(validate_group_by_output(None, OutputFormat::Tui).is_ok());
+        assert!(validate_group_by_output(Some(GroupBy::Name), OutputFormat::Table).is_ok());
+        assert!(validate_group_by_output(Some(GroupBy::Exe), OutputFormat::Json).is_ok());
+        assert!(validate_group_by_output(Some(GroupBy::User), OutputFormat::Ndjson).is_ok());
+        assert!(validate_group_by_output(Some(GroupBy::Name), OutputFormat::Tui).is_err());
+        assert!(validate_group_by_output(Some(GroupBy::Name), OutputFormat::None).is_err());
+    }
+
+    #[test]
+    fn test_args_group_by() {
+        let args = Args::parse_from(["process-shepherd", "--group-by", "parent-window"]);
+        assert_eq!(args.group_by, Some(GroupBy::ParentWindow));
+
+        assert!(Args::try_parse_from(["process-shepherd", "--group-by", "host"]).is_err());
+        assert!(Args::try_parse_from(["process-shepherd", "--group-by", "name", "--tree"]).is_err());
+    }
 
-        thread::sleep(Duration::from_secs(UPDATE_INTERVAL_SECS));
+    #[test]
+    fn test_args_defaults() {
+        let args = Args::parse_from(["process-shepherd"]);
+        assert_eq!(args.interval, DEFAULT_UPDATE_INTERVAL_SECS);
+        assert_eq!(args.window, DEFAULT_RETENTION_SECS);
+        assert_eq!(args.top, DEFAULT_TOP_N);
+        assert_eq!(args.averaging, AveragingMode::Mean);
+        assert_eq!(args.output, OutputFormat::Table);
+        assert_eq!(args.sort, SortKey::Cpu);
+        assert_eq!(args.sort_order, None);
+        assert_eq!(args.csv, None);
+        assert_eq!(args.prometheus_listen, None);
+        assert!(!args.tree);
+        assert_eq!(args.group_by, None);
+        assert!(!args.user_summary);
+        assert!(!args.cpu_stats);
+        assert!(Args::parse_from(["process-shepherd", "--cpu-stats"]).cpu_stats);
+        assert!(Args::parse_from(["process-shepherd", "--user-summary"]).user_summary);
+    }
+
+    #[test]
+    fn test_args_averaging() {
+        let args = Args::parse_from(["process-shepherd", "--averaging", "time-weighted"]);
+        assert_eq!(args.averaging, AveragingMode::TimeWeighted);
+
+        assert!(Args::try_parse_from(["process-shepherd", "--averaging", "median"]).is_err());
+
+        let args = Args::parse_from(["process-shepherd", "--averaging", "ewma", "--half-life", "2.5"]);
+        assert_eq!(args.averaging, AveragingMode::Ewma);
+        assert_eq!(args.half_life, Some(2.5));
+        assert_eq!(Args::parse_from(["process-shepherd"]).half_life, None);
+
+        assert!(!Args::parse_from(["process-shepherd"]).accounting);
+        assert!(Args::parse_from(["process-shepherd", "--accounting"]).accounting);
+        assert!(Args::parse_from(["process-shepherd", "--accounting", "--reaped-children"]).reaped_children);
+        assert!(Args::try_parse_from(["process-shepherd", "--reaped-children"]).is_err());
+    }
+
+    #[test]
+    fn test_args_cpu_scale() {
+        assert_eq!(Args::parse_from(["process-shepherd"]).cpu_scale, CpuScale::Machine);
+        let args = Args::parse_from(["process-shepherd", "--cpu-scale", "core"]);
+        assert_eq!(args.cpu_scale, CpuScale::Core);
+        assert!(Args::try_parse_from(["process-shepherd", "--cpu-scale", "socket"]).is_err());
+    }
+
+    #[test]
+    fn test_args_keep_exited() {
+        assert!(!Args::parse_from(["process-shepherd"]).keep_exited);
+        assert!(Args::parse_from(["process-shepherd", "--keep-exited"]).keep_exited);
+    }
+
+    #[test]
+    fn test_validate_half_life() {
+        assert_eq!(validate_half_life("10"), Ok(10.0));
+        assert_eq!(validate_half_life("0.5"), Ok(0.5));
+        assert!(validate_half_life("0").is_err());
+        assert!(validate_half_life("-3").is_err());
+        assert!(validate_half_life("NaN").is_err());
+        assert!(validate_half_life("86401").is_err());
+        assert!(validate_half_life("10s").is_err());
+    }
+
+    #[test]
+    fn test_validate_averaging_options() {
+        assert!(validate_averaging_options(AveragingMode::Mean, None, true, false).is_ok());
+        assert!(validate_averaging_options(AveragingMode::Ewma, Some(5.0), false, false).is_ok());
+        assert!(validate_averaging_options(AveragingMode::Ewma, None, true, false).is_err());
+        assert!(validate_averaging_options(AveragingMode::TimeWeighted, Some(5.0), false, false).is_err());
+        assert!(validate_averaging_options(AveragingMode::Mean, None, true, true).is_ok());
+        assert!(validate_averaging_options(AveragingMode::TimeWeighted, None, false, true).is_err());
+        assert!(validate_averaging_options(AveragingMode::Ewma, None, false, true).is_err());
+    }
+
+    #[test]
+    fn test_args_prometheus_headless() {
+        let args = Args::parse_from(["process-shepherd", "--prometheus-listen", "127.0.0.1:9184", "--output", "none"]);
+        assert_eq!(args.prometheus_listen, Some("127.0.0.1:9184".parse().unwrap()));
+        assert_eq!(args.output, OutputFormat::None);
+
+        assert!(Args::try_parse_from(["process-shepherd", "--prometheus-listen", "localhost"]).is_err());
+    }
+
+    #[test]
+    fn test_args_sort() {
+        let args = Args::parse_from(["process-shepherd", "--sort", "memory", "--sort-order", "asc"]);
+        assert_eq!(args.sort, SortKey::Memory);
+        assert_eq!(args.sort_order, Some(SortOrder::Ascending));
+
+        let args = Args::parse_from(["process-shepherd", "--sort", "peak"]);
+        assert_eq!(args.sort, SortKey::PeakCpu);
+
+        assert!(Args::try_parse_from(["process-shepherd", "--sort", "disk"]).is_err());
+        assert!(Args::try_parse_from(["process-shepherd", "--sort-order", "up"]).is_err());
+    }
+
+    #[test]
+    fn test_args_include_exclude() {
+        let args = Args::parse_from([
+            "process-shepherd",
+            "--include", "chrome*",
+            "--include", "re:^node$",
+            "--exclude", "*--type=gpu*",
+        ]);
+        let include: Vec<&str> = args.include.iter().map(|p| p.as_str()).collect();
+        assert_eq!(include, vec!["chrome*", "re:^node$"]);
+        assert_eq!(args.exclude.len(), 1);
+
+        assert!(Args::try_parse_from(["process-shepherd", "--include", "re:("]).is_err());
+    }
+
+    #[test]
+    fn test_args_focus() {
+        let args = Args::parse_from([
+            "process-shepherd",
+            "--pid", "42",
+            "--pid", "43",
+            "--name", "postgres*",
+            "--with-children",
+        ]);
+        let focus = focus_from_args(&args);
+
+        assert_eq!(focus.pids(), &[Pid::from_u32(42), Pid::from_u32(43)]);
+        assert_eq!(focus.names()[0].as_str(), "postgres*");
+        assert!(focus.include_descendants());
+        assert_eq!(describe_focus(&focus), "PID 42, PID 43, \"postgres*\" and their descendants");
+
+        assert!(focus_from_args(&Args::parse_from(["process-shepherd"])).is_empty());
+        assert!(Args::try_parse_from(["process-shepherd", "--pid", "abc"]).is_err());
+        // --with-children needs something to take the children of
+        assert!(Args::try_parse_from(["process-shepherd", "--with-children"]).is_err());
+    }
+
+    #[test]
+    fn test_args_csv_path() {
+        let args = Args::parse_from(["process-shepherd", "--csv", "capture.csv"]);
+        assert_eq!(args.csv, Some(PathBuf::from("capture.csv")));
+    }
+
+    #[test]
+    fn test_args_output_formats() {
+        let args = Args::parse_from(["process-shepherd", "--output", "json"]);
+        assert_eq!(args.output, OutputFormat::Json);
+
+        let args = Args::parse_from(["process-shepherd", "--output", "ndjson"]);
+        assert_eq!(args.output, OutputFormat::Ndjson);
+
+        let args = Args::parse_from(["process-shepherd", "--output", "tui"]);
+        assert_eq!(args.output, OutputFormat::Tui);
+
+        assert!(Args::try_parse_from(["process-shepherd", "--output", "xml"]).is_err());
+    }
+
+    #[test]
+    fn test_args_custom_values() {
+        let args = Args::parse_from(["process-shepherd", "--interval", "5", "--window", "900", "--top", "10"]);
+        assert_eq!(args.interval, 5);
+        assert_eq!(args.window, 900);
+        assert_eq!(args.top, 10);
     }
 }
@@ -0,0 +1,107 @@
+use crate::process_source::ProcessKey;
+use sysinfo::Pid;
+
+/// Resident memory of one process split by kind, in bytes, as listed in `/proc/<pid>/status`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MemoryBreakdown {
+    /// Private memory (heap, stacks and other anonymous mappings)
+    pub anonymous_bytes: u64,
+    /// Memory mapped from files, including the executable and libraries
+    pub file_bytes: u64,
+    /// Shared memory (tmpfs, System V shared memory and shared anonymous mappings)
+    pub shared_bytes: u64,
+    /// Memory swapped out
+    pub swap_bytes: u64,
+}
+
+/// Parse the contents of `/proc/<pid>/status`
+///
+/// # Examples
+/// ```
+/// use process_shepherd::process_details::parse_process_status;
+///
+/// let status = "Name:\tapp\nVmRSS:\t  3072 kB\nRssAnon:\t  2048 kB\nRssFile:\t  1024 kB\nRssShmem:\t     0 kB\nVmSwap:\t   512 kB\n";
+/// let memory = parse_process_status(status).unwrap();
+/// assert_eq!(memory.anonymous_bytes, 2048 * 1024);
+/// assert_eq!(memory.file_bytes, 1024 * 1024);
+/// assert_eq!(memory.swap_bytes, 512 * 1024);
+/// ```
+pub fn parse_process_status(contents: &str) -> Option<MemoryBreakdown> {
+    // Values are in kB; kernel threads have no memory lines at all
+    let field = |name: &str| {
+        contents
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+            .and_then(|value| value.split_whitespace().next()?.parse::<u64>().ok())
+            .map(|kb| kb * 1024)
+    };
+
+    Some(MemoryBreakdown {
+        anonymous_bytes: field("RssAnon")?,
+        file_bytes: field("RssFile")?,
+        shared_bytes: field("RssShmem")?,
+        swap_bytes: field("VmSwap").unwrap_or(0),
+    })
+}
+
+/// Read the memory breakdown of a process, `None` if unavailable (not Linux, or the process exited)
+#[cfg(target_os = "linux")]
+pub fn read_memory_breakdown(pid: Pid) -> Option<MemoryBreakdown> {
+    let contents = std::fs::read_to_string(format!("/proc/{}/status", pid.as_u32())).ok()?;
+    parse_process_status(&contents)
+}
+
+/// Read the memory breakdown of a process, `None` if unavailable (not Linux, or the process exited)
+#[cfg(not(target_os = "linux"))]
+pub fn read_memory_breakdown(_pid: Pid) -> Option<MemoryBreakdown> {
+    None
+}
+
+/// Everything known about one process beyond its row in the ranking, shown in the detail pane
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessDetails {
+    /// The process these details are about
+    pub key: ProcessKey,
+    /// Working directory, empty if unknown
+    pub cwd: String,
+    pub virtual_memory_bytes: u64,
+    /// Number of threads, if known (Linux only)
+    pub thread_count: Option<usize>,
+    /// Parent, grandparent and so on, nearest first, with their names (empty if not tracked)
+    pub ancestors: Vec<(Pid, String)>,
+    /// Resident memory by kind, only known for running processes on Linux
+    pub memory: Option<MemoryBreakdown>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_process_status() {
+        let status = "Name:\tcargo\nUmask:\t0022\nState:\tR (running)\nVmPeak:\t  900000 kB\nVmRSS:\t  150000 kB\n\
+                      RssAnon:\t  120000 kB\nRssFile:\t   28000 kB\nRssShmem:\t    2000 kB\nVmSwap:\t       0 kB\nThreads:\t9\n";
+        let memory = parse_process_status(status).unwrap();
+
+        assert_eq!(memory.anonymous_bytes, 120000 * 1024);
+        assert_eq!(memory.file_bytes, 28000 * 1024);
+        assert_eq!(memory.shared_bytes, 2000 * 1024);
+        assert_eq!(memory.swap_bytes, 0);
+    }
+
+    #[test]
+    fn test_parse_process_status_without_memory() {
+        // Kernel threads have no user memory
+        assert_eq!(parse_process_status("Name:\tkthreadd\nState:\tS (sleeping)\nThreads:\t1\n"), None);
+        assert_eq!(parse_process_status(""), None);
+        // The name must match completely
+        assert_eq!(parse_process_status("RssAnonX:\t1 kB\nRssFile:\t1 kB\nRssShmem:\t1 kB\n"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_read_own_memory_breakdown() {
+        let memory = read_memory_breakdown(Pid::from_u32(std::process::id())).unwrap();
+        assert!(memory.anonymous_bytes > 0);
+    }
+}
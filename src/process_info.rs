@@ -1,5 +1,60 @@
+use crate::cpu_calculator::CpuStats;
+use crate::process_source::ProcessKey;
+use chrono::{DateTime, Utc};
 use sysinfo::Pid;
 
+/// Minimum change in CPU percentage between two measurements to count as a trend
+pub const TREND_THRESHOLD: f32 = 0.1;
+
+/// Direction of CPU usage compared to the previous measurement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trend {
+    Up,
+    Down,
+    Stable,
+}
+
+impl Trend {
+    /// Determine the trend from the current and previous CPU percentages
+    ///
+    /// # Examples
+    /// ```
+    /// use process_shepherd::process_info::Trend;
+    ///
+    /// assert_eq!(Trend::from_change(1.5, 1.0, 0.1), Trend::Up);
+    /// assert_eq!(Trend::from_change(1.0, 1.5, 0.1), Trend::Down);
+    /// assert_eq!(Trend::from_change(1.0, 1.05, 0.1), Trend::Stable);
+    /// ```
+    pub fn from_change(current: f32, previous: f32, threshold: f32) -> Self {
+        let diff = current - previous;
+        if diff > threshold {
+            Trend::Up
+        } else if diff < -threshold {
+            Trend::Down
+        } else {
+            Trend::Stable
+        }
+    }
+
+    /// Single-character indicator used in the terminal display
+    pub fn indicator(self) -> &'static str {
+        match self {
+            Trend::Up => "↑",
+            Trend::Down => "↓",
+            Trend::Stable => " ",
+        }
+    }
+
+    /// Lowercase name used in machine-readable output
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Trend::Up => "up",
+            Trend::Down => "down",
+            Trend::Stable => "stable",
+        }
+    }
+}
+
 /// Information about a process including CPU usage and distinguishing details
 #[derive(Debug, Clone)]
 pub struct ProcessInfo {
@@ -8,6 +63,39 @@ pub struct ProcessInfo {
     pub cpu_percent: f32,
     pub memory_bytes: u64,
     pub extra_info: String,
+    /// CPU percentage reported for this process in the previous ranking, if any
+    pub previous_cpu_percent: Option<f32>,
+    /// Raw CPU usage of the most recent sample (not normalized, can exceed 100%)
+    pub latest_cpu_usage: f32,
+    /// Number of samples in the tracking window the average is based on
+    pub sample_count: usize,
+    /// Highest normalized CPU percentage of a single sample in the tracking window
+    pub peak_cpu_percent: f32,
+    /// Full command line, arguments separated by spaces
+    pub command_line: String,
+    /// Normalized CPU percentage of every sample in the tracking window, oldest first
+    pub cpu_samples: Vec<f32>,
+    /// Distribution of the CPU percentages in the tracking window
+    pub cpu_stats: CpuStats,
+    /// CPU-seconds consumed in the tracking window, only known with CPU time accounting
+    pub cpu_seconds: Option<f64>,
+    /// CPU-seconds of children the process reaped in the tracking window, only known when
+    /// they are attributed to it
+    ///
+    /// A child that was tracked before it exited is also counted in its own `cpu_seconds`.
+    pub children_cpu_seconds: Option<f64>,
+    /// Start time in seconds since the Unix epoch, 0 if unknown
+    pub start_time: u64,
+    /// When the process was found to have exited, only set for exited processes kept in the ranking
+    pub exited_at: Option<DateTime<Utc>>,
+    /// PID of the parent process, if known
+    pub parent: Option<Pid>,
+    /// Path of the executable, empty if unknown
+    pub exe: String,
+    /// User running the process, empty if unknown
+    pub user: String,
+    /// Titles of the windows owned by the process or its parent
+    pub window_title: String,
 }
 
 impl ProcessInfo {
@@ -18,8 +106,129 @@ impl ProcessInfo {
             cpu_percent,
             memory_bytes,
             extra_info,
+            previous_cpu_percent: None,
+            latest_cpu_usage: 0.0,
+            sample_count: 0,
+            peak_cpu_percent: 0.0,
+            command_line: String::new(),
+            cpu_samples: Vec::new(),
+            cpu_stats: CpuStats::default(),
+            cpu_seconds: None,
+            children_cpu_seconds: None,
+            start_time: 0,
+            exited_at: None,
+            parent: None,
+            exe: String::new(),
+            user: String::new(),
+            window_title: String::new(),
         }
     }
+
+    /// Set the CPU percentage from the previous ranking used for trend calculation
+    pub fn with_previous_cpu_percent(mut self, previous_cpu_percent: Option<f32>) -> Self {
+        self.previous_cpu_percent = previous_cpu_percent;
+        self
+    }
+
+    /// Set the raw CPU usage of the most recent sample
+    pub fn with_latest_cpu_usage(mut self, latest_cpu_usage: f32) -> Self {
+        self.latest_cpu_usage = latest_cpu_usage;
+        self
+    }
+
+    /// Set the number of samples the average is based on
+    pub fn with_sample_count(mut self, sample_count: usize) -> Self {
+        self.sample_count = sample_count;
+        self
+    }
+
+    /// Set the highest normalized CPU percentage of a single sample
+    pub fn with_peak_cpu_percent(mut self, peak_cpu_percent: f32) -> Self {
+        self.peak_cpu_percent = peak_cpu_percent;
+        self
+    }
+
+    /// Set the full command line
+    pub fn with_command_line(mut self, command_line: String) -> Self {
+        self.command_line = command_line;
+        self
+    }
+
+    /// Set the normalized CPU percentage of every sample in the tracking window
+    pub fn with_cpu_samples(mut self, cpu_samples: Vec<f32>) -> Self {
+        self.cpu_samples = cpu_samples;
+        self
+    }
+
+    /// Set the distribution of the CPU percentages in the tracking window
+    pub fn with_cpu_stats(mut self, cpu_stats: CpuStats) -> Self {
+        self.cpu_stats = cpu_stats;
+        self
+    }
+
+    /// Set the CPU-seconds consumed in the tracking window
+    pub fn with_cpu_seconds(mut self, cpu_seconds: Option<f64>) -> Self {
+        self.cpu_seconds = cpu_seconds;
+        self
+    }
+
+    /// Set the CPU-seconds of the children reaped in the tracking window
+    pub fn with_children_cpu_seconds(mut self, children_cpu_seconds: Option<f64>) -> Self {
+        self.children_cpu_seconds = children_cpu_seconds;
+        self
+    }
+
+    /// Set the start time in seconds since the Unix epoch
+    pub fn with_start_time(mut self, start_time: u64) -> Self {
+        self.start_time = start_time;
+        self
+    }
+
+    /// Identity of the process, telling it apart from earlier processes with the same PID
+    pub fn key(&self) -> ProcessKey {
+        ProcessKey::new(self.pid, self.start_time)
+    }
+
+    /// Set when the process was found to have exited
+    pub fn with_exited_at(mut self, exited_at: Option<DateTime<Utc>>) -> Self {
+        self.exited_at = exited_at;
+        self
+    }
+
+    /// Set the PID of the parent process
+    pub fn with_parent(mut self, parent: Option<Pid>) -> Self {
+        self.parent = parent;
+        self
+    }
+
+    /// Set the path of the executable
+    pub fn with_exe(mut self, exe: String) -> Self {
+        self.exe = exe;
+        self
+    }
+
+    /// Set the user running the process
+    pub fn with_user(mut self, user: String) -> Self {
+        self.user = user;
+        self
+    }
+
+    /// Set the titles of the windows owned by the process or its parent
+    pub fn with_window_title(mut self, window_title: String) -> Self {
+        self.window_title = window_title;
+        self
+    }
+
+    /// Change in CPU percentage since the previous ranking, or `None` if there is no previous data
+    pub fn trend_delta(&self) -> Option<f32> {
+        self.previous_cpu_percent.map(|previous| self.cpu_percent - previous)
+    }
+
+    /// Trend compared to the previous ranking, or `None` if there is no previous data
+    pub fn trend(&self) -> Option<Trend> {
+        self.previous_cpu_percent
+            .map(|previous| Trend::from_change(self.cpu_percent, previous, TREND_THRESHOLD))
+    }
 }
 
 #[cfg(test)]
@@ -72,4 +281,39 @@ mod tests {
         assert_eq!(info.memory_bytes, 2 * 1024 * 1024 * 1024);
         assert_eq!(info.extra_info, long_info);
     }
+
+    #[test]
+    fn test_process_info_without_previous_has_no_trend() {
+        let info = ProcessInfo::new("a".to_string(), Pid::from_u32(1), 5.0, 0, String::new());
+
+        assert_eq!(info.previous_cpu_percent, None);
+        assert_eq!(info.trend(), None);
+    }
+
+    #[test]
+    fn test_process_info_trend_from_previous() {
+        let info = ProcessInfo::new("a".to_string(), Pid::from_u32(1), 5.0, 0, String::new());
+
+        assert_eq!(info.clone().with_previous_cpu_percent(Some(2.0)).trend(), Some(Trend::Up));
+        assert_eq!(info.clone().with_previous_cpu_percent(Some(8.0)).trend(), Some(Trend::Down));
+        assert_eq!(info.with_previous_cpu_percent(Some(5.05)).trend(), Some(Trend::Stable));
+    }
+
+    #[test]
+    fn test_process_info_trend_delta() {
+        let info = ProcessInfo::new("a".to_string(), Pid::from_u32(1), 5.0, 0, String::new());
+
+        assert_eq!(info.trend_delta(), None);
+        assert_eq!(info.with_previous_cpu_percent(Some(2.0)).trend_delta(), Some(3.0));
+    }
+
+    #[test]
+    fn test_trend_names_and_indicators() {
+        assert_eq!(Trend::Up.as_str(), "up");
+        assert_eq!(Trend::Down.as_str(), "down");
+        assert_eq!(Trend::Stable.as_str(), "stable");
+        assert_eq!(Trend::Up.indicator(), "↑");
+        assert_eq!(Trend::Down.indicator(), "↓");
+        assert_eq!(Trend::Stable.indicator(), " ");
+    }
 }
@@ -0,0 +1,640 @@
+use crate::cpu_time;
+use crate::process_details::{self, MemoryBreakdown};
+use crate::system_summary::SystemStats;
+use crate::window_info;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet, VecDeque};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System, Uid, UpdateKind, Users};
+
+/// Identity of a process: its PID together with its start time, so that a process that
+/// reuses the PID of an exited one is told apart from it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ProcessKey {
+    pub pid: Pid,
+    /// Start time in seconds since the Unix epoch, 0 if unknown
+    pub start_time: u64,
+}
+
+impl ProcessKey {
+    pub fn new(pid: Pid, start_time: u64) -> Self {
+        Self { pid, start_time }
+    }
+}
+
+/// Point-in-time view of a single process as reported by a [`ProcessSource`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessSnapshot {
+    pub pid: Pid,
+    pub name: String,
+    /// Raw CPU usage since the previous refresh (not normalized, can exceed 100%)
+    pub cpu_usage: f32,
+    pub memory_bytes: u64,
+    pub extra_info: String,
+    /// Full command line, arguments separated by spaces
+    pub command_line: String,
+    /// PID of the parent process, if known
+    pub parent: Option<Pid>,
+    /// Path of the executable, empty if unknown
+    pub exe: String,
+    /// Name of the user running the process (or the user ID if the name is unknown), empty if unknown
+    pub user: String,
+    /// Titles of the windows owned by the process or, failing that, its parent
+    pub window_title: String,
+    /// User and system CPU time consumed since the process started in seconds, if known
+    pub cpu_time_secs: Option<f64>,
+    /// CPU time of the children that exited and were waited for by the process in seconds, if known
+    pub children_cpu_time_secs: Option<f64>,
+    /// Start time in seconds since the Unix epoch, 0 if unknown
+    pub start_time: u64,
+    /// Working directory, empty if unknown
+    pub cwd: String,
+    pub virtual_memory_bytes: u64,
+    /// Number of threads, if known (Linux only)
+    pub thread_count: Option<usize>,
+}
+
+impl ProcessSnapshot {
+    pub fn new(pid: Pid, name: impl Into<String>, cpu_usage: f32) -> Self {
+        Self {
+            pid,
+            name: name.into(),
+            cpu_usage,
+            memory_bytes: 0,
+            extra_info: String::new(),
+            command_line: String::new(),
+            parent: None,
+            exe: String::new(),
+            user: String::new(),
+            window_title: String::new(),
+            cpu_time_secs: None,
+            children_cpu_time_secs: None,
+            start_time: 0,
+            cwd: String::new(),
+            virtual_memory_bytes: 0,
+            thread_count: None,
+        }
+    }
+
+    /// Identity of the process, telling it apart from earlier processes with the same PID
+    pub fn key(&self) -> ProcessKey {
+        ProcessKey::new(self.pid, self.start_time)
+    }
+
+    /// Set the memory usage in bytes
+    pub fn with_memory(mut self, memory_bytes: u64) -> Self {
+        self.memory_bytes = memory_bytes;
+        self
+    }
+
+    /// Set the details used to distinguish multiple instances of the same process
+    pub fn with_extra_info(mut self, extra_info: impl Into<String>) -> Self {
+        self.extra_info = extra_info.into();
+        self
+    }
+
+    /// Set the full command line
+    pub fn with_command_line(mut self, command_line: impl Into<String>) -> Self {
+        self.command_line = command_line.into();
+        self
+    }
+
+    /// Set the PID of the parent process
+    pub fn with_parent(mut self, parent: Pid) -> Self {
+        self.parent = Some(parent);
+        self
+    }
+
+    /// Set the path of the executable
+    pub fn with_exe(mut self, exe: impl Into<String>) -> Self {
+        self.exe = exe.into();
+        self
+    }
+
+    /// Set the user running the process
+    pub fn with_user(mut self, user: impl Into<String>) -> Self {
+        self.user = user.into();
+        self
+    }
+
+    /// Set the titles of the windows owned by the process or its parent
+    pub fn with_window_title(mut self, window_title: impl Into<String>) -> Self {
+        self.window_title = window_title.into();
+        self
+    }
+
+    /// Set the CPU time consumed since the process started in seconds
+    pub fn with_cpu_time_secs(mut self, cpu_time_secs: f64) -> Self {
+        self.cpu_time_secs = Some(cpu_time_secs);
+        self
+    }
+
+    /// Set the start time in seconds since the Unix epoch
+    pub fn with_start_time(mut self, start_time: u64) -> Self {
+        self.start_time = start_time;
+        self
+    }
+
+    /// Set the working directory
+    pub fn with_cwd(mut self, cwd: impl Into<String>) -> Self {
+        self.cwd = cwd.into();
+        self
+    }
+
+    /// Set the virtual memory size in bytes
+    pub fn with_virtual_memory(mut self, virtual_memory_bytes: u64) -> Self {
+        self.virtual_memory_bytes = virtual_memory_bytes;
+        self
+    }
+
+    /// Set the number of threads
+    pub fn with_thread_count(mut self, thread_count: usize) -> Self {
+        self.thread_count = Some(thread_count);
+        self
+    }
+
+    /// Set the CPU time of the children reaped by the process in seconds
+    pub fn with_children_cpu_time_secs(mut self, children_cpu_time_secs: f64) -> Self {
+        self.children_cpu_time_secs = Some(children_cpu_time_secs);
+        self
+    }
+}
+
+/// All processes observed by a [`ProcessSource`] during one refresh
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceTick {
+    pub timestamp: DateTime<Utc>,
+    pub processes: Vec<ProcessSnapshot>,
+    /// CPU-seconds the whole machine has been busy since boot, summed over all cores, if known
+    pub busy_cpu_secs: Option<f64>,
+    /// Machine-wide utilisation, if known
+    pub system: Option<SystemStats>,
+}
+
+impl SourceTick {
+    pub fn new(timestamp: DateTime<Utc>, processes: Vec<ProcessSnapshot>) -> Self {
+        Self {
+            timestamp,
+            processes,
+            busy_cpu_secs: None,
+            system: None,
+        }
+    }
+
+    /// Set the CPU-seconds the machine has been busy since boot
+    pub fn with_busy_cpu_secs(mut self, busy_cpu_secs: f64) -> Self {
+        self.busy_cpu_secs = Some(busy_cpu_secs);
+        self
+    }
+
+    /// Set the machine-wide utilisation
+    pub fn with_system(mut self, system: SystemStats) -> Self {
+        self.system = Some(system);
+        self
+    }
+}
+
+/// Produces per-tick process snapshots for a [`crate::ProcessTracker`]
+pub trait ProcessSource {
+    /// Number of CPU cores used to normalize CPU percentages
+    fn cpu_count(&self) -> f32;
+
+    /// Refresh process information and return the processes currently running
+    fn refresh(&mut self) -> SourceTick;
+
+    /// Resident memory of a running process split by kind, if the source can tell
+    fn memory_breakdown(&self, _pid: Pid) -> Option<MemoryBreakdown> {
+        None
+    }
+}
+
+/// Process source backed by `sysinfo::System`
+pub struct SysinfoSource {
+    system: System,
+    users: Users,
+    /// User IDs still missing from `users` after a refresh, so they don't trigger one every tick
+    unresolved_user_ids: HashSet<Uid>,
+    window_titles_cache: HashMap<u32, Vec<String>>,
+    read_cpu_times: bool,
+}
+
+impl SysinfoSource {
+    pub fn new() -> Self {
+        Self {
+            system: System::new_all(),
+            users: Users::new_with_refreshed_list(),
+            unresolved_user_ids: HashSet::new(),
+            window_titles_cache: HashMap::new(),
+            read_cpu_times: false,
+        }
+    }
+
+    /// Also report the cumulative CPU time of every process and of the machine (Linux only)
+    pub fn with_cpu_times(mut self, read_cpu_times: bool) -> Self {
+        self.read_cpu_times = read_cpu_times;
+        self
+    }
+
+    /// Window titles of the process, or of its parent if the process has no windows (Windows only)
+    fn window_title(&self, process: &sysinfo::Process) -> String {
+        let pid = process.pid().as_u32();
+
+        // Check this process's window titles first
+        if let Some(titles) = self.window_titles_cache.get(&pid) {
+            if !titles.is_empty() {
+                // Join multiple window titles with " | "
+                let titles_str = titles.join(" | ");
+                if !titles_str.trim().is_empty() {
+                    return titles_str;
+                }
+            }
+        }
+
+        // If this process has no windows, check parent process
+        // This is useful for multi-process applications like Firefox where
+        // content processes don't own windows but their parent does
+        if let Some(parent_pid) = process.parent() {
+            if let Some(titles) = self.window_titles_cache.get(&parent_pid.as_u32()) {
+                if !titles.is_empty() {
+                    let titles_str = titles.join(" | ");
+                    if !titles_str.trim().is_empty() {
+                        return titles_str;
+                    }
+                }
+            }
+        }
+
+        String::new()
+    }
+
+    /// Reload the user list if a process runs as a user created since the last load
+    fn refresh_users_on_miss(&mut self) {
+        let unknown: HashSet<Uid> = self
+            .system
+            .processes()
+            .values()
+            .filter_map(|process| process.user_id())
+            .filter(|uid| self.users.get_user_by_id(uid).is_none() && !self.unresolved_user_ids.contains(uid))
+            .cloned()
+            .collect();
+        if unknown.is_empty() {
+            return;
+        }
+
+        self.users.refresh_list();
+        self.unresolved_user_ids
+            .extend(unknown.into_iter().filter(|uid| self.users.get_user_by_id(uid).is_none()));
+    }
+
+    /// Name of the user running the process, falling back to the user ID
+    fn user_name(&self, process: &sysinfo::Process) -> String {
+        match process.user_id() {
+            Some(uid) => match self.users.get_user_by_id(uid) {
+                Some(user) => user.name().to_string(),
+                None => uid.to_string(),
+            },
+            None => String::new(),
+        }
+    }
+
+    /// Machine-wide utilisation as of the last refresh
+    fn system_stats(&self) -> SystemStats {
+        let load_average = System::load_average();
+        // sysinfo reports zeros on Windows, which has no load average
+        let load_average = cfg!(not(windows)).then_some([load_average.one, load_average.five, load_average.fifteen]);
+        SystemStats {
+            cpu_percent: self.system.global_cpu_usage(),
+            core_percents: self.system.cpus().iter().map(|cpu| cpu.cpu_usage()).collect(),
+            load_average,
+            memory_used_bytes: self.system.used_memory(),
+            memory_total_bytes: self.system.total_memory(),
+            swap_used_bytes: self.system.used_swap(),
+            swap_total_bytes: self.system.total_swap(),
+        }
+    }
+
+    /// Extract additional information from a process to help distinguish multiple instances
+    /// This includes window titles (on Windows), command line arguments, working directory, and memory usage
+    fn extract_extra_info(&self, process: &sysinfo::Process, window_title: &str) -> String {
+        // First priority: Window titles of the process or its parent (Windows only)
+        if !window_title.is_empty() {
+            return window_title.to_string();
+        }
+
+        // Second priority: Command line arguments
+        let cmd = process.cmd();
+        if !cmd.is_empty() {
+            // Skip the first argument (usually the executable path)
+            // and take the next 1-2 meaningful arguments
+            let meaningful_args: Vec<String> = cmd.iter()
+                .skip(1)
+                .take(2)
+                .map(|arg| arg.to_string_lossy().to_string())
+                .collect();
+
+            if !meaningful_args.is_empty() {
+                return meaningful_args.join(" ");
+            }
+        }
+
+        // Third priority: Working directory
+        if let Some(cwd) = process.cwd() {
+            return format!("({})", cwd.display());
+        }
+
+        // Last resort: Empty string
+        String::new()
+    }
+}
+
+/// Join the command line arguments of a process with spaces
+fn join_command_line(process: &sysinfo::Process) -> String {
+    process
+        .cmd()
+        .iter()
+        .map(|arg| arg.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl Default for SysinfoSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProcessSource for SysinfoSource {
+    fn cpu_count(&self) -> f32 {
+        // System::new_all() already initializes CPU info
+        // Use max(1) to prevent division by zero
+        (self.system.cpus().len() as f32).max(1.0)
+    }
+
+    fn refresh(&mut self) -> SourceTick {
+        self.system.refresh_processes_specifics(
+            ProcessesToUpdate::All,
+            true,
+            ProcessRefreshKind::new()
+                .with_cpu()
+                .with_memory()
+                .with_cmd(UpdateKind::OnlyIfNotSet)
+                .with_exe(UpdateKind::OnlyIfNotSet)
+                // A process can change its directory, unlike its command line and executable
+                .with_cwd(UpdateKind::Always)
+                .with_user(UpdateKind::OnlyIfNotSet),
+        );
+
+        self.system.refresh_cpu_usage();
+        self.system.refresh_memory();
+        self.refresh_users_on_miss();
+
+        // Refresh window titles cache once per update (only on Windows)
+        self.window_titles_cache = window_info::get_all_window_titles();
+
+        let processes = self
+            .system
+            .processes()
+            .values()
+            .map(|process| {
+                let window_title = self.window_title(process);
+                let snapshot = ProcessSnapshot::new(
+                    process.pid(),
+                    process.name().to_string_lossy(),
+                    process.cpu_usage(),
+                )
+                .with_memory(process.memory())
+                .with_extra_info(self.extract_extra_info(process, &window_title))
+                .with_command_line(join_command_line(process))
+                .with_exe(process.exe().map(|exe| exe.display().to_string()).unwrap_or_default())
+                .with_user(self.user_name(process))
+                .with_window_title(window_title)
+                .with_start_time(process.start_time())
+                .with_cwd(process.cwd().map(|cwd| cwd.display().to_string()).unwrap_or_default())
+                .with_virtual_memory(process.virtual_memory());
+
+                let snapshot = match process.tasks() {
+                    Some(tasks) => snapshot.with_thread_count(tasks.len()),
+                    None => snapshot,
+                };
+
+                let snapshot = match process.parent() {
+                    Some(parent) => snapshot.with_parent(parent),
+                    None => snapshot,
+                };
+                match self.read_cpu_times.then(|| cpu_time::read_process_cpu_times(process.pid())).flatten() {
+                    Some(times) => snapshot
+                        .with_cpu_time_secs(times.own_secs())
+                        .with_children_cpu_time_secs(times.children_secs()),
+                    None => snapshot,
+                }
+            })
+            .collect();
+
+        let tick = SourceTick::new(Utc::now(), processes).with_system(self.system_stats());
+        match self.read_cpu_times.then(cpu_time::read_system_cpu_times).flatten() {
+            Some(times) => tick.with_busy_cpu_secs(times.busy_secs()),
+            None => tick,
+        }
+    }
+
+    fn memory_breakdown(&self, pid: Pid) -> Option<MemoryBreakdown> {
+        process_details::read_memory_breakdown(pid)
+    }
+}
+
+/// Process source that replays a predefined sequence of ticks
+///
+/// Useful for testing ranking, retention and filtering with controlled data.
+/// Once all scripted ticks have been replayed, further refreshes report no
+/// processes at the timestamp of the last tick.
+///
+/// # Examples
+/// ```
+/// use chrono::{TimeZone, Utc};
+/// use process_shepherd::process_source::{ProcessSnapshot, ScriptedSource};
+/// use process_shepherd::ProcessTracker;
+/// use sysinfo::Pid;
+///
+/// let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+/// let source = ScriptedSource::new(4.0)
+///     .with_tick(start, vec![ProcessSnapshot::new(Pid::from_u32(1), "busy", 200.0)]);
+///
+/// let mut tracker = ProcessTracker::builder().build_with_source(source);
+/// tracker.update();
+///
+/// let top = tracker.top_processes();
+/// assert_eq!(top[0].name, "busy");
+/// assert_eq!(top[0].cpu_percent, 50.0);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedSource {
+    cpu_count: f32,
+    ticks: VecDeque<SourceTick>,
+    last_timestamp: DateTime<Utc>,
+}
+
+impl ScriptedSource {
+    pub fn new(cpu_count: f32) -> Self {
+        Self {
+            cpu_count,
+            ticks: VecDeque::new(),
+            last_timestamp: DateTime::<Utc>::UNIX_EPOCH,
+        }
+    }
+
+    /// Append a tick to be returned by a later refresh
+    pub fn with_tick(mut self, timestamp: DateTime<Utc>, processes: Vec<ProcessSnapshot>) -> Self {
+        self.push_tick(timestamp, processes);
+        self
+    }
+
+    /// Append a tick to be returned by a later refresh
+    pub fn push_tick(&mut self, timestamp: DateTime<Utc>, processes: Vec<ProcessSnapshot>) {
+        self.ticks.push_back(SourceTick::new(timestamp, processes));
+    }
+
+    /// Append a fully built tick, e.g. one carrying the machine's busy time
+    pub fn push_source_tick(&mut self, tick: SourceTick) {
+        self.ticks.push_back(tick);
+    }
+
+    /// Number of scripted ticks not yet returned
+    pub fn remaining_ticks(&self) -> usize {
+        self.ticks.len()
+    }
+}
+
+impl ProcessSource for ScriptedSource {
+    fn cpu_count(&self) -> f32 {
+        self.cpu_count.max(1.0)
+    }
+
+    fn refresh(&mut self) -> SourceTick {
+        match self.ticks.pop_front() {
+            Some(tick) => {
+                self.last_timestamp = tick.timestamp;
+                tick
+            }
+            None => SourceTick::new(self.last_timestamp, Vec::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn start_time() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_snapshot_builder_methods() {
+        let snapshot = ProcessSnapshot::new(Pid::from_u32(42), "app", 12.5)
+            .with_memory(1024)
+            .with_extra_info("--serve")
+            .with_command_line("app --serve")
+            .with_parent(Pid::from_u32(1))
+            .with_exe("/usr/bin/app")
+            .with_user("alice")
+            .with_window_title("App - Main")
+            .with_cpu_time_secs(12.5)
+            .with_cwd("/srv/app")
+            .with_virtual_memory(4096)
+            .with_thread_count(3);
+
+        assert_eq!(snapshot.pid.as_u32(), 42);
+        assert_eq!(snapshot.name, "app");
+        assert_eq!(snapshot.cpu_usage, 12.5);
+        assert_eq!(snapshot.memory_bytes, 1024);
+        assert_eq!(snapshot.extra_info, "--serve");
+        assert_eq!(snapshot.command_line, "app --serve");
+        assert_eq!(snapshot.parent, Some(Pid::from_u32(1)));
+        assert_eq!(snapshot.exe, "/usr/bin/app");
+        assert_eq!(snapshot.user, "alice");
+        assert_eq!(snapshot.window_title, "App - Main");
+        assert_eq!(snapshot.cpu_time_secs, Some(12.5));
+        assert_eq!(snapshot.cwd, "/srv/app");
+        assert_eq!(snapshot.virtual_memory_bytes, 4096);
+        assert_eq!(snapshot.thread_count, Some(3));
+    }
+
+    #[test]
+    fn test_scripted_source_replays_ticks_in_order() {
+        let t0 = start_time();
+        let t1 = t0 + chrono::Duration::seconds(2);
+        let mut source = ScriptedSource::new(2.0)
+            .with_tick(t0, vec![ProcessSnapshot::new(Pid::from_u32(1), "a", 10.0)])
+            .with_tick(t1, vec![ProcessSnapshot::new(Pid::from_u32(2), "b", 20.0)]);
+
+        assert_eq!(source.remaining_ticks(), 2);
+
+        let first = source.refresh();
+        assert_eq!(first.timestamp, t0);
+        assert_eq!(first.processes[0].name, "a");
+
+        let second = source.refresh();
+        assert_eq!(second.timestamp, t1);
+        assert_eq!(second.processes[0].name, "b");
+        assert_eq!(source.remaining_ticks(), 0);
+    }
+
+    #[test]
+    fn test_scripted_source_exhausted_returns_empty_tick() {
+        let t0 = start_time();
+        let mut source = ScriptedSource::new(1.0)
+            .with_tick(t0, vec![ProcessSnapshot::new(Pid::from_u32(1), "a", 10.0)]);
+
+        source.refresh();
+        let tick = source.refresh();
+
+        assert_eq!(tick.timestamp, t0);
+        assert!(tick.processes.is_empty());
+    }
+
+    #[test]
+    fn test_scripted_source_cpu_count_never_zero() {
+        assert_eq!(ScriptedSource::new(0.0).cpu_count(), 1.0);
+        assert_eq!(ScriptedSource::new(8.0).cpu_count(), 8.0);
+    }
+
+    #[test]
+    fn test_sysinfo_source_reports_processes() {
+        let mut source = SysinfoSource::new();
+        let tick = source.refresh();
+
+        assert!(source.cpu_count() >= 1.0);
+        // At least the test process itself must be visible
+        let own_pid = Pid::from_u32(std::process::id());
+        let own = tick.processes.iter().find(|p| p.pid == own_pid).expect("test process is listed");
+        assert!(!own.exe.is_empty());
+        assert!(own.start_time > 0);
+        assert!(!own.cwd.is_empty());
+        assert!(own.virtual_memory_bytes >= own.memory_bytes);
+        if cfg!(target_os = "linux") {
+            assert!(own.thread_count.unwrap() >= 1);
+        }
+        // CPU times are only read on request
+        assert_eq!(own.cpu_time_secs, None);
+        assert_eq!(own.children_cpu_time_secs, None);
+        assert_eq!(tick.busy_cpu_secs, None);
+
+        let system = tick.system.expect("machine-wide utilisation is reported");
+        assert_eq!(system.core_percents.len() as f32, source.cpu_count());
+        assert!(system.memory_total_bytes > 0);
+        assert!(system.memory_used_bytes <= system.memory_total_bytes);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_sysinfo_source_reports_cpu_times() {
+        let mut source = SysinfoSource::new().with_cpu_times(true);
+        let tick = source.refresh();
+
+        let own_pid = Pid::from_u32(std::process::id());
+        let own = tick.processes.iter().find(|p| p.pid == own_pid).expect("test process is listed");
+        assert!(own.cpu_time_secs.is_some());
+        assert!(own.children_cpu_time_secs.is_some());
+        assert!(tick.busy_cpu_secs.unwrap() > 0.0);
+    }
+}
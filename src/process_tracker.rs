@@ -0,0 +1,1951 @@
+use crate::cpu_calculator::{
+    calculate_average_cpu_percentage, calculate_cpu_stats, calculate_cpu_time_consumed, calculate_cpu_time_percentage,
+    calculate_peak_cpu_percentage, calculate_time_weighted_cpu_percentage, normalize_cpu_samples, AveragingMode,
+    CpuSample, CpuScale, CpuStats, CpuTimeSample, Ewma,
+};
+use crate::cpu_time::CpuTimeTotals;
+use crate::display;
+use crate::filter::{Pattern, ProcessFilter};
+use crate::focus::FocusSet;
+use crate::grouping::{group_processes, sort_groups, GroupBy, ProcessGroup};
+use crate::process_details::ProcessDetails;
+use crate::process_source::{ProcessKey, ProcessSnapshot, ProcessSource, SysinfoSource};
+use crate::process_tree::{build_forest, flatten_forest, prune_forest, sort_forest, TreeNode};
+use crate::sorting::{describe_sort, sort_processes, SortKey, SortOrder};
+use crate::system_summary::{SystemStats, SystemSummary};
+use crate::ProcessInfo;
+use chrono::{DateTime, Utc};
+use console::Term;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use sysinfo::Pid;
+
+/// Default length of the tracking window in seconds
+pub const DEFAULT_RETENTION_SECS: i64 = 60;
+/// Default minimum CPU percentage for a process to be reported
+pub const DEFAULT_CPU_THRESHOLD: f32 = 1.0;
+/// Default time between two samples in seconds
+pub const DEFAULT_UPDATE_INTERVAL_SECS: u64 = 2;
+/// Default number of processes to display
+pub const DEFAULT_TOP_N: usize = 20;
+/// Default half-life in seconds of the exponentially weighted moving average
+pub const DEFAULT_EWMA_HALF_LIFE_SECS: f32 = 10.0;
+
+/// Builder for configuring a [`ProcessTracker`]
+///
+/// # Examples
+/// ```no_run
+/// use process_shepherd::ProcessTracker;
+///
+/// let mut tracker = ProcessTracker::builder()
+///     .retention_seconds(120)
+///     .cpu_threshold(5.0)
+///     .update_interval_secs(4)
+///     .top_n(10)
+///     .build();
+///
+/// tracker.update();
+/// for info in tracker.top_processes() {
+///     println!("{} {:.2}%", info.name, info.cpu_percent);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProcessTrackerBuilder {
+    retention_seconds: i64,
+    cpu_threshold: f32,
+    update_interval_secs: u64,
+    top_n: usize,
+    sort_key: SortKey,
+    sort_order: SortOrder,
+    filter: ProcessFilter,
+    focus: FocusSet,
+    averaging: AveragingMode,
+    ewma_half_life_secs: f32,
+    cpu_time_accounting: bool,
+    reaped_children: bool,
+    keep_exited: bool,
+    cpu_scale: CpuScale,
+    cpu_stats_columns: bool,
+}
+
+impl Default for ProcessTrackerBuilder {
+    fn default() -> Self {
+        Self {
+            retention_seconds: DEFAULT_RETENTION_SECS,
+            cpu_threshold: DEFAULT_CPU_THRESHOLD,
+            update_interval_secs: DEFAULT_UPDATE_INTERVAL_SECS,
+            top_n: DEFAULT_TOP_N,
+            sort_key: SortKey::Cpu,
+            sort_order: SortOrder::Descending,
+            filter: ProcessFilter::default(),
+            focus: FocusSet::default(),
+            averaging: AveragingMode::Mean,
+            ewma_half_life_secs: DEFAULT_EWMA_HALF_LIFE_SECS,
+            cpu_time_accounting: false,
+            reaped_children: false,
+            keep_exited: false,
+            cpu_scale: CpuScale::default(),
+            cpu_stats_columns: false,
+        }
+    }
+}
+
+impl ProcessTrackerBuilder {
+    /// Set how many seconds of CPU samples are kept per process
+    pub fn retention_seconds(mut self, retention_seconds: i64) -> Self {
+        self.retention_seconds = retention_seconds;
+        self
+    }
+
+    /// Set the minimum average CPU percentage for a process to be reported
+    pub fn cpu_threshold(mut self, cpu_threshold: f32) -> Self {
+        self.cpu_threshold = cpu_threshold;
+        self
+    }
+
+    /// Set the intended time between two calls to [`ProcessTracker::update`]
+    pub fn update_interval_secs(mut self, update_interval_secs: u64) -> Self {
+        self.update_interval_secs = update_interval_secs;
+        self
+    }
+
+    /// Set how many processes [`ProcessTracker::top_processes`] returns
+    pub fn top_n(mut self, top_n: usize) -> Self {
+        self.top_n = top_n;
+        self
+    }
+
+    /// Set the column and direction used to rank processes
+    pub fn sort(mut self, sort_key: SortKey, sort_order: SortOrder) -> Self {
+        self.sort_key = sort_key;
+        self.sort_order = sort_order;
+        self
+    }
+
+    /// Set the include/exclude rules applied before ranking
+    pub fn filter(mut self, filter: ProcessFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Restrict tracking to the processes in focus, reporting them regardless of the CPU threshold
+    pub fn focus(mut self, focus: FocusSet) -> Self {
+        self.focus = focus;
+        self
+    }
+
+    /// Set how the samples in the tracking window are combined into one CPU percentage
+    pub fn averaging(mut self, averaging: AveragingMode) -> Self {
+        self.averaging = averaging;
+        self
+    }
+
+    /// Set the half-life of the moving average used with [`AveragingMode::Ewma`]
+    pub fn ewma_half_life_secs(mut self, ewma_half_life_secs: f32) -> Self {
+        self.ewma_half_life_secs = ewma_half_life_secs;
+        self
+    }
+
+    /// Rank processes by the CPU time they consumed in the window, read from cumulative
+    /// CPU time counters, instead of by sampled usage
+    ///
+    /// Processes whose source reports no CPU time (e.g. not on Linux) keep the sampled average.
+    pub fn cpu_time_accounting(mut self, cpu_time_accounting: bool) -> Self {
+        self.cpu_time_accounting = cpu_time_accounting;
+        self
+    }
+
+    /// With CPU time accounting, add the CPU time of children a process waited for to its own,
+    /// so work done by short-lived children that never showed up in a sample is visible
+    ///
+    /// Only children that exited in the window and were reaped by the process count. A child
+    /// that was tracked itself is not subtracted, so its CPU time shows up twice.
+    pub fn reaped_children(mut self, reaped_children: bool) -> Self {
+        self.reaped_children = reaped_children;
+        self
+    }
+
+    /// Keep processes that exited in the ranking until their samples leave the tracking window,
+    /// instead of dropping them at the next refresh
+    pub fn keep_exited(mut self, keep_exited: bool) -> Self {
+        self.keep_exited = keep_exited;
+        self
+    }
+
+    /// What 100% means in the reported CPU percentages: the whole machine (default) or one core
+    pub fn cpu_scale(mut self, cpu_scale: CpuScale) -> Self {
+        self.cpu_scale = cpu_scale;
+        self
+    }
+
+    /// Show the CPU distribution (percentiles, maximum, standard deviation) in the process table
+    pub fn cpu_stats_columns(mut self, cpu_stats_columns: bool) -> Self {
+        self.cpu_stats_columns = cpu_stats_columns;
+        self
+    }
+
+    /// Build the tracker, reading processes from the system via sysinfo
+    pub fn build(self) -> ProcessTracker {
+        let read_cpu_times = self.cpu_time_accounting;
+        self.build_with_source(SysinfoSource::new().with_cpu_times(read_cpu_times))
+    }
+
+    /// Build the tracker, reading processes from a custom source
+    pub fn build_with_source<S: ProcessSource>(self, source: S) -> ProcessTracker<S> {
+        let cpu_count = source.cpu_count().max(1.0);
+
+        ProcessTracker {
+            source,
+            history: HashMap::new(),
+            ewma: HashMap::new(),
+            cpu_times: HashMap::new(),
+            children_cpu_times: HashMap::new(),
+            busy_cpu_times: Vec::new(),
+            latest: HashMap::new(),
+            exited: HashMap::new(),
+            system: None,
+            process_count: 0,
+            retention_seconds: self.retention_seconds.max(1),
+            last_output_lines: 0,
+            previous_cpu_burn: HashMap::new(),
+            cpu_count,
+            cpu_threshold: self.cpu_threshold,
+            update_interval_secs: self.update_interval_secs.max(1),
+            top_n: self.top_n,
+            sort_key: self.sort_key,
+            sort_order: self.sort_order,
+            filter: self.filter,
+            focus: self.focus,
+            averaging: self.averaging,
+            ewma_half_life_secs: self.ewma_half_life_secs.max(0.0),
+            cpu_time_accounting: self.cpu_time_accounting,
+            reaped_children: self.reaped_children,
+            keep_exited: self.keep_exited,
+            cpu_scale: self.cpu_scale,
+            cpu_stats_columns: self.cpu_stats_columns,
+            first_update: None,
+            last_update: None,
+        }
+    }
+}
+
+/// Tracks CPU usage history for processes
+pub struct ProcessTracker<S: ProcessSource = SysinfoSource> {
+    source: S,
+    /// Samples in the tracking window; empty with [`AveragingMode::Ewma`]
+    history: HashMap<ProcessKey, Vec<CpuSample>>,
+    /// Moving average and latest sample per process, used instead of `history` with [`AveragingMode::Ewma`]
+    ewma: HashMap<ProcessKey, Ewma>,
+    /// Cumulative CPU time readings in the window per process, kept after the process
+    /// exits until they leave the window; only with CPU time accounting
+    cpu_times: HashMap<ProcessKey, Vec<CpuTimeSample>>,
+    /// Cumulative CPU time readings of the reaped children of each live process; only with
+    /// CPU time accounting and reaped children attribution
+    children_cpu_times: HashMap<ProcessKey, Vec<CpuTimeSample>>,
+    /// Cumulative busy time of the machine in the window; only with CPU time accounting
+    busy_cpu_times: Vec<CpuTimeSample>,
+    /// Most recent snapshot of every process seen in the last refresh
+    latest: HashMap<ProcessKey, ProcessSnapshot>,
+    /// Last snapshot and exit time of exited processes whose samples are still in the window;
+    /// only when keeping exited processes
+    exited: HashMap<ProcessKey, ExitedProcess>,
+    /// Machine-wide utilisation at the last refresh, if the source reports it
+    system: Option<SystemStats>,
+    /// Number of processes running at the last refresh, before applying the focus
+    process_count: usize,
+    retention_seconds: i64,
+    last_output_lines: usize,
+    previous_cpu_burn: HashMap<ProcessKey, f32>,
+    cpu_count: f32,
+    cpu_threshold: f32,
+    update_interval_secs: u64,
+    top_n: usize,
+    sort_key: SortKey,
+    sort_order: SortOrder,
+    filter: ProcessFilter,
+    focus: FocusSet,
+    averaging: AveragingMode,
+    ewma_half_life_secs: f32,
+    cpu_time_accounting: bool,
+    reaped_children: bool,
+    keep_exited: bool,
+    cpu_scale: CpuScale,
+    cpu_stats_columns: bool,
+    first_update: Option<DateTime<Utc>>,
+    last_update: Option<DateTime<Utc>>,
+}
+
+impl ProcessTracker {
+    /// Create a tracker with the given retention window and CPU threshold,
+    /// using defaults for everything else
+    pub fn new(retention_seconds: i64, cpu_threshold: f32) -> Self {
+        Self::builder()
+            .retention_seconds(retention_seconds)
+            .cpu_threshold(cpu_threshold)
+            .build()
+    }
+
+    /// Start configuring a new tracker
+    pub fn builder() -> ProcessTrackerBuilder {
+        ProcessTrackerBuilder::default()
+    }
+}
+
+impl<S: ProcessSource> ProcessTracker<S> {
+    /// The source this tracker reads processes from
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Length of the tracking window in seconds
+    pub fn retention_seconds(&self) -> i64 {
+        self.retention_seconds
+    }
+
+    /// Minimum average CPU percentage for a process to be reported
+    pub fn cpu_threshold(&self) -> f32 {
+        self.cpu_threshold
+    }
+
+    /// Intended time between two calls to [`ProcessTracker::update`]
+    pub fn update_interval(&self) -> Duration {
+        Duration::from_secs(self.update_interval_secs)
+    }
+
+    /// Number of processes returned by [`ProcessTracker::top_processes`]
+    pub fn top_n(&self) -> usize {
+        self.top_n
+    }
+
+    /// Column used to rank processes
+    pub fn sort_key(&self) -> SortKey {
+        self.sort_key
+    }
+
+    /// Direction used to rank processes
+    pub fn sort_order(&self) -> SortOrder {
+        self.sort_order
+    }
+
+    /// Change how processes are ranked from the next ranking on
+    pub fn set_sort(&mut self, sort_key: SortKey, sort_order: SortOrder) {
+        self.sort_key = sort_key;
+        self.sort_order = sort_order;
+    }
+
+    /// Include/exclude rules applied before ranking
+    pub fn filter(&self) -> &ProcessFilter {
+        &self.filter
+    }
+
+    /// Set the interactive search applied on top of the include/exclude rules
+    pub fn set_query(&mut self, query: Option<Pattern>) {
+        self.filter.set_query(query);
+    }
+
+    /// Processes tracked in focus mode; empty when every process is tracked
+    pub fn focus(&self) -> &FocusSet {
+        &self.focus
+    }
+
+    /// How the samples in the tracking window are combined into one CPU percentage
+    pub fn averaging(&self) -> AveragingMode {
+        self.averaging
+    }
+
+    /// Half-life in seconds of the moving average used with [`AveragingMode::Ewma`]
+    pub fn ewma_half_life_secs(&self) -> f32 {
+        self.ewma_half_life_secs
+    }
+
+    /// Whether processes are ranked by the CPU time they consumed in the window
+    pub fn cpu_time_accounting(&self) -> bool {
+        self.cpu_time_accounting
+    }
+
+    /// Whether the CPU time of reaped children is attributed to their parent
+    pub fn reaped_children(&self) -> bool {
+        self.reaped_children
+    }
+
+    /// Whether exited processes stay in the ranking until their samples leave the window
+    pub fn keep_exited(&self) -> bool {
+        self.keep_exited
+    }
+
+    /// What 100% means in the reported CPU percentages
+    pub fn cpu_scale(&self) -> CpuScale {
+        self.cpu_scale
+    }
+
+    /// Number of CPU cores used to normalize CPU percentages
+    pub fn cpu_count(&self) -> f32 {
+        self.cpu_count
+    }
+
+    /// Number of processes with samples in the tracking window
+    pub fn tracked_process_count(&self) -> usize {
+        self.history.len() + self.ewma.len()
+    }
+
+    /// Timestamp of the most recent [`ProcessTracker::update`], if any
+    pub fn last_update(&self) -> Option<DateTime<Utc>> {
+        self.last_update
+    }
+
+    /// Update process information and record CPU usage samples
+    pub fn update(&mut self) {
+        let tick = self.source.refresh();
+        let now = tick.timestamp;
+        let previous_update = self.last_update;
+        self.first_update.get_or_insert(now);
+        self.last_update = Some(now);
+
+        self.system = tick.system;
+        self.process_count = tick.processes.len();
+
+        if self.cpu_time_accounting {
+            if let Some(busy_cpu_secs) = tick.busy_cpu_secs {
+                self.busy_cpu_times.push(CpuTimeSample::new(now, busy_cpu_secs));
+            }
+        }
+
+        // In focus mode only the focused processes (and their descendants) are recorded
+        let mut processes = tick.processes;
+        if !self.focus.is_empty() {
+            let focused = self.focus.resolve(&processes);
+            processes.retain(|snapshot| focused.contains(&snapshot.pid));
+        }
+
+        // Collect current CPU usage for all processes
+        let previous = std::mem::take(&mut self.latest);
+        for snapshot in processes {
+            let key = snapshot.key();
+            let sample = CpuSample::new(now, snapshot.cpu_usage);
+            if self.averaging == AveragingMode::Ewma {
+                match self.ewma.get_mut(&key) {
+                    Some(ewma) => ewma.update(&sample, self.ewma_half_life_secs),
+                    None => {
+                        self.ewma.insert(key, Ewma::new(&sample));
+                    }
+                }
+            } else {
+                self.history.entry(key).or_default().push(sample);
+            }
+            if self.cpu_time_accounting {
+                if let Some(cpu_time_secs) = snapshot.cpu_time_secs {
+                    let readings = self.cpu_times.entry(key).or_default();
+                    record_cpu_time(readings, CpuTimeSample::new(now, cpu_time_secs), previous_update, key.start_time);
+                }
+                if let Some(children_cpu_time_secs) = snapshot.children_cpu_time_secs.filter(|_| self.reaped_children) {
+                    let readings = self.children_cpu_times.entry(key).or_default();
+                    record_cpu_time(
+                        readings,
+                        CpuTimeSample::new(now, children_cpu_time_secs),
+                        previous_update,
+                        key.start_time,
+                    );
+                }
+            }
+            self.exited.remove(&key);
+            self.latest.insert(key, snapshot);
+        }
+
+        if self.keep_exited {
+            for (key, snapshot) in previous {
+                if !self.latest.contains_key(&key) {
+                    self.exited.insert(key, ExitedProcess::new(snapshot, now));
+                }
+            }
+        }
+
+        // Clean up old samples and remove dead processes
+        let cutoff_time = now - chrono::Duration::seconds(self.retention_seconds);
+        self.history.retain(|key, samples| {
+            // Remove samples older than retention period
+            samples.retain(|s| s.timestamp >= cutoff_time);
+
+            // Keep the entry only if there are samples and the process still exists (or is kept after exiting)
+            !samples.is_empty() && (self.latest.contains_key(key) || self.exited.contains_key(key))
+        });
+        self.ewma.retain(|key, ewma| {
+            ewma.last_sample().timestamp >= cutoff_time
+                && (self.latest.contains_key(key) || self.exited.contains_key(key))
+        });
+        self.exited.retain(|key, _| self.history.contains_key(key) || self.ewma.contains_key(key));
+
+        // CPU time of exited processes still counts towards the totals while it is in the window
+        self.cpu_times.retain(|_, readings| {
+            readings.retain(|r| r.timestamp >= cutoff_time);
+            !readings.is_empty()
+        });
+        self.busy_cpu_times.retain(|r| r.timestamp >= cutoff_time);
+        // Reaped children are only shown next to their parent, so they leave with it
+        self.children_cpu_times.retain(|key, readings| {
+            readings.retain(|r| r.timestamp >= cutoff_time);
+            !readings.is_empty() && self.latest.contains_key(key)
+        });
+    }
+
+    /// CPU time consumed in the tracking window by all processes and by the whole machine
+    ///
+    /// # Returns
+    /// `None` unless CPU time accounting is enabled
+    pub fn cpu_time_totals(&self) -> Option<CpuTimeTotals> {
+        if !self.cpu_time_accounting {
+            return None;
+        }
+
+        Some(CpuTimeTotals {
+            process_secs: self.cpu_times.values().map(|readings| calculate_cpu_time_consumed(readings)).sum(),
+            busy_secs: (self.busy_cpu_times.len() > 1).then(|| calculate_cpu_time_consumed(&self.busy_cpu_times)),
+        })
+    }
+
+    /// Machine-wide utilisation at the last refresh and the share of it used by `listed`
+    ///
+    /// # Returns
+    /// `None` until the source reports machine-wide utilisation
+    pub fn system_summary(&self, listed: &[ProcessInfo]) -> Option<SystemSummary> {
+        self.system
+            .clone()
+            .map(|stats| SystemSummary::new(stats, self.process_count, listed, self.cpu_count))
+    }
+
+    /// Details of a process shown in the detail pane, looked up when needed rather than every tick
+    ///
+    /// # Returns
+    /// `None` if the process is neither running nor kept after exiting
+    pub fn process_details(&self, key: ProcessKey) -> Option<ProcessDetails> {
+        let (snapshot, running) = match self.latest.get(&key) {
+            Some(snapshot) => (snapshot, true),
+            None => (&self.exited.get(&key)?.snapshot, false),
+        };
+
+        // Follow the parents through the running processes; in focus mode only the focused ones are known
+        let by_pid: HashMap<Pid, &ProcessSnapshot> = self.latest.values().map(|s| (s.pid, s)).collect();
+        let mut ancestors = Vec::new();
+        let mut parent = snapshot.parent;
+        while let Some(pid) = parent {
+            // PID 0 is the kernel's idle task; stop at it and at loops
+            if pid.as_u32() == 0 || pid == key.pid || ancestors.iter().any(|(seen, _)| *seen == pid) {
+                break;
+            }
+            let ancestor = by_pid.get(&pid);
+            ancestors.push((pid, ancestor.map(|a| a.name.clone()).unwrap_or_default()));
+            parent = ancestor.and_then(|a| a.parent);
+        }
+
+        Some(ProcessDetails {
+            key,
+            cwd: snapshot.cwd.clone(),
+            virtual_memory_bytes: snapshot.virtual_memory_bytes,
+            thread_count: snapshot.thread_count,
+            ancestors,
+            // The PID of an exited process may already belong to another one
+            memory: if running { self.source.memory_breakdown(key.pid) } else { None },
+        })
+    }
+
+    /// Calculate average CPU percentage for each process in the retention window
+    ///
+    /// # Returns
+    /// All processes at or above the CPU threshold (or every focused process in focus mode), sorted by the configured sort key and order
+    /// (CPU percentage descending by default), with the CPU percentage from the previous [`ProcessTracker::rank_processes`] call attached
+    pub fn calculate_cpu_burn(&self) -> Vec<ProcessInfo> {
+        let mut results = self.collect_processes(true);
+        sort_processes(&mut results, self.sort_key, self.sort_order);
+        results
+    }
+
+    /// Build the result of every tracked process that passes the filter, unsorted
+    ///
+    /// # Arguments
+    /// * `apply_threshold` - Skip processes below the CPU threshold (never skipped in focus mode)
+    fn collect_processes(&self, apply_threshold: bool) -> Vec<ProcessInfo> {
+        let mut results = Vec::new();
+        let divisor = self.cpu_scale.divisor(self.cpu_count);
+
+        // The moving average replaces the window, so it only carries the latest sample
+        let windows = self.history.iter().map(|(key, samples)| (key, samples.as_slice())).chain(
+            self.ewma.iter().map(|(key, ewma)| (key, std::slice::from_ref(ewma.last_sample()))),
+        );
+
+        for (key, samples) in windows {
+            if samples.is_empty() {
+                continue;
+            }
+
+            // Use the cpu_calculator module for the calculation
+            let avg_cpu_percentage = match self.averaging {
+                AveragingMode::Mean => calculate_average_cpu_percentage(samples, divisor),
+                AveragingMode::TimeWeighted => calculate_time_weighted_cpu_percentage(
+                    samples,
+                    divisor,
+                    self.update_interval_secs as f32,
+                    self.averaging_span_secs(),
+                ),
+                AveragingMode::Ewma => self.ewma.get(key).map_or(0.0, |ewma| ewma.cpu_percentage(divisor)),
+            };
+            // Counted CPU time is exact, so it replaces the sampled average once it spans some time
+            let cpu_time_readings = self.cpu_times.get(key).filter(|_| self.cpu_time_accounting);
+            let children_cpu_time_readings = self.children_cpu_times.get(key).filter(|_| self.cpu_time_accounting);
+            let children_cpu_percentage = children_cpu_time_readings
+                .and_then(|readings| calculate_cpu_time_percentage(readings, divisor))
+                .unwrap_or(0.0);
+            let avg_cpu_percentage = cpu_time_readings
+                .and_then(|readings| calculate_cpu_time_percentage(readings, divisor))
+                .map_or(avg_cpu_percentage, |own| own + children_cpu_percentage);
+            // The moving average keeps a running peak, but no samples for a history or distribution
+            let (sample_count, peak_cpu_percent, cpu_samples, cpu_stats) = match self.ewma.get(key) {
+                Some(ewma) => (ewma.sample_count(), ewma.peak_cpu_percentage(divisor), Vec::new(), CpuStats::default()),
+                None => (
+                    samples.len(),
+                    calculate_peak_cpu_percentage(samples, divisor),
+                    normalize_cpu_samples(samples, divisor),
+                    calculate_cpu_stats(samples, divisor),
+                ),
+            };
+
+            // Filter out processes below the configured CPU threshold; focused processes are always shown
+            if apply_threshold && self.focus.is_empty() && avg_cpu_percentage < self.cpu_threshold {
+                continue;
+            }
+
+            let exited = self.exited.get(key);
+            if let Some(snapshot) = self.latest.get(key).or(exited.map(|exited| &exited.snapshot)) {
+                if !self.filter.matches(&snapshot.name, &snapshot.command_line, &snapshot.extra_info) {
+                    continue;
+                }
+
+                results.push(
+                    ProcessInfo::new(
+                        snapshot.name.clone(),
+                        key.pid,
+                        avg_cpu_percentage,
+                        snapshot.memory_bytes,
+                        snapshot.extra_info.clone(),
+                    )
+                    .with_previous_cpu_percent(self.previous_cpu_burn.get(key).copied())
+                    .with_latest_cpu_usage(samples.last().map_or(0.0, |s| s.cpu_usage))
+                    .with_sample_count(sample_count)
+                    .with_peak_cpu_percent(peak_cpu_percent)
+                    .with_command_line(snapshot.command_line.clone())
+                    .with_cpu_samples(cpu_samples)
+                    .with_cpu_stats(cpu_stats)
+                    .with_cpu_seconds(cpu_time_readings.map(|readings| calculate_cpu_time_consumed(readings)))
+                    .with_children_cpu_seconds(
+                        children_cpu_time_readings.map(|readings| calculate_cpu_time_consumed(readings)),
+                    )
+                    .with_start_time(key.start_time)
+                    .with_exited_at(exited.map(|exited| exited.exited_at))
+                    .with_parent(snapshot.parent)
+                    .with_exe(snapshot.exe.clone())
+                    .with_user(snapshot.user.clone())
+                    .with_window_title(snapshot.window_title.clone()),
+                );
+            }
+        }
+
+        results
+    }
+
+    /// Time the time-weighted average is taken over: the tracking window, or less while
+    /// the tracker has not been running that long
+    fn averaging_span_secs(&self) -> f32 {
+        match (self.first_update, self.last_update) {
+            (Some(first), Some(last)) => {
+                let running_secs = (last - first).num_milliseconds() as f32 / 1000.0 + self.update_interval_secs as f32;
+                running_secs.min(self.retention_seconds as f32)
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// Nest tracked processes under their parents with subtree CPU and memory totals
+    ///
+    /// # Returns
+    /// The top N root processes whose subtree is at or above the CPU threshold (every
+    /// focused process in focus mode), each level sorted by the configured sort key and order
+    pub fn process_tree(&self) -> Vec<TreeNode> {
+        let mut forest = build_forest(self.collect_processes(false));
+        if self.focus.is_empty() {
+            prune_forest(&mut forest, self.cpu_threshold);
+        }
+        sort_forest(&mut forest, self.sort_key, self.sort_order);
+        forest.truncate(self.top_n);
+        forest
+    }
+
+    /// Return the top N processes by average CPU percentage
+    pub fn top_processes(&self) -> Vec<ProcessInfo> {
+        let mut results = self.calculate_cpu_burn();
+        results.truncate(self.top_n);
+        results
+    }
+
+    /// Return the top N processes for this tick and remember their CPU percentages
+    /// so that the next ranking can report trends against them
+    pub fn rank_processes(&mut self) -> Vec<ProcessInfo> {
+        let mut results = self.calculate_cpu_burn();
+
+        // Update previous CPU burn for next trend calculation
+        self.previous_cpu_burn = results.iter().map(|info| (info.key(), info.cpu_percent)).collect();
+
+        results.truncate(self.top_n);
+        results
+    }
+
+    /// Aggregate tracked processes into application groups
+    ///
+    /// # Returns
+    /// The top N groups whose combined CPU percentage is at or above the CPU threshold
+    /// (every group in focus mode), sorted by the configured sort key and order
+    pub fn process_groups(&self, group_by: GroupBy) -> Vec<ProcessGroup> {
+        let mut groups = group_processes(&self.collect_processes(false), group_by);
+        if self.focus.is_empty() {
+            groups.retain(|group| group.cpu_percent >= self.cpu_threshold);
+        }
+        sort_groups(&mut groups, self.sort_key, self.sort_order);
+        groups.truncate(self.top_n);
+        groups
+    }
+
+    /// Total CPU, memory and process count per user over every tracked process
+    ///
+    /// # Returns
+    /// One group per user, regardless of the CPU threshold and top N, busiest user first
+    pub fn user_summary(&self) -> Vec<ProcessGroup> {
+        let mut users = group_processes(&self.collect_processes(false), GroupBy::User);
+        sort_groups(&mut users, SortKey::Cpu, SortOrder::Descending);
+        users
+    }
+
+    /// Display the ranked processes (as returned by [`ProcessTracker::rank_processes`])
+    pub fn display_top_processes(&mut self, results: &[ProcessInfo]) {
+        let term = Term::stdout();
+        let sort_description = describe_sort(self.sort_key, self.sort_order);
+
+        // Focus mode shows fewer processes in more detail
+        self.last_output_lines = if self.focus.is_empty() {
+            let system = self.system_summary(&results[..results.len().min(self.top_n)]);
+            // Use display module to render the output with terminal handling
+            display::display_top_processes(
+                &term,
+                results,
+                system.as_ref(),
+                self.retention_seconds,
+                self.update_interval_secs,
+                self.top_n,
+                self.cpu_scale,
+                &sort_description,
+                self.cpu_stats_columns,
+                self.last_output_lines,
+            )
+        } else {
+            display::display_focused_processes(
+                &term,
+                results,
+                self.retention_seconds,
+                self.update_interval_secs,
+                self.top_n,
+                self.cpu_scale,
+                &sort_description,
+                self.last_output_lines,
+            )
+        };
+    }
+
+    /// Display application groups (as returned by [`ProcessTracker::process_groups`])
+    pub fn display_process_groups(&mut self, groups: &[ProcessGroup], group_by: GroupBy) {
+        let term = Term::stdout();
+
+        self.last_output_lines = display::display_process_groups(
+            &term,
+            groups,
+            group_by,
+            self.retention_seconds,
+            self.update_interval_secs,
+            self.top_n,
+            self.cpu_scale,
+            &describe_sort(self.sort_key, self.sort_order),
+            self.last_output_lines,
+        );
+    }
+
+    /// Display the CPU time totals (as returned by [`ProcessTracker::cpu_time_totals`])
+    /// below the output of the previous display call
+    pub fn display_cpu_time_totals(&mut self, totals: &CpuTimeTotals) {
+        self.last_output_lines += display::display_cpu_time_totals(&Term::stdout(), totals);
+    }
+
+    /// Display the per-user totals (as returned by [`ProcessTracker::user_summary`])
+    /// below the output of the previous display call
+    pub fn display_user_summary(&mut self, users: &[ProcessGroup]) {
+        self.last_output_lines += display::display_user_summary(&Term::stdout(), users, self.cpu_scale);
+    }
+
+    /// Display a process tree (as returned by [`ProcessTracker::process_tree`]) fully expanded
+    pub fn display_process_tree(&mut self, forest: &[TreeNode]) {
+        let term = Term::stdout();
+        let rows = flatten_forest(forest, &HashSet::new());
+
+        self.last_output_lines = display::display_process_tree(
+            &term,
+            &rows,
+            self.retention_seconds,
+            self.update_interval_secs,
+            self.top_n,
+            self.cpu_scale,
+            &describe_sort(self.sort_key, self.sort_order),
+            self.last_output_lines,
+        );
+    }
+}
+
+/// A process that exited while its samples are still in the tracking window
+struct ExitedProcess {
+    snapshot: ProcessSnapshot,
+    exited_at: DateTime<Utc>,
+}
+
+impl ExitedProcess {
+    fn new(mut snapshot: ProcessSnapshot, exited_at: DateTime<Utc>) -> Self {
+        // Its memory has been released
+        snapshot.memory_bytes = 0;
+        snapshot.virtual_memory_bytes = 0;
+        Self { snapshot, exited_at }
+    }
+}
+
+/// Append a cumulative CPU time reading to the readings of one process
+///
+/// A process that started since the previous refresh consumed all of its CPU time in the
+/// window, so a zero reading at the previous refresh is added first. Processes that were
+/// already running (e.g. ones that just entered the focus) wait for a second reading instead.
+fn record_cpu_time(
+    readings: &mut Vec<CpuTimeSample>,
+    reading: CpuTimeSample,
+    previous_update: Option<DateTime<Utc>>,
+    start_time: u64,
+) {
+    let baseline = previous_update.map(|previous| CpuTimeSample::new(previous, 0.0));
+
+    match readings.last() {
+        // A counter going backwards means the PID was reused by a process whose start time is unknown
+        Some(last) if last.cpu_time_secs > reading.cpu_time_secs => {
+            readings.clear();
+            readings.extend(baseline);
+        }
+        Some(_) => {}
+        None => readings.extend(baseline.filter(|baseline| start_time as i64 >= baseline.timestamp.timestamp())),
+    }
+    readings.push(reading);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process_info::Trend;
+    use crate::process_source::{ScriptedSource, SourceTick};
+    use chrono::TimeZone;
+    use sysinfo::Pid;
+
+    fn start_time() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+    }
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        start_time() + chrono::Duration::seconds(seconds)
+    }
+
+    fn process(pid: u32, name: &str, cpu_usage: f32) -> ProcessSnapshot {
+        ProcessSnapshot::new(Pid::from_u32(pid), name, cpu_usage)
+    }
+
+    #[test]
+    fn test_trend_calculation() {
+        // Test that trend indicators are correctly determined
+        let current: f32 = 1.0;
+        let previous: f32 = 0.5;
+        let diff = current - previous;
+
+        // Should be upward trend
+        assert!(diff > 0.1);
+
+        let current: f32 = 0.5;
+        let previous: f32 = 1.0;
+        let diff = current - previous;
+
+        // Should be downward trend
+        assert!(diff < -0.1);
+
+        let current: f32 = 1.0;
+        let previous: f32 = 1.05;
+        let diff = current - previous;
+
+        // Should be stable
+        assert!(diff.abs() <= 0.1);
+    }
+
+    #[test]
+    fn test_filter_processes_below_threshold() {
+        // Test that processes below the threshold are filtered out
+        let threshold = 1.0;
+        let _tracker = ProcessTracker::new(60, threshold);
+
+        // Mock data: processes with various CPU percentages
+        // In a real scenario, these would be calculated from actual process data
+        // For this test, we're verifying the filtering logic
+
+        // Process with 0.5% CPU should be filtered
+        let cpu_below_threshold = 0.5f32;
+        assert!(cpu_below_threshold < threshold, "CPU below threshold should be less than threshold");
+
+        // Process with exactly 1% CPU should be included
+        let cpu_at_threshold = 1.0f32;
+        assert!(cpu_at_threshold >= threshold, "CPU at threshold should be >= threshold");
+
+        // Process with 1.5% CPU should be included
+        let cpu_above_threshold = 1.5f32;
+        assert!(cpu_above_threshold >= threshold, "CPU above threshold should be >= threshold");
+    }
+
+    #[test]
+    fn test_filter_edge_cases() {
+        // Test edge cases for the CPU filter
+        let threshold = 1.0;
+
+        // Just below threshold
+        let cpu = 0.99f32;
+        assert!(cpu < threshold, "0.99% should be filtered");
+
+        // Exactly at threshold
+        let cpu = 1.0f32;
+        assert!(cpu >= threshold, "1.0% should be included");
+
+        // Just above threshold
+        let cpu = 1.01f32;
+        assert!(cpu >= threshold, "1.01% should be included");
+    }
+
+    #[test]
+    fn test_custom_threshold() {
+        // Test that custom thresholds work correctly
+        let threshold_5 = 5.0;
+        let _tracker = ProcessTracker::new(60, threshold_5);
+
+        // Process with 3% CPU should be filtered with 5% threshold
+        let cpu_below = 3.0f32;
+        assert!(cpu_below < threshold_5, "3% should be filtered with 5% threshold");
+
+        // Process with 5% CPU should be included
+        let cpu_at = 5.0f32;
+        assert!(cpu_at >= threshold_5, "5% should be included with 5% threshold");
+
+        // Process with 7% CPU should be included
+        let cpu_above = 7.0f32;
+        assert!(cpu_above >= threshold_5, "7% should be included with 5% threshold");
+    }
+
+    #[test]
+    fn test_builder_defaults() {
+        let tracker = ProcessTracker::builder().build();
+
+        assert_eq!(tracker.retention_seconds(), DEFAULT_RETENTION_SECS);
+        assert_eq!(tracker.cpu_threshold(), DEFAULT_CPU_THRESHOLD);
+        assert_eq!(tracker.update_interval(), Duration::from_secs(DEFAULT_UPDATE_INTERVAL_SECS));
+        assert_eq!(tracker.top_n(), DEFAULT_TOP_N);
+        assert!(tracker.cpu_count() >= 1.0);
+    }
+
+    #[test]
+    fn test_builder_custom_values() {
+        let tracker = ProcessTracker::builder()
+            .retention_seconds(900)
+            .cpu_threshold(5.0)
+            .update_interval_secs(10)
+            .top_n(5)
+            .build();
+
+        assert_eq!(tracker.retention_seconds(), 900);
+        assert_eq!(tracker.cpu_threshold(), 5.0);
+        assert_eq!(tracker.update_interval(), Duration::from_secs(10));
+        assert_eq!(tracker.top_n(), 5);
+    }
+
+    #[test]
+    fn test_builder_clamps_zero_interval_and_retention() {
+        let tracker = ProcessTracker::builder()
+            .retention_seconds(0)
+            .update_interval_secs(0)
+            .build();
+
+        assert_eq!(tracker.retention_seconds(), 1);
+        assert_eq!(tracker.update_interval(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_top_processes_respects_top_n() {
+        let mut tracker = ProcessTracker::builder().cpu_threshold(0.0).top_n(3).build();
+        tracker.update();
+
+        assert!(tracker.top_processes().len() <= 3);
+    }
+
+    #[test]
+    fn test_ranking_sorted_by_average_cpu_descending() {
+        let source = ScriptedSource::new(1.0).with_tick(
+            at(0),
+            vec![process(1, "low", 5.0), process(2, "high", 80.0), process(3, "mid", 20.0)],
+        );
+        let mut tracker = ProcessTracker::builder().build_with_source(source);
+        tracker.update();
+
+        let names: Vec<String> = tracker.top_processes().into_iter().map(|p| p.name).collect();
+        assert_eq!(names, vec!["high", "mid", "low"]);
+    }
+
+    #[test]
+    fn test_average_normalized_by_source_cpu_count() {
+        let source = ScriptedSource::new(4.0)
+            .with_tick(at(0), vec![process(1, "app", 100.0)])
+            .with_tick(at(2), vec![process(1, "app", 300.0)]);
+        let mut tracker = ProcessTracker::builder().build_with_source(source);
+        tracker.update();
+        tracker.update();
+
+        // Average: (100 + 300) / 2 = 200, normalized on 4 cores: 50%
+        let top = tracker.top_processes();
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].cpu_percent, 50.0);
+    }
+
+    #[test]
+    fn test_threshold_filters_low_cpu_processes() {
+        let source = ScriptedSource::new(1.0).with_tick(
+            at(0),
+            vec![process(1, "below", 0.99), process(2, "at", 1.0), process(3, "above", 1.5)],
+        );
+        let mut tracker = ProcessTracker::builder().cpu_threshold(1.0).build_with_source(source);
+        tracker.update();
+
+        let mut names: Vec<String> = tracker.calculate_cpu_burn().into_iter().map(|p| p.name).collect();
+        names.sort();
+        assert_eq!(names, vec!["above", "at"]);
+    }
+
+    #[test]
+    fn test_retention_prunes_old_samples() {
+        let source = ScriptedSource::new(1.0)
+            .with_tick(at(0), vec![process(1, "app", 90.0)])
+            .with_tick(at(30), vec![process(1, "app", 10.0)])
+            .with_tick(at(61), vec![process(1, "app", 10.0)]);
+        let mut tracker = ProcessTracker::builder()
+            .retention_seconds(60)
+            .build_with_source(source);
+
+        tracker.update();
+        tracker.update();
+        assert_eq!(tracker.top_processes()[0].cpu_percent, 50.0);
+
+        // The sample at t=0 falls out of the 60 second window at t=61
+        tracker.update();
+        assert_eq!(tracker.top_processes()[0].cpu_percent, 10.0);
+    }
+
+    #[test]
+    fn test_dead_processes_are_removed() {
+        let source = ScriptedSource::new(1.0)
+            .with_tick(at(0), vec![process(1, "short", 50.0), process(2, "long", 10.0)])
+            .with_tick(at(2), vec![process(2, "long", 10.0)]);
+        let mut tracker = ProcessTracker::builder().build_with_source(source);
+
+        tracker.update();
+        assert_eq!(tracker.top_processes().len(), 2);
+
+        tracker.update();
+        let top = tracker.top_processes();
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].name, "long");
+    }
+
+    #[test]
+    fn test_latest_snapshot_details_are_reported() {
+        let source = ScriptedSource::new(1.0)
+            .with_tick(at(0), vec![process(7, "app", 10.0).with_memory(100).with_extra_info("old")])
+            .with_tick(at(2), vec![process(7, "app", 10.0).with_memory(200).with_extra_info("new")]);
+        let mut tracker = ProcessTracker::builder().build_with_source(source);
+        tracker.update();
+        tracker.update();
+
+        let top = tracker.top_processes();
+        assert_eq!(top[0].memory_bytes, 200);
+        assert_eq!(top[0].extra_info, "new");
+    }
+
+    #[test]
+    fn test_top_n_truncates_scripted_results() {
+        let processes = (1..=10).map(|pid| process(pid, "p", pid as f32 * 10.0)).collect();
+        let source = ScriptedSource::new(1.0).with_tick(at(0), processes);
+        let mut tracker = ProcessTracker::builder().top_n(3).build_with_source(source);
+        tracker.update();
+
+        let pids: Vec<u32> = tracker.top_processes().iter().map(|p| p.pid.as_u32()).collect();
+        assert_eq!(pids, vec![10, 9, 8]);
+    }
+
+    #[test]
+    fn test_rank_processes_reports_trend_against_previous_ranking() {
+        let source = ScriptedSource::new(1.0)
+            .with_tick(at(0), vec![process(1, "rising", 10.0), process(2, "falling", 50.0)])
+            .with_tick(at(2), vec![process(1, "rising", 50.0), process(2, "falling", 10.0)]);
+        let mut tracker = ProcessTracker::builder().build_with_source(source);
+
+        tracker.update();
+        let first = tracker.rank_processes();
+        assert!(first.iter().all(|info| info.trend().is_none()));
+
+        tracker.update();
+        let second = tracker.rank_processes();
+        let trend_of = |name: &str| second.iter().find(|info| info.name == name).unwrap().trend();
+        assert_eq!(trend_of("rising"), Some(Trend::Up));
+        assert_eq!(trend_of("falling"), Some(Trend::Down));
+    }
+
+    #[test]
+    fn test_latest_cpu_usage_is_last_raw_sample() {
+        let source = ScriptedSource::new(4.0)
+            .with_tick(at(0), vec![process(1, "app", 100.0)])
+            .with_tick(at(2), vec![process(1, "app", 300.0)]);
+        let mut tracker = ProcessTracker::builder().build_with_source(source);
+        tracker.update();
+        tracker.update();
+
+        let top = tracker.top_processes();
+        assert_eq!(top[0].latest_cpu_usage, 300.0);
+        assert_eq!(top[0].cpu_percent, 50.0);
+        assert_eq!(top[0].sample_count, 2);
+    }
+
+    #[test]
+    fn test_tracked_process_count_ignores_threshold() {
+        let source = ScriptedSource::new(1.0)
+            .with_tick(at(0), vec![process(1, "busy", 50.0), process(2, "idle", 0.0)]);
+        let mut tracker = ProcessTracker::builder().cpu_threshold(1.0).build_with_source(source);
+        tracker.update();
+
+        assert_eq!(tracker.tracked_process_count(), 2);
+        assert_eq!(tracker.top_processes().len(), 1);
+    }
+
+    #[test]
+    fn test_sort_by_memory_selects_top_n_memory_hogs() {
+        let source = ScriptedSource::new(1.0).with_tick(
+            at(0),
+            vec![
+                process(1, "cpu-hog", 90.0).with_memory(10),
+                process(2, "mem-hog", 2.0).with_memory(5000),
+                process(3, "medium", 5.0).with_memory(800),
+            ],
+        );
+        let mut tracker = ProcessTracker::builder()
+            .top_n(2)
+            .sort(SortKey::Memory, SortOrder::Descending)
+            .build_with_source(source);
+        tracker.update();
+
+        let names: Vec<String> = tracker.rank_processes().into_iter().map(|p| p.name).collect();
+        assert_eq!(names, vec!["mem-hog", "medium"]);
+    }
+
+    #[test]
+    fn test_set_sort_changes_next_ranking() {
+        let source = ScriptedSource::new(1.0)
+            .with_tick(at(0), vec![process(2, "b", 10.0), process(1, "a", 50.0)]);
+        let mut tracker = ProcessTracker::builder().build_with_source(source);
+        tracker.update();
+        assert_eq!(tracker.top_processes()[0].pid.as_u32(), 1);
+
+        tracker.set_sort(SortKey::Pid, SortOrder::Descending);
+        assert_eq!(tracker.sort_key(), SortKey::Pid);
+        assert_eq!(tracker.top_processes()[0].pid.as_u32(), 2);
+    }
+
+    #[test]
+    fn test_peak_cpu_percent_from_window() {
+        let source = ScriptedSource::new(2.0)
+            .with_tick(at(0), vec![process(1, "spiky", 0.0)])
+            .with_tick(at(2), vec![process(1, "spiky", 200.0)])
+            .with_tick(at(4), vec![process(1, "spiky", 0.0)]);
+        let mut tracker = ProcessTracker::builder().build_with_source(source);
+        tracker.update();
+        tracker.update();
+        tracker.update();
+
+        assert_eq!(tracker.top_processes()[0].peak_cpu_percent, 100.0);
+    }
+
+    #[test]
+    fn test_filter_applied_before_top_n() {
+        let source = ScriptedSource::new(1.0).with_tick(
+            at(0),
+            vec![
+                process(1, "chrome", 90.0),
+                process(2, "node", 50.0).with_command_line("node webpack --watch"),
+                process(3, "node", 40.0).with_command_line("node server.js"),
+                process(4, "code", 30.0).with_extra_info("webpack.config.js - Visual Studio Code"),
+            ],
+        );
+        let filter = ProcessFilter::new(
+            vec![Pattern::parse("webpack").unwrap(), Pattern::parse("node").unwrap()],
+            vec![Pattern::parse("*server.js").unwrap()],
+        );
+        let mut tracker = ProcessTracker::builder().top_n(2).filter(filter).build_with_source(source);
+        tracker.update();
+
+        let pids: Vec<u32> = tracker.rank_processes().iter().map(|p| p.pid.as_u32()).collect();
+        assert_eq!(pids, vec![2, 4]);
+    }
+
+    #[test]
+    fn test_query_narrows_ranking() {
+        let source = ScriptedSource::new(1.0)
+            .with_tick(at(0), vec![process(1, "cargo", 50.0), process(2, "rustc", 40.0)]);
+        let mut tracker = ProcessTracker::builder().build_with_source(source);
+        tracker.update();
+
+        tracker.set_query(Some(Pattern::parse("rust*").unwrap()));
+        let names: Vec<String> = tracker.top_processes().into_iter().map(|p| p.name).collect();
+        assert_eq!(names, vec!["rustc"]);
+
+        tracker.set_query(None);
+        assert_eq!(tracker.top_processes().len(), 2);
+    }
+
+    #[test]
+    fn test_last_update_uses_source_timestamp() {
+        let source = ScriptedSource::new(1.0).with_tick(at(4), vec![process(1, "app", 10.0)]);
+        let mut tracker = ProcessTracker::builder().build_with_source(source);
+
+        assert_eq!(tracker.last_update(), None);
+        tracker.update();
+        assert_eq!(tracker.last_update(), Some(at(4)));
+    }
+
+    #[test]
+    fn test_focus_keeps_history_only_for_focused_processes() {
+        let source = ScriptedSource::new(1.0).with_tick(
+            at(0),
+            vec![process(1, "api", 0.2), process(2, "chrome", 90.0), process(3, "api", 0.0)],
+        );
+        let focus = FocusSet::new(Vec::new(), vec![Pattern::parse("api").unwrap()]);
+        let mut tracker = ProcessTracker::builder().cpu_threshold(5.0).focus(focus).build_with_source(source);
+        tracker.update();
+
+        assert_eq!(tracker.tracked_process_count(), 2);
+
+        // Focused processes are reported even though they are below the threshold
+        let pids: Vec<u32> = tracker.top_processes().iter().map(|p| p.pid.as_u32()).collect();
+        assert_eq!(pids, vec![1, 3]);
+    }
+
+    #[test]
+    fn test_focus_follows_descendants_started_later() {
+        let source = ScriptedSource::new(1.0)
+            .with_tick(at(0), vec![process(10, "make", 1.0), process(20, "bash", 5.0)])
+            .with_tick(
+                at(2),
+                vec![
+                    process(10, "make", 1.0),
+                    process(11, "cc", 80.0).with_parent(Pid::from_u32(10)),
+                    process(20, "bash", 5.0),
+                ],
+            );
+        let focus = FocusSet::new(vec![Pid::from_u32(10)], Vec::new()).with_descendants(true);
+        let mut tracker = ProcessTracker::builder().focus(focus).build_with_source(source);
+
+        tracker.update();
+        assert_eq!(tracker.tracked_process_count(), 1);
+
+        tracker.update();
+        let pids: Vec<u32> = tracker.top_processes().iter().map(|p| p.pid.as_u32()).collect();
+        assert_eq!(pids, vec![11, 10]);
+    }
+
+    #[test]
+    fn test_cpu_samples_are_normalized_oldest_first() {
+        let source = ScriptedSource::new(2.0)
+            .with_tick(at(0), vec![process(1, "app", 20.0)])
+            .with_tick(at(2), vec![process(1, "app", 60.0)]);
+        let mut tracker = ProcessTracker::builder().build_with_source(source);
+        tracker.update();
+        tracker.update();
+
+        assert_eq!(tracker.top_processes()[0].cpu_samples, vec![10.0, 30.0]);
+    }
+
+    #[test]
+    fn test_process_tree_nests_children_and_ignores_child_threshold() {
+        let source = ScriptedSource::new(1.0).with_tick(
+            at(0),
+            vec![
+                process(10, "browser", 1.0).with_memory(100),
+                process(11, "tab", 0.5).with_memory(50).with_parent(Pid::from_u32(10)),
+                process(12, "tab", 0.5).with_memory(50).with_parent(Pid::from_u32(10)),
+                process(20, "idle", 0.2),
+                process(30, "compiler", 3.0),
+            ],
+        );
+        let mut tracker = ProcessTracker::builder().cpu_threshold(1.5).build_with_source(source);
+        tracker.update();
+
+        // Every process is below or near the threshold on its own, but the browser tree adds up to 2%
+        let forest = tracker.process_tree();
+        let roots: Vec<u32> = forest.iter().map(|n| n.info.pid.as_u32()).collect();
+        assert_eq!(roots, vec![30, 10]);
+        assert_eq!(forest[1].subtree_cpu_percent, 2.0);
+        assert_eq!(forest[1].subtree_memory_bytes, 200);
+        assert_eq!(forest[1].children.len(), 0, "children below the threshold are hidden");
+        assert_eq!(forest[1].info.parent, None);
+    }
+
+    #[test]
+    fn test_process_tree_truncates_roots_to_top_n() {
+        let source = ScriptedSource::new(1.0).with_tick(
+            at(0),
+            vec![
+                process(1, "a", 50.0),
+                process(2, "a-child", 50.0).with_parent(Pid::from_u32(1)),
+                process(3, "b", 30.0),
+                process(4, "c", 20.0),
+            ],
+        );
+        let mut tracker = ProcessTracker::builder().top_n(2).build_with_source(source);
+        tracker.update();
+
+        let forest = tracker.process_tree();
+        assert_eq!(forest.len(), 2);
+        assert_eq!(forest[0].descendant_count(), 1);
+        assert_eq!(forest[1].info.pid.as_u32(), 3);
+    }
+
+    #[test]
+    fn test_process_groups_sum_instances_below_threshold() {
+        let source = ScriptedSource::new(1.0).with_tick(
+            at(0),
+            vec![
+                process(1, "chrome", 0.8).with_memory(100),
+                process(2, "chrome", 0.8).with_memory(200),
+                process(3, "chrome", 0.8).with_memory(300),
+                process(4, "rustc", 2.0).with_memory(50),
+                process(5, "sshd", 0.1),
+            ],
+        );
+        let mut tracker = ProcessTracker::builder().cpu_threshold(1.0).build_with_source(source);
+        tracker.update();
+
+        // No chrome process reaches the threshold on its own, but the application does
+        let groups = tracker.process_groups(GroupBy::Name);
+        let keys: Vec<&str> = groups.iter().map(|g| g.key.as_str()).collect();
+        assert_eq!(keys, vec!["chrome", "rustc"]);
+        assert_eq!(groups[0].instance_count(), 3);
+        assert_eq!(groups[0].memory_bytes, 600);
+    }
+
+    #[test]
+    fn test_process_groups_by_user_truncated_to_top_n() {
+        let source = ScriptedSource::new(1.0).with_tick(
+            at(0),
+            vec![
+                process(1, "a", 10.0).with_user("alice"),
+                process(2, "b", 20.0).with_user("bob"),
+                process(3, "c", 30.0).with_user("alice"),
+            ],
+        );
+        let mut tracker = ProcessTracker::builder().top_n(1).build_with_source(source);
+        tracker.update();
+
+        let groups = tracker.process_groups(GroupBy::User);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].key, "alice");
+        assert_eq!(groups[0].cpu_percent, 40.0);
+    }
+
+    #[test]
+    fn test_user_summary_covers_all_tracked_processes() {
+        let source = ScriptedSource::new(2.0).with_tick(
+            at(0),
+            vec![
+                process(1, "cc1", 100.0).with_user("ci").with_memory(100),
+                process(2, "cc1", 60.0).with_user("ci").with_memory(100),
+                process(3, "bash", 0.2).with_user("alice").with_memory(10),
+                process(4, "vim", 1.0).with_user("alice").with_memory(20),
+            ],
+        );
+        let mut tracker = ProcessTracker::builder().cpu_threshold(5.0).top_n(1).build_with_source(source);
+        tracker.update();
+
+        let users = tracker.user_summary();
+        let names: Vec<&str> = users.iter().map(|u| u.key.as_str()).collect();
+        assert_eq!(names, vec!["ci", "alice"]);
+        assert_eq!(users[0].cpu_percent, 80.0);
+        assert_eq!(users[1].cpu_percent, 0.6);
+        assert_eq!(users[1].instance_count(), 2);
+        assert_eq!(users[1].memory_bytes, 30);
+    }
+
+    #[test]
+    fn test_time_weighted_averaging_weights_uneven_ticks() {
+        let source = ScriptedSource::new(1.0)
+            .with_tick(at(0), vec![process(1, "app", 0.0)])
+            .with_tick(at(2), vec![process(1, "app", 0.0)])
+            // The loop stalled for 6 seconds while the process was busy
+            .with_tick(at(8), vec![process(1, "app", 100.0)]);
+        let mut mean = ProcessTracker::builder().cpu_threshold(0.0).build_with_source(source.clone());
+        let mut weighted = ProcessTracker::builder()
+            .cpu_threshold(0.0)
+            .averaging(AveragingMode::TimeWeighted)
+            .build_with_source(source);
+        for _ in 0..3 {
+            mean.update();
+            weighted.update();
+        }
+
+        assert_eq!(weighted.averaging(), AveragingMode::TimeWeighted);
+        assert!((mean.top_processes()[0].cpu_percent - 100.0 / 3.0).abs() < 0.001);
+        // (0 * 2 + 0 * 2 + 100 * 6) / 10 seconds
+        assert_eq!(weighted.top_processes()[0].cpu_percent, 60.0);
+    }
+
+    #[test]
+    fn test_time_weighted_averaging_does_not_overweight_brief_processes() {
+        let mut source = ScriptedSource::new(1.0);
+        for tick in 0..10 {
+            let mut processes = vec![process(1, "steady", 20.0)];
+            if tick == 9 {
+                processes.push(process(2, "blip", 100.0));
+            }
+            source.push_tick(at(tick * 2), processes);
+        }
+        let mut tracker = ProcessTracker::builder()
+            .retention_seconds(20)
+            .averaging(AveragingMode::TimeWeighted)
+            .build_with_source(source);
+        for _ in 0..10 {
+            tracker.update();
+        }
+
+        // The blip burned 2 CPU-seconds in a 20 second window: 10%, not 100%
+        let top = tracker.top_processes();
+        assert_eq!(top[0].name, "steady");
+        assert_eq!(top[0].cpu_percent, 20.0);
+        assert_eq!(top[1].name, "blip");
+        assert_eq!(top[1].cpu_percent, 10.0);
+    }
+
+    #[test]
+    fn test_cpu_stats_of_tracked_processes() {
+        let mut source = ScriptedSource::new(2.0);
+        for tick in 0..10 {
+            let spike = if tick == 4 { 200.0 } else { 20.0 };
+            source.push_tick(at(tick * 2), vec![process(1, "spiky", spike), process(2, "steady", 38.0)]);
+        }
+        let mut tracker = ProcessTracker::builder().build_with_source(source);
+        for _ in 0..10 {
+            tracker.update();
+        }
+
+        // Both average 19%, but only one of them spikes
+        let top = tracker.top_processes();
+        let spiky = top.iter().find(|p| p.name == "spiky").unwrap();
+        let steady = top.iter().find(|p| p.name == "steady").unwrap();
+        assert_eq!(spiky.cpu_percent, 19.0);
+        assert_eq!(steady.cpu_percent, 19.0);
+        assert_eq!(spiky.cpu_stats.p50, 10.0);
+        assert_eq!(spiky.cpu_stats.max, 100.0);
+        assert_eq!(spiky.cpu_stats.last, 10.0);
+        assert!(spiky.cpu_stats.stddev > 25.0);
+        assert_eq!(steady.cpu_stats.max, 19.0);
+        assert_eq!(steady.cpu_stats.stddev, 0.0);
+    }
+
+    #[test]
+    fn test_ewma_averaging_keeps_constant_state() {
+        let mut source = ScriptedSource::new(1.0);
+        for tick in 0..50 {
+            source.push_tick(at(tick * 2), vec![process(1, "app", 40.0)]);
+        }
+        let mut tracker = ProcessTracker::builder()
+            .averaging(AveragingMode::Ewma)
+            .ewma_half_life_secs(4.0)
+            .build_with_source(source);
+        for _ in 0..50 {
+            tracker.update();
+        }
+
+        assert!(tracker.history.is_empty());
+        assert_eq!(tracker.ewma.len(), 1);
+        let top = tracker.top_processes();
+        assert_eq!(top[0].cpu_percent, 40.0);
+        assert_eq!(top[0].sample_count, 50);
+    }
+
+    #[test]
+    fn test_ewma_averaging_trend_follows_smoothed_signal() {
+        let mut source = ScriptedSource::new(1.0);
+        source.push_tick(at(0), vec![process(1, "app", 100.0)]);
+        for tick in 1..4 {
+            source.push_tick(at(tick * 2), vec![process(1, "app", 0.0)]);
+        }
+        let mut tracker = ProcessTracker::builder()
+            .averaging(AveragingMode::Ewma)
+            .ewma_half_life_secs(2.0)
+            .build_with_source(source);
+
+        tracker.update();
+        assert_eq!(tracker.rank_processes()[0].cpu_percent, 100.0);
+
+        // After the spike ends the smoothed value halves every tick and trends down
+        for expected in [50.0, 25.0, 12.5] {
+            tracker.update();
+            let ranked = tracker.rank_processes();
+            assert_eq!(ranked[0].cpu_percent, expected);
+            assert_eq!(ranked[0].trend(), Some(Trend::Down));
+        }
+    }
+
+    #[test]
+    fn test_ewma_averaging_sorts_by_running_peak() {
+        let source = ScriptedSource::new(1.0)
+            .with_tick(at(0), vec![process(1, "spiky", 90.0), process(2, "steady", 30.0)])
+            .with_tick(at(2), vec![process(1, "spiky", 0.0), process(2, "steady", 30.0)])
+            .with_tick(at(4), vec![process(1, "spiky", 0.0), process(2, "steady", 30.0)]);
+        let mut tracker = ProcessTracker::builder()
+            .averaging(AveragingMode::Ewma)
+            .ewma_half_life_secs(2.0)
+            .sort(SortKey::PeakCpu, SortOrder::Descending)
+            .build_with_source(source);
+        for _ in 0..3 {
+            tracker.update();
+        }
+
+        // The spike outranks the steady process although only the latest sample is kept
+        let ranked = tracker.rank_processes();
+        assert_eq!(ranked[0].name, "spiky");
+        assert_eq!(ranked[0].peak_cpu_percent, 90.0);
+        assert!(ranked[0].cpu_percent < ranked[1].cpu_percent);
+        assert!(ranked[0].cpu_samples.is_empty());
+        assert_eq!(ranked[0].cpu_stats, CpuStats::default());
+    }
+
+    #[test]
+    fn test_ewma_state_dropped_with_exited_processes() {
+        let source = ScriptedSource::new(1.0)
+            .with_tick(at(0), vec![process(1, "a", 10.0), process(2, "b", 10.0)])
+            .with_tick(at(2), vec![process(1, "a", 10.0)]);
+        let mut tracker = ProcessTracker::builder()
+            .averaging(AveragingMode::Ewma)
+            .build_with_source(source);
+        tracker.update();
+        tracker.update();
+
+        assert_eq!(tracker.ewma.len(), 1);
+        assert_eq!(tracker.tracked_process_count(), 1);
+    }
+
+    fn timed_process(pid: u32, name: &str, cpu_usage: f32, cpu_time_secs: f64) -> ProcessSnapshot {
+        process(pid, name, cpu_usage).with_cpu_time_secs(cpu_time_secs)
+    }
+
+    #[test]
+    fn test_cpu_time_accounting_reports_exact_cpu_seconds() {
+        // The sampled usage misses the work done between the samples
+        let source = ScriptedSource::new(2.0)
+            .with_tick(at(0), vec![timed_process(1, "batch", 0.0, 10.0)])
+            .with_tick(at(2), vec![timed_process(1, "batch", 0.0, 12.0)])
+            .with_tick(at(4), vec![timed_process(1, "batch", 0.0, 13.0)]);
+        let mut tracker = ProcessTracker::builder().cpu_time_accounting(true).build_with_source(source);
+        for _ in 0..3 {
+            tracker.update();
+        }
+
+        let top = tracker.top_processes();
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].cpu_seconds, Some(3.0));
+        // 3 CPU-seconds over 4 seconds on 2 cores
+        assert_eq!(top[0].cpu_percent, 37.5);
+    }
+
+    #[test]
+    fn test_cpu_time_totals_include_exited_processes() {
+        let mut source = ScriptedSource::new(1.0);
+        source.push_source_tick(
+            SourceTick::new(at(0), vec![timed_process(1, "a", 50.0, 100.0)]).with_busy_cpu_secs(1000.0),
+        );
+        // Process 2 starts after the first refresh, so all of its CPU time is counted
+        let short = timed_process(2, "short", 0.0, 1.5).with_start_time(at(1).timestamp() as u64);
+        source.push_source_tick(
+            SourceTick::new(at(2), vec![timed_process(1, "a", 50.0, 101.0), short])
+                .with_busy_cpu_secs(1002.5),
+        );
+        source.push_source_tick(
+            SourceTick::new(at(4), vec![timed_process(1, "a", 50.0, 102.0)]).with_busy_cpu_secs(1004.0),
+        );
+        let mut tracker = ProcessTracker::builder().cpu_time_accounting(true).build_with_source(source);
+        for _ in 0..3 {
+            tracker.update();
+        }
+
+        assert_eq!(tracker.top_processes().len(), 1);
+        let totals = tracker.cpu_time_totals().unwrap();
+        assert_eq!(totals.process_secs, 3.5);
+        assert_eq!(totals.busy_secs, Some(4.0));
+    }
+
+    #[test]
+    fn test_system_summary_from_latest_tick() {
+        let stats = SystemStats { cpu_percent: 60.0, core_percents: vec![100.0, 20.0], ..SystemStats::default() };
+        let mut source = ScriptedSource::new(2.0);
+        source.push_source_tick(
+            SourceTick::new(at(0), vec![process(1, "busy", 80.0), process(2, "idle", 0.0), process(3, "other", 30.0)])
+                .with_system(stats.clone()),
+        );
+        let mut tracker = ProcessTracker::builder().build_with_source(source);
+        assert_eq!(tracker.system_summary(&[]), None);
+        tracker.update();
+
+        let top = tracker.top_processes();
+        let summary = tracker.system_summary(&top[..1]).unwrap();
+        assert_eq!(summary.stats, stats);
+        assert_eq!(summary.process_count, 3);
+        assert_eq!(summary.listed_count, 1);
+        // 80% of one core on two cores
+        assert_eq!(summary.listed_cpu_percent, 40.0);
+        assert_eq!(summary.other_cpu_percent(), 20.0);
+    }
+
+    #[test]
+    fn test_cpu_time_readings_reset_when_counter_goes_backwards() {
+        // A lower reading means the PID was reused by a new process
+        let source = ScriptedSource::new(1.0)
+            .with_tick(at(0), vec![timed_process(7, "old", 0.0, 500.0)])
+            .with_tick(at(2), vec![timed_process(7, "new", 0.0, 0.5)]);
+        let mut tracker = ProcessTracker::builder().cpu_time_accounting(true).build_with_source(source);
+        tracker.update();
+        tracker.update();
+
+        assert_eq!(tracker.top_processes()[0].cpu_seconds, Some(0.5));
+        assert_eq!(tracker.cpu_time_totals().unwrap().process_secs, 0.5);
+    }
+
+    #[test]
+    fn test_cpu_time_of_process_entering_focus_late_waits_for_second_reading() {
+        // The worker was already running before the first refresh but only matches the focus later
+        let worker = |cpu_time_secs| {
+            timed_process(2, "worker", 0.0, cpu_time_secs)
+                .with_start_time(at(-3600).timestamp() as u64)
+                .with_parent(Pid::from_u32(1))
+        };
+        let source = ScriptedSource::new(1.0)
+            .with_tick(at(0), vec![timed_process(1, "shell", 0.0, 1.0)])
+            .with_tick(at(2), vec![timed_process(1, "shell", 0.0, 1.0), worker(500.0)])
+            .with_tick(at(4), vec![timed_process(1, "shell", 0.0, 1.0), worker(501.5)]);
+        let mut tracker = ProcessTracker::builder()
+            .cpu_time_accounting(true)
+            .focus(FocusSet::new(Vec::new(), vec![Pattern::parse("worker").unwrap()]))
+            .build_with_source(source);
+
+        tracker.update();
+        tracker.update();
+        assert_eq!(tracker.top_processes()[0].cpu_seconds, Some(0.0));
+
+        tracker.update();
+        assert_eq!(tracker.top_processes()[0].cpu_seconds, Some(1.5));
+        assert_eq!(tracker.cpu_time_totals().unwrap().process_secs, 1.5);
+    }
+
+    #[test]
+    fn test_cpu_time_ignored_without_accounting() {
+        let source = ScriptedSource::new(1.0)
+            .with_tick(at(0), vec![timed_process(1, "a", 20.0, 10.0)])
+            .with_tick(at(2), vec![timed_process(1, "a", 20.0, 12.0)]);
+        let mut tracker = ProcessTracker::builder().build_with_source(source);
+        tracker.update();
+        tracker.update();
+
+        let top = tracker.top_processes();
+        assert_eq!(top[0].cpu_percent, 20.0);
+        assert_eq!(top[0].cpu_seconds, None);
+        assert!(tracker.cpu_time_totals().is_none());
+    }
+
+    #[test]
+    fn test_reaped_children_attributed_to_parent() {
+        // make barely runs itself, but its compilers exit between samples
+        let make = |cpu_time_secs: f64, children_cpu_time_secs: f64| {
+            timed_process(1, "make", 0.5, cpu_time_secs).with_children_cpu_time_secs(children_cpu_time_secs)
+        };
+        let source = ScriptedSource::new(1.0)
+            .with_tick(at(0), vec![make(1.0, 100.0)])
+            .with_tick(at(2), vec![make(1.1, 101.9)])
+            .with_tick(at(4), vec![make(1.2, 103.8)]);
+        let mut tracker = ProcessTracker::builder()
+            .cpu_time_accounting(true)
+            .reaped_children(true)
+            .build_with_source(source);
+        for _ in 0..3 {
+            tracker.update();
+        }
+
+        let top = tracker.top_processes();
+        assert!((top[0].cpu_seconds.unwrap() - 0.2).abs() < 1e-9);
+        assert!((top[0].children_cpu_seconds.unwrap() - 3.8).abs() < 1e-9);
+        // 4 CPU-seconds over 4 seconds on one core
+        assert!((top[0].cpu_percent - 100.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_reaped_children_ignored_unless_requested() {
+        let source = ScriptedSource::new(1.0)
+            .with_tick(at(0), vec![timed_process(1, "make", 0.0, 1.0).with_children_cpu_time_secs(100.0)])
+            .with_tick(at(2), vec![timed_process(1, "make", 0.0, 1.0).with_children_cpu_time_secs(102.0)]);
+        let mut tracker = ProcessTracker::builder()
+            .cpu_threshold(0.0)
+            .cpu_time_accounting(true)
+            .build_with_source(source);
+        tracker.update();
+        tracker.update();
+
+        let ranked = tracker.rank_processes();
+        assert_eq!(ranked[0].cpu_percent, 0.0);
+        assert_eq!(ranked[0].children_cpu_seconds, None);
+    }
+
+    #[test]
+    fn test_exited_processes_dropped_by_default() {
+        let source = ScriptedSource::new(1.0)
+            .with_tick(at(0), vec![process(1, "a", 10.0), process(2, "burst", 100.0)])
+            .with_tick(at(2), vec![process(1, "a", 10.0)]);
+        let mut tracker = ProcessTracker::builder().build_with_source(source);
+        tracker.update();
+        tracker.update();
+
+        let top = tracker.top_processes();
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].exited_at, None);
+    }
+
+    #[test]
+    fn test_keep_exited_until_samples_leave_window() {
+        let mut source = ScriptedSource::new(1.0);
+        source.push_tick(at(0), vec![process(1, "a", 10.0), process(2, "burst", 100.0).with_memory(4096)]);
+        for tick in 1..=5 {
+            source.push_tick(at(tick * 2), vec![process(1, "a", 10.0)]);
+        }
+        let mut tracker = ProcessTracker::builder()
+            .retention_seconds(6)
+            .update_interval_secs(2)
+            .keep_exited(true)
+            .build_with_source(source);
+        tracker.update();
+        tracker.update();
+
+        let top = tracker.top_processes();
+        assert_eq!(top.len(), 2);
+        assert_eq!(top[0].name, "burst");
+        assert_eq!(top[0].cpu_percent, 100.0);
+        assert_eq!(top[0].exited_at, Some(at(2)));
+        assert_eq!(top[0].memory_bytes, 0);
+        assert_eq!(top[1].exited_at, None);
+
+        // The exit time stays put while the sample ages out of the 6 second window
+        tracker.update();
+        tracker.update();
+        assert_eq!(tracker.top_processes()[0].exited_at, Some(at(2)));
+        tracker.update();
+        let top = tracker.top_processes();
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].name, "a");
+        assert_eq!(tracker.tracked_process_count(), 1);
+    }
+
+    #[test]
+    fn test_keep_exited_process_that_returns_is_live_again() {
+        let source = ScriptedSource::new(1.0)
+            .with_tick(at(0), vec![process(1, "flaky", 10.0)])
+            .with_tick(at(2), vec![])
+            .with_tick(at(4), vec![process(1, "flaky", 10.0)]);
+        let mut tracker = ProcessTracker::builder().keep_exited(true).build_with_source(source);
+        tracker.update();
+        tracker.update();
+        assert!(tracker.top_processes()[0].exited_at.is_some());
+
+        tracker.update();
+        assert_eq!(tracker.top_processes()[0].exited_at, None);
+    }
+
+    /// A process started at the given second, so a later one can reuse its PID
+    fn started_process(pid: u32, exe: &str, cpu_usage: f32, start_time: u64) -> ProcessSnapshot {
+        process(pid, exe.rsplit('/').next().unwrap_or(exe), cpu_usage)
+            .with_exe(exe)
+            .with_start_time(start_time)
+    }
+
+    #[test]
+    fn test_reused_pid_starts_a_new_history() {
+        let source = ScriptedSource::new(1.0)
+            .with_tick(at(0), vec![started_process(7, "/usr/bin/old", 100.0, 1000)])
+            .with_tick(at(2), vec![started_process(7, "/usr/bin/old", 100.0, 1000)])
+            .with_tick(at(4), vec![started_process(7, "/usr/bin/new", 2.0, 1003)]);
+        let mut tracker = ProcessTracker::builder().build_with_source(source);
+        for _ in 0..3 {
+            tracker.update();
+        }
+
+        let top = tracker.top_processes();
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].exe, "/usr/bin/new");
+        // Not (100 + 100 + 2) / 3 from the old process's samples
+        assert_eq!(top[0].cpu_percent, 2.0);
+        assert_eq!(top[0].sample_count, 1);
+        assert_eq!(top[0].start_time, 1003);
+        assert_eq!(tracker.tracked_process_count(), 1);
+    }
+
+    #[test]
+    fn test_reused_pid_has_no_trend_against_old_process() {
+        let source = ScriptedSource::new(1.0)
+            .with_tick(at(0), vec![started_process(7, "/usr/bin/old", 90.0, 1000)])
+            .with_tick(at(2), vec![started_process(7, "/usr/bin/new", 5.0, 1001)]);
+        let mut tracker = ProcessTracker::builder().build_with_source(source);
+        tracker.update();
+        assert_eq!(tracker.rank_processes()[0].cpu_percent, 90.0);
+
+        tracker.update();
+        let ranked = tracker.rank_processes();
+        assert_eq!(ranked[0].exe, "/usr/bin/new");
+        assert_eq!(ranked[0].previous_cpu_percent, None);
+        assert_eq!(ranked[0].trend(), None);
+    }
+
+    #[test]
+    fn test_reused_pid_listed_next_to_exited_process() {
+        let source = ScriptedSource::new(1.0)
+            .with_tick(at(0), vec![started_process(7, "/usr/bin/old", 90.0, 1000)])
+            .with_tick(at(2), vec![started_process(7, "/usr/bin/new", 5.0, 1001)]);
+        let mut tracker = ProcessTracker::builder().keep_exited(true).build_with_source(source);
+        tracker.update();
+        tracker.update();
+
+        let top = tracker.top_processes();
+        assert_eq!(top.len(), 2);
+        assert_eq!((top[0].exe.as_str(), top[0].exited_at), ("/usr/bin/old", Some(at(2))));
+        assert_eq!((top[1].exe.as_str(), top[1].exited_at), ("/usr/bin/new", None));
+        assert_eq!(top[0].pid, top[1].pid);
+    }
+
+    #[test]
+    fn test_reused_pid_keeps_separate_subtrees() {
+        let source = ScriptedSource::new(1.0)
+            .with_tick(
+                at(0),
+                vec![
+                    started_process(7, "/usr/bin/old", 30.0, 1000),
+                    started_process(8, "/usr/bin/job", 10.0, 1001).with_parent(Pid::from_u32(7)),
+                ],
+            )
+            .with_tick(
+                at(2),
+                vec![
+                    started_process(7, "/usr/bin/new", 5.0, 2000),
+                    started_process(9, "/usr/bin/task", 20.0, 2001).with_parent(Pid::from_u32(7)),
+                ],
+            );
+        let mut tracker = ProcessTracker::builder().keep_exited(true).build_with_source(source);
+        tracker.update();
+        tracker.update();
+
+        let mut tree: Vec<(String, Vec<String>)> = tracker
+            .process_tree()
+            .iter()
+            .map(|node| (node.info.name.clone(), node.children.iter().map(|c| c.info.name.clone()).collect()))
+            .collect();
+        tree.sort();
+        assert_eq!(
+            tree,
+            vec![
+                ("new".to_string(), vec!["task".to_string()]),
+                ("old".to_string(), vec!["job".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reused_pid_cpu_time_counted_from_start() {
+        let source = ScriptedSource::new(1.0)
+            .with_tick(at(0), vec![started_process(7, "/usr/bin/old", 0.0, 1000).with_cpu_time_secs(0.5)])
+            .with_tick(
+                at(2),
+                vec![started_process(7, "/usr/bin/new", 0.0, at(1).timestamp() as u64).with_cpu_time_secs(1.2)],
+            );
+        let mut tracker = ProcessTracker::builder().cpu_time_accounting(true).build_with_source(source);
+        tracker.update();
+        tracker.update();
+
+        // A counter that happens to be higher is still not a delta against the old process
+        let top = tracker.top_processes();
+        assert_eq!(top[0].exe, "/usr/bin/new");
+        assert_eq!(top[0].cpu_seconds, Some(1.2));
+    }
+
+    #[test]
+    fn test_process_details_follow_parent_chain() {
+        let source = ScriptedSource::new(1.0).with_tick(
+            at(0),
+            vec![
+                process(1, "init", 0.0),
+                process(40, "sshd", 0.0).with_parent(Pid::from_u32(1)),
+                process(41, "bash", 0.0).with_parent(Pid::from_u32(40)),
+                process(42, "make", 90.0)
+                    .with_parent(Pid::from_u32(41))
+                    .with_cwd("/src/app")
+                    .with_virtual_memory(8192)
+                    .with_thread_count(4),
+            ],
+        );
+        let mut tracker = ProcessTracker::builder().build_with_source(source);
+        tracker.update();
+
+        let key = ProcessKey::new(Pid::from_u32(42), 0);
+        let details = tracker.process_details(key).unwrap();
+        assert_eq!(details.key, key);
+        assert_eq!(details.cwd, "/src/app");
+        assert_eq!(details.virtual_memory_bytes, 8192);
+        assert_eq!(details.thread_count, Some(4));
+        let ancestors: Vec<(u32, &str)> = details.ancestors.iter().map(|(pid, name)| (pid.as_u32(), name.as_str())).collect();
+        assert_eq!(ancestors, vec![(41, "bash"), (40, "sshd"), (1, "init")]);
+        // Scripted sources know no memory breakdown
+        assert_eq!(details.memory, None);
+
+        assert_eq!(tracker.process_details(ProcessKey::new(Pid::from_u32(99), 0)), None);
+    }
+
+    #[test]
+    fn test_process_details_of_untracked_parent_and_exited_process() {
+        let source = ScriptedSource::new(1.0)
+            .with_tick(at(0), vec![process(7, "worker", 50.0).with_parent(Pid::from_u32(3)).with_virtual_memory(4096)])
+            .with_tick(at(2), vec![]);
+        let mut tracker = ProcessTracker::builder().keep_exited(true).build_with_source(source);
+        tracker.update();
+
+        let key = ProcessKey::new(Pid::from_u32(7), 0);
+        let details = tracker.process_details(key).unwrap();
+        assert_eq!(details.ancestors, vec![(Pid::from_u32(3), String::new())]);
+
+        tracker.update();
+        let details = tracker.process_details(key).unwrap();
+        assert_eq!(details.virtual_memory_bytes, 0);
+        assert_eq!(details.memory, None);
+    }
+}
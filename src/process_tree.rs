@@ -0,0 +1,403 @@
+use crate::process_source::ProcessKey;
+use crate::sorting::{compare_processes, SortKey, SortOrder};
+use crate::ProcessInfo;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use sysinfo::Pid;
+
+/// A process with its children and the CPU and memory of its whole subtree
+#[derive(Debug, Clone)]
+pub struct TreeNode {
+    pub info: ProcessInfo,
+    /// Average CPU percentage of this process and all its descendants
+    pub subtree_cpu_percent: f32,
+    /// Memory of this process and all its descendants in bytes
+    pub subtree_memory_bytes: u64,
+    pub children: Vec<TreeNode>,
+}
+
+impl TreeNode {
+    fn new(info: ProcessInfo, children: Vec<TreeNode>) -> Self {
+        let subtree_cpu_percent = info.cpu_percent + children.iter().map(|c| c.subtree_cpu_percent).sum::<f32>();
+        let subtree_memory_bytes = info.memory_bytes + children.iter().map(|c| c.subtree_memory_bytes).sum::<u64>();
+
+        Self {
+            info,
+            subtree_cpu_percent,
+            subtree_memory_bytes,
+            children,
+        }
+    }
+
+    /// Number of processes below this one
+    pub fn descendant_count(&self) -> usize {
+        self.children.iter().map(|c| 1 + c.descendant_count()).sum()
+    }
+}
+
+/// One visible line of a flattened process tree
+#[derive(Debug, Clone)]
+pub struct TreeRow {
+    pub info: ProcessInfo,
+    /// Nesting level, 0 for root processes
+    pub depth: usize,
+    /// Box-drawing guide drawn before the process name (e.g. "│  └─ ")
+    pub prefix: String,
+    pub subtree_cpu_percent: f32,
+    pub subtree_memory_bytes: u64,
+    /// Number of direct children, including collapsed ones
+    pub child_count: usize,
+    pub expanded: bool,
+}
+
+impl TreeRow {
+    /// Process name with the tree guide and a collapsed marker
+    pub fn display_name(&self) -> String {
+        let marker = if self.child_count > 0 && !self.expanded { "+ " } else { "" };
+        format!("{}{}{}", self.prefix, marker, self.info.name)
+    }
+}
+
+/// Nest processes under their parents
+///
+/// Processes are told apart by [`ProcessKey`], so an exited process and the one that reused
+/// its PID are separate nodes. Processes whose parent is not in the list become roots. Parent
+/// links that form a cycle (possible after PID reuse) are broken at the lowest PID of the cycle.
+///
+/// # Examples
+/// ```
+/// use process_shepherd::process_tree::build_forest;
+/// use process_shepherd::ProcessInfo;
+/// use sysinfo::Pid;
+///
+/// let browser = ProcessInfo::new("browser".to_string(), Pid::from_u32(10), 2.0, 100, String::new());
+/// let tab = ProcessInfo::new("tab".to_string(), Pid::from_u32(11), 5.0, 50, String::new())
+///     .with_parent(Some(Pid::from_u32(10)));
+///
+/// let forest = build_forest(vec![browser, tab]);
+/// assert_eq!(forest.len(), 1);
+/// assert_eq!(forest[0].subtree_cpu_percent, 7.0);
+/// assert_eq!(forest[0].subtree_memory_bytes, 150);
+/// ```
+pub fn build_forest(processes: Vec<ProcessInfo>) -> Vec<TreeNode> {
+    let mut keys: Vec<ProcessKey> = processes.iter().map(|info| info.key()).collect();
+    keys.sort();
+
+    // An exited process and the one that reused its PID can both be listed
+    let mut keys_by_pid: HashMap<Pid, Vec<ProcessKey>> = HashMap::new();
+    for key in &keys {
+        keys_by_pid.entry(key.pid).or_default().push(*key);
+    }
+    let parents: HashMap<ProcessKey, Option<ProcessKey>> = processes
+        .iter()
+        .map(|info| (info.key(), info.parent.and_then(|parent| resolve_parent(info.key(), parent, &keys_by_pid))))
+        .collect();
+
+    let mut children: HashMap<ProcessKey, Vec<ProcessKey>> = HashMap::new();
+    for key in &keys {
+        if let Some(Some(parent)) = parents.get(key) {
+            if parent != key {
+                children.entry(*parent).or_default().push(*key);
+            }
+        }
+    }
+
+    let mut infos: HashMap<ProcessKey, ProcessInfo> = processes.into_iter().map(|info| (info.key(), info)).collect();
+    let mut forest = Vec::new();
+
+    // Real roots first, then whatever is left over in cycles
+    let roots = keys.iter().filter(|key| match parents[*key] {
+        Some(parent) => parent == **key,
+        None => true,
+    });
+    for key in roots.copied().collect::<Vec<_>>() {
+        if let Some(node) = take_subtree(key, &mut infos, &children) {
+            forest.push(node);
+        }
+    }
+    for key in keys {
+        if let Some(node) = take_subtree(key, &mut infos, &children) {
+            forest.push(node);
+        }
+    }
+
+    forest
+}
+
+/// The listed process a parent PID refers to
+///
+/// Of the processes with that PID, the parent is the latest one started no later than the
+/// child (or the latest one if start times don't tell), since a process that reused the
+/// PID after the child started can't be its parent.
+fn resolve_parent(child: ProcessKey, parent: Pid, keys_by_pid: &HashMap<Pid, Vec<ProcessKey>>) -> Option<ProcessKey> {
+    let candidates = keys_by_pid.get(&parent)?;
+    candidates
+        .iter()
+        .filter(|key| key.start_time <= child.start_time)
+        .max()
+        .or_else(|| candidates.iter().max())
+        .copied()
+}
+
+/// Build the subtree rooted at the process from the processes not yet placed in the tree
+fn take_subtree(
+    key: ProcessKey,
+    infos: &mut HashMap<ProcessKey, ProcessInfo>,
+    children: &HashMap<ProcessKey, Vec<ProcessKey>>,
+) -> Option<TreeNode> {
+    let info = infos.remove(&key)?;
+    let nodes = children
+        .get(&key)
+        .into_iter()
+        .flatten()
+        .filter_map(|child| take_subtree(*child, infos, children))
+        .collect();
+    Some(TreeNode::new(info, nodes))
+}
+
+/// Compare two nodes, using subtree totals for CPU and memory
+fn compare_nodes(a: &TreeNode, b: &TreeNode, key: SortKey, order: SortOrder) -> Ordering {
+    let subtree = match key {
+        SortKey::Cpu => a.subtree_cpu_percent.partial_cmp(&b.subtree_cpu_percent).unwrap_or(Ordering::Equal),
+        SortKey::Memory => a.subtree_memory_bytes.cmp(&b.subtree_memory_bytes),
+        _ => Ordering::Equal,
+    };
+    let subtree = match order {
+        SortOrder::Ascending => subtree,
+        SortOrder::Descending => subtree.reverse(),
+    };
+
+    subtree.then_with(|| compare_processes(&a.info, &b.info, key, order))
+}
+
+/// Sort every level of the forest by the key and order
+///
+/// CPU and memory sort by subtree totals, so a parent with many busy children ranks high.
+pub fn sort_forest(forest: &mut [TreeNode], key: SortKey, order: SortOrder) {
+    forest.sort_by(|a, b| compare_nodes(a, b, key, order));
+    for node in forest.iter_mut() {
+        sort_forest(&mut node.children, key, order);
+    }
+}
+
+/// Remove subtrees whose total CPU percentage is below the threshold
+///
+/// Subtree totals are kept as they are, so a parent still reports the CPU of hidden children.
+pub fn prune_forest(forest: &mut Vec<TreeNode>, cpu_threshold: f32) {
+    forest.retain(|node| node.subtree_cpu_percent >= cpu_threshold);
+    for node in forest.iter_mut() {
+        prune_forest(&mut node.children, cpu_threshold);
+    }
+}
+
+/// Keep only the processes that match, nesting them again as [`build_forest`] would
+///
+/// Children of a removed process become roots, and subtree totals only count the kept
+/// processes, the same as building the forest from the filtered processes.
+pub fn filter_forest(forest: Vec<TreeNode>, keep: impl Fn(&ProcessInfo) -> bool) -> Vec<TreeNode> {
+    fn collect(nodes: Vec<TreeNode>, keep: &impl Fn(&ProcessInfo) -> bool, kept: &mut Vec<ProcessInfo>) {
+        for node in nodes {
+            if keep(&node.info) {
+                kept.push(node.info);
+            }
+            collect(node.children, keep, kept);
+        }
+    }
+
+    let mut kept = Vec::new();
+    collect(forest, &keep, &mut kept);
+    build_forest(kept)
+}
+
+/// Flatten the forest into display rows, skipping the children of collapsed processes
+pub fn flatten_forest(forest: &[TreeNode], collapsed: &HashSet<ProcessKey>) -> Vec<TreeRow> {
+    let mut rows = Vec::new();
+    for node in forest {
+        flatten_node(node, 0, "", "", collapsed, &mut rows);
+    }
+    rows
+}
+
+fn flatten_node(
+    node: &TreeNode,
+    depth: usize,
+    prefix: &str,
+    child_prefix: &str,
+    collapsed: &HashSet<ProcessKey>,
+    rows: &mut Vec<TreeRow>,
+) {
+    let expanded = !collapsed.contains(&node.info.key());
+    rows.push(TreeRow {
+        info: node.info.clone(),
+        depth,
+        prefix: prefix.to_string(),
+        subtree_cpu_percent: node.subtree_cpu_percent,
+        subtree_memory_bytes: node.subtree_memory_bytes,
+        child_count: node.children.len(),
+        expanded,
+    });
+
+    if !expanded {
+        return;
+    }
+
+    for (i, child) in node.children.iter().enumerate() {
+        let last = i + 1 == node.children.len();
+        let (branch, continuation) = if last { ("└─ ", "   ") } else { ("├─ ", "│  ") };
+        flatten_node(
+            child,
+            depth + 1,
+            &format!("{}{}", child_prefix, branch),
+            &format!("{}{}", child_prefix, continuation),
+            collapsed,
+            rows,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(pid: u32, parent: Option<u32>, cpu: f32, memory: u64) -> ProcessInfo {
+        ProcessInfo::new(format!("p{}", pid), Pid::from_u32(pid), cpu, memory, String::new())
+            .with_parent(parent.map(Pid::from_u32))
+    }
+
+    fn browser() -> Vec<ProcessInfo> {
+        vec![
+            info(1, None, 0.5, 10),
+            info(100, Some(1), 2.0, 300),
+            info(101, Some(100), 10.0, 100),
+            info(102, Some(100), 30.0, 200),
+            info(103, Some(102), 1.0, 50),
+            info(200, Some(1), 20.0, 1000),
+        ]
+    }
+
+    fn names(rows: &[TreeRow]) -> Vec<String> {
+        rows.iter().map(|row| row.display_name()).collect()
+    }
+
+    #[test]
+    fn test_build_forest_aggregates_subtrees() {
+        let forest = build_forest(browser());
+
+        assert_eq!(forest.len(), 1);
+        let root = &forest[0];
+        assert_eq!(root.descendant_count(), 5);
+        assert_eq!(root.subtree_cpu_percent, 63.5);
+        assert_eq!(root.subtree_memory_bytes, 1660);
+
+        let browser = root.children.iter().find(|c| c.info.pid.as_u32() == 100).unwrap();
+        assert_eq!(browser.subtree_cpu_percent, 43.0);
+        assert_eq!(browser.subtree_memory_bytes, 650);
+    }
+
+    #[test]
+    fn test_build_forest_orphans_become_roots() {
+        let forest = build_forest(vec![info(5, Some(4), 1.0, 0), info(6, Some(5), 1.0, 0), info(7, None, 1.0, 0)]);
+
+        let roots: Vec<u32> = forest.iter().map(|n| n.info.pid.as_u32()).collect();
+        assert_eq!(roots, vec![5, 7]);
+        assert_eq!(forest[0].children.len(), 1);
+    }
+
+    #[test]
+    fn test_build_forest_breaks_cycles() {
+        let forest = build_forest(vec![info(2, Some(3), 1.0, 0), info(3, Some(2), 1.0, 0), info(4, Some(4), 1.0, 0)]);
+
+        // The self-parented process is a root; the 2 <-> 3 cycle is rooted at its lowest PID
+        let roots: Vec<u32> = forest.iter().map(|n| n.info.pid.as_u32()).collect();
+        assert_eq!(roots, vec![4, 2]);
+        assert_eq!(forest[1].children[0].info.pid.as_u32(), 3);
+    }
+
+    #[test]
+    fn test_build_forest_keeps_processes_sharing_a_pid_apart() {
+        let started = |pid: u32, parent: Option<u32>, start_time: u64| info(pid, parent, 1.0, 10).with_start_time(start_time);
+        let forest = build_forest(vec![
+            started(1, None, 100),
+            // The exited process and its child, then the process that reused its PID and its child
+            started(7, Some(1), 1000),
+            started(8, Some(7), 1001),
+            started(7, Some(1), 2000),
+            started(9, Some(7), 2001),
+        ]);
+
+        assert_eq!(forest.len(), 1);
+        let reused: Vec<(u64, Vec<u32>)> = forest[0]
+            .children
+            .iter()
+            .map(|node| (node.info.start_time, node.children.iter().map(|c| c.info.pid.as_u32()).collect()))
+            .collect();
+        assert_eq!(reused, vec![(1000, vec![8]), (2000, vec![9])]);
+        assert_eq!(forest[0].subtree_cpu_percent, 5.0);
+    }
+
+    #[test]
+    fn test_sort_forest_by_subtree_cpu() {
+        let mut forest = build_forest(browser());
+        sort_forest(&mut forest, SortKey::Cpu, SortOrder::Descending);
+
+        // p100 has little CPU itself but its subtree (43%) outranks p200 (20%)
+        let rows = flatten_forest(&forest, &HashSet::new());
+        assert_eq!(
+            names(&rows),
+            vec!["p1", "├─ p100", "│  ├─ p102", "│  │  └─ p103", "│  └─ p101", "└─ p200"]
+        );
+        assert_eq!(rows[3].depth, 3);
+    }
+
+    #[test]
+    fn test_sort_forest_by_name_uses_own_values() {
+        let mut forest = build_forest(browser());
+        sort_forest(&mut forest, SortKey::Pid, SortOrder::Ascending);
+
+        let children: Vec<u32> = forest[0].children.iter().map(|c| c.info.pid.as_u32()).collect();
+        assert_eq!(children, vec![100, 200]);
+    }
+
+    #[test]
+    fn test_prune_forest_keeps_totals_of_hidden_children() {
+        let mut forest = build_forest(browser());
+        prune_forest(&mut forest, 5.0);
+
+        let rows = flatten_forest(&forest, &HashSet::new());
+        let pids: Vec<u32> = rows.iter().map(|r| r.info.pid.as_u32()).collect();
+        assert!(!pids.contains(&103));
+        assert!(pids.contains(&100));
+
+        let p102 = rows.iter().find(|r| r.info.pid.as_u32() == 102).unwrap();
+        assert_eq!(p102.subtree_cpu_percent, 31.0);
+    }
+
+    #[test]
+    fn test_flatten_skips_collapsed_children() {
+        let mut forest = build_forest(browser());
+        sort_forest(&mut forest, SortKey::Cpu, SortOrder::Descending);
+        let collapsed: HashSet<ProcessKey> = [ProcessKey::new(Pid::from_u32(100), 0)].into_iter().collect();
+
+        let rows = flatten_forest(&forest, &collapsed);
+        assert_eq!(names(&rows), vec!["p1", "├─ + p100", "└─ p200"]);
+        assert_eq!(rows[1].child_count, 2);
+        assert!(!rows[1].expanded);
+    }
+
+    #[test]
+    fn test_filter_forest_reroots_kept_children() {
+        let forest = filter_forest(build_forest(browser()), |info| info.pid.as_u32() != 100);
+
+        let mut roots: Vec<u32> = forest.iter().map(|n| n.info.pid.as_u32()).collect();
+        roots.sort();
+        assert_eq!(roots, vec![1, 101, 102]);
+        let p1 = forest.iter().find(|n| n.info.pid.as_u32() == 1).unwrap();
+        assert_eq!(p1.subtree_cpu_percent, 20.5);
+    }
+
+    #[test]
+    fn test_empty_forest() {
+        assert!(build_forest(Vec::new()).is_empty());
+        assert!(flatten_forest(&[], &HashSet::new()).is_empty());
+    }
+}
@@ -0,0 +1,613 @@
+use crate::cpu_calculator::CpuScale;
+use crate::cpu_time::CpuTimeTotals;
+use crate::display::truncate_string;
+use crate::grouping::ProcessGroup;
+use crate::ProcessInfo;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Maximum length of the `details` label, to keep label values bounded
+pub const DETAILS_LABEL_MAX_LEN: usize = 40;
+
+/// How long a scrape connection may take to send its request, and may stall on a write
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Largest request (request line and headers) read from a scrape connection
+const MAX_REQUEST_BYTES: u64 = 8 * 1024;
+
+/// Most connections served at once; further ones are closed right away
+const MAX_CONNECTIONS: usize = 8;
+
+/// Host-level values exported alongside the per-process gauges
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostMetrics {
+    pub cpu_count: f32,
+    /// What 100% means in the CPU percentage gauges
+    pub cpu_scale: CpuScale,
+    pub window_seconds: i64,
+    pub interval_seconds: u64,
+    pub tracked_processes: usize,
+}
+
+/// Escape a label value per the Prometheus text exposition format
+pub fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn write_gauge_header(output: &mut String, name: &str, help: &str) {
+    let _ = writeln!(output, "# HELP {} {}", name, help);
+    let _ = writeln!(output, "# TYPE {} gauge", name);
+}
+
+/// Labels identifying a process in the per-process gauges
+///
+/// The start time tells a reused PID apart, e.g. when exited processes are kept in the ranking.
+fn process_labels(info: &ProcessInfo) -> String {
+    format!(
+        "pid=\"{}\",start_time=\"{}\",name=\"{}\",details=\"{}\"",
+        info.pid.as_u32(),
+        info.start_time,
+        escape_label_value(&info.name),
+        escape_label_value(&truncate_string(&info.extra_info, DETAILS_LABEL_MAX_LEN)),
+    )
+}
+
+/// Render the ranked processes and host values in the Prometheus text format
+///
+/// # Arguments
+/// * `results` - Ranked processes to export (already limited to top N)
+/// * `host` - Host-level values
+///
+/// # Returns
+/// The body to serve on `/metrics`
+pub fn render_metrics(results: &[ProcessInfo], host: &HostMetrics) -> String {
+    let mut output = String::new();
+
+    let labels: Vec<String> = results.iter().map(process_labels).collect();
+
+    write_gauge_header(
+        &mut output,
+        "process_shepherd_process_cpu_percent",
+        "Average CPU percentage of the process over the tracking window, scaled as given by process_shepherd_cpu_scale_info.",
+    );
+    for (info, labels) in results.iter().zip(&labels) {
+        let _ = writeln!(output, "process_shepherd_process_cpu_percent{{{}}} {}", labels, info.cpu_percent);
+    }
+
+    write_gauge_header(
+        &mut output,
+        "process_shepherd_process_memory_bytes",
+        "Memory used by the process in bytes.",
+    );
+    for (info, labels) in results.iter().zip(&labels) {
+        let _ = writeln!(output, "process_shepherd_process_memory_bytes{{{}}} {}", labels, info.memory_bytes);
+    }
+
+    write_gauge_header(
+        &mut output,
+        "process_shepherd_process_samples",
+        "Number of CPU samples of the process in the tracking window.",
+    );
+    for (info, labels) in results.iter().zip(&labels) {
+        let _ = writeln!(output, "process_shepherd_process_samples{{{}}} {}", labels, info.sample_count);
+    }
+
+    write_gauge_header(&mut output, "process_shepherd_cpu_count", "Number of CPU cores used for normalization.");
+    let _ = writeln!(output, "process_shepherd_cpu_count {}", host.cpu_count);
+
+    write_gauge_header(
+        &mut output,
+        "process_shepherd_cpu_scale_info",
+        "What 100% means in the CPU percentages: the whole machine (machine) or one core (core).",
+    );
+    let _ = writeln!(output, "process_shepherd_cpu_scale_info{{scale=\"{}\"}} 1", host.cpu_scale);
+
+    write_gauge_header(&mut output, "process_shepherd_window_seconds", "Length of the tracking window in seconds.");
+    let _ = writeln!(output, "process_shepherd_window_seconds {}", host.window_seconds);
+
+    write_gauge_header(&mut output, "process_shepherd_interval_seconds", "Seconds between two CPU samples.");
+    let _ = writeln!(output, "process_shepherd_interval_seconds {}", host.interval_seconds);
+
+    write_gauge_header(
+        &mut output,
+        "process_shepherd_tracked_processes",
+        "Number of processes with samples in the tracking window.",
+    );
+    let _ = writeln!(output, "process_shepherd_tracked_processes {}", host.tracked_processes);
+
+    output
+}
+
+/// Reads one statistic of a process
+type StatValue = fn(&ProcessInfo) -> f32;
+
+/// Render the CPU distribution of the ranked processes in the Prometheus text format
+///
+/// # Arguments
+/// * `results` - Ranked processes to export (already limited to top N)
+///
+/// # Returns
+/// Gauges to append to the output of [`render_metrics`], labelled like the per-process gauges
+pub fn render_cpu_stats_metrics(results: &[ProcessInfo]) -> String {
+    let mut output = String::new();
+    let labels: Vec<String> = results.iter().map(process_labels).collect();
+
+    let gauges: [(&str, &str, StatValue); 5] = [
+        (
+            "process_shepherd_process_cpu_p50_percent",
+            "Median CPU percentage of the process's samples in the tracking window.",
+            |info| info.cpu_stats.p50,
+        ),
+        (
+            "process_shepherd_process_cpu_p95_percent",
+            "95th percentile CPU percentage of the process's samples in the tracking window.",
+            |info| info.cpu_stats.p95,
+        ),
+        (
+            "process_shepherd_process_cpu_p99_percent",
+            "99th percentile CPU percentage of the process's samples in the tracking window.",
+            |info| info.cpu_stats.p99,
+        ),
+        (
+            "process_shepherd_process_cpu_max_percent",
+            "Highest CPU percentage of a single sample of the process in the tracking window.",
+            |info| info.cpu_stats.max,
+        ),
+        (
+            "process_shepherd_process_cpu_stddev",
+            "Standard deviation of the process's CPU percentage samples in the tracking window.",
+            |info| info.cpu_stats.stddev,
+        ),
+    ];
+    for (name, help, value) in gauges {
+        write_gauge_header(&mut output, name, help);
+        for (info, labels) in results.iter().zip(&labels) {
+            let _ = writeln!(output, "{}{{{}}} {}", name, labels, value(info));
+        }
+    }
+
+    output
+}
+
+/// Render per-user totals in the Prometheus text format
+///
+/// # Arguments
+/// * `users` - Totals per user (as returned by [`crate::ProcessTracker::user_summary`])
+///
+/// # Returns
+/// Gauges to append to the output of [`render_metrics`]
+pub fn render_user_metrics(users: &[ProcessGroup]) -> String {
+    let mut output = String::new();
+
+    write_gauge_header(
+        &mut output,
+        "process_shepherd_user_cpu_percent",
+        "Sum of the average CPU percentages of the user's processes over the tracking window.",
+    );
+    for user in users {
+        let _ = writeln!(
+            output,
+            "process_shepherd_user_cpu_percent{{user=\"{}\"}} {}",
+            escape_label_value(&user.key),
+            user.cpu_percent
+        );
+    }
+
+    write_gauge_header(
+        &mut output,
+        "process_shepherd_user_memory_bytes",
+        "Memory used by the user's processes in bytes.",
+    );
+    for user in users {
+        let _ = writeln!(
+            output,
+            "process_shepherd_user_memory_bytes{{user=\"{}\"}} {}",
+            escape_label_value(&user.key),
+            user.memory_bytes
+        );
+    }
+
+    write_gauge_header(&mut output, "process_shepherd_user_processes", "Number of tracked processes of the user.");
+    for user in users {
+        let _ = writeln!(
+            output,
+            "process_shepherd_user_processes{{user=\"{}\"}} {}",
+            escape_label_value(&user.key),
+            user.instance_count()
+        );
+    }
+
+    output
+}
+
+/// Render the CPU time consumed in the tracking window in the Prometheus text format
+///
+/// # Arguments
+/// * `results` - Ranked processes to export (already limited to top N)
+/// * `totals` - Totals over every process (as returned by [`crate::ProcessTracker::cpu_time_totals`])
+///
+/// # Returns
+/// Gauges to append to the output of [`render_metrics`]; processes without a CPU time reading are left out
+pub fn render_cpu_time_metrics(results: &[ProcessInfo], totals: &CpuTimeTotals) -> String {
+    let mut output = String::new();
+
+    write_gauge_header(
+        &mut output,
+        "process_shepherd_process_cpu_seconds",
+        "CPU-seconds consumed by the process in the tracking window.",
+    );
+    for info in results {
+        if let Some(cpu_seconds) = info.cpu_seconds {
+            let _ = writeln!(output, "process_shepherd_process_cpu_seconds{{{}}} {}", process_labels(info), cpu_seconds);
+        }
+    }
+
+    if results.iter().any(|info| info.children_cpu_seconds.is_some()) {
+        write_gauge_header(
+            &mut output,
+            "process_shepherd_process_children_cpu_seconds",
+            "CPU-seconds of the children the process reaped in the tracking window; children that were tracked themselves also count in their own series.",
+        );
+        for info in results {
+            if let Some(children_cpu_seconds) = info.children_cpu_seconds {
+                let _ = writeln!(
+                    output,
+                    "process_shepherd_process_children_cpu_seconds{{{}}} {}",
+                    process_labels(info),
+                    children_cpu_seconds
+                );
+            }
+        }
+    }
+
+    write_gauge_header(
+        &mut output,
+        "process_shepherd_processes_cpu_seconds",
+        "CPU-seconds consumed in the tracking window by all processes, including exited ones.",
+    );
+    let _ = writeln!(output, "process_shepherd_processes_cpu_seconds {}", totals.process_secs);
+
+    if let Some(busy_secs) = totals.busy_secs {
+        write_gauge_header(
+            &mut output,
+            "process_shepherd_busy_cpu_seconds",
+            "CPU-seconds the machine was busy in the tracking window.",
+        );
+        let _ = writeln!(output, "process_shepherd_busy_cpu_seconds {}", busy_secs);
+    }
+
+    output
+}
+
+/// Minimal HTTP server that serves the latest rendered metrics on `/metrics`
+///
+/// The server runs on a background thread for the lifetime of the process.
+pub struct MetricsServer {
+    body: Arc<Mutex<String>>,
+    local_addr: SocketAddr,
+}
+
+impl MetricsServer {
+    /// Bind to the address and start serving on a background thread
+    pub fn start(addr: SocketAddr) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let body = Arc::new(Mutex::new(String::new()));
+
+        let shared_body = Arc::clone(&body);
+        thread::spawn(move || {
+            // A misbehaving client must not stop the exporter, so connections are served on a
+            // bounded number of threads, with a deadline and a size limit on the request
+            let active = Arc::new(AtomicUsize::new(0));
+            for stream in listener.incoming().flatten() {
+                // Only this thread adds connections, so the count can't grow past the check
+                if active.load(Ordering::Acquire) >= MAX_CONNECTIONS {
+                    continue;
+                }
+                active.fetch_add(1, Ordering::AcqRel);
+
+                let body = Arc::clone(&shared_body);
+                let active = Arc::clone(&active);
+                thread::spawn(move || {
+                    let _ = handle_connection(stream, &body);
+                    active.fetch_sub(1, Ordering::AcqRel);
+                });
+            }
+        });
+
+        Ok(Self { body, local_addr })
+    }
+
+    /// Address the server is listening on (useful when binding to port 0)
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Replace the metrics served on `/metrics`
+    pub fn update(&self, metrics: String) {
+        if let Ok(mut body) = self.body.lock() {
+            *body = metrics;
+        }
+    }
+}
+
+/// Read one line of the request head, failing once the deadline has passed
+///
+/// # Returns
+/// The line, or an `InvalidData` error if the request is larger than [`MAX_REQUEST_BYTES`]
+fn read_request_line(reader: &mut BufReader<io::Take<TcpStream>>, deadline: Instant) -> io::Result<String> {
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    if remaining.is_zero() {
+        return Err(io::Error::new(io::ErrorKind::TimedOut, "request not received in time"));
+    }
+    reader.get_ref().get_ref().set_read_timeout(Some(remaining))?;
+
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if !line.ends_with('\n') && reader.get_ref().limit() == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "request too large"));
+    }
+    Ok(line)
+}
+
+fn handle_connection(stream: TcpStream, body: &Mutex<String>) -> io::Result<()> {
+    stream.set_write_timeout(Some(CONNECTION_TIMEOUT))?;
+    let deadline = Instant::now() + CONNECTION_TIMEOUT;
+    let mut reader = BufReader::new(stream.take(MAX_REQUEST_BYTES));
+
+    let request_line = match read_request_head(&mut reader, deadline) {
+        Ok(request_line) => request_line,
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+            let stream = reader.into_inner().into_inner();
+            return write_response(stream, "431 Request Header Fields Too Large", "Request Too Large\n");
+        }
+        Err(e) => return Err(e),
+    };
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let path = parts.next().unwrap_or("");
+
+    let (status, content) = if method == "GET" && (path == "/metrics" || path.starts_with("/metrics?")) {
+        let content = body.lock().map(|b| b.clone()).unwrap_or_default();
+        ("200 OK", content)
+    } else {
+        ("404 Not Found", "Not Found\n".to_string())
+    };
+
+    write_response(reader.into_inner().into_inner(), status, &content)
+}
+
+/// Read the request line and drain the headers that follow it
+fn read_request_head(reader: &mut BufReader<io::Take<TcpStream>>, deadline: Instant) -> io::Result<String> {
+    let request_line = read_request_line(reader, deadline)?;
+    while read_request_line(reader, deadline)?.len() > 2 {}
+    Ok(request_line)
+}
+
+fn write_response(mut stream: TcpStream, status: &str, content: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content.len(),
+        content
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use sysinfo::Pid;
+
+    fn host() -> HostMetrics {
+        HostMetrics {
+            cpu_count: 8.0,
+            cpu_scale: CpuScale::Machine,
+            window_seconds: 60,
+            interval_seconds: 2,
+            tracked_processes: 312,
+        }
+    }
+
+    fn http_get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_escape_label_value() {
+        assert_eq!(escape_label_value("plain"), "plain");
+        assert_eq!(escape_label_value("say \"hi\""), "say \\\"hi\\\"");
+        assert_eq!(escape_label_value("C:\\Windows"), "C:\\\\Windows");
+        assert_eq!(escape_label_value("a\nb"), "a\\nb");
+    }
+
+    #[test]
+    fn test_render_metrics_process_gauges() {
+        let results = vec![
+            ProcessInfo::new("cargo".to_string(), Pid::from_u32(42), 12.5, 4096, "build".to_string())
+                .with_sample_count(30),
+        ];
+        let metrics = render_metrics(&results, &host());
+
+        assert!(metrics.contains("# TYPE process_shepherd_process_cpu_percent gauge"));
+        assert!(metrics.contains("process_shepherd_process_cpu_percent{pid=\"42\",start_time=\"0\",name=\"cargo\",details=\"build\"} 12.5"));
+        assert!(metrics.contains("process_shepherd_process_memory_bytes{pid=\"42\",start_time=\"0\",name=\"cargo\",details=\"build\"} 4096"));
+        assert!(metrics.contains("process_shepherd_process_samples{pid=\"42\",start_time=\"0\",name=\"cargo\",details=\"build\"} 30"));
+    }
+
+    #[test]
+    fn test_render_metrics_tells_reused_pids_apart() {
+        use chrono::{TimeZone, Utc};
+
+        let results = vec![
+            ProcessInfo::new("worker".to_string(), Pid::from_u32(42), 12.5, 4096, String::new())
+                .with_start_time(1_704_110_400),
+            ProcessInfo::new("worker".to_string(), Pid::from_u32(42), 3.0, 2048, String::new())
+                .with_start_time(1_704_110_460)
+                .with_exited_at(Some(Utc.with_ymd_and_hms(2024, 1, 1, 12, 2, 0).unwrap())),
+        ];
+        let metrics = render_metrics(&results, &host());
+
+        assert!(metrics.contains(
+            "process_shepherd_process_cpu_percent{pid=\"42\",start_time=\"1704110400\",name=\"worker\",details=\"\"} 12.5"
+        ));
+        assert!(metrics.contains(
+            "process_shepherd_process_cpu_percent{pid=\"42\",start_time=\"1704110460\",name=\"worker\",details=\"\"} 3"
+        ));
+    }
+
+    #[test]
+    fn test_render_metrics_host_gauges() {
+        let metrics = render_metrics(&[], &host());
+
+        assert!(metrics.contains("process_shepherd_cpu_count 8"));
+        assert!(metrics.contains("process_shepherd_cpu_scale_info{scale=\"machine\"} 1"));
+        assert!(metrics.contains("process_shepherd_window_seconds 60"));
+        assert!(metrics.contains("process_shepherd_interval_seconds 2"));
+        assert!(metrics.contains("process_shepherd_tracked_processes 312"));
+    }
+
+    #[test]
+    fn test_render_metrics_bounds_details_label() {
+        let long_details = "x".repeat(500);
+        let results = vec![ProcessInfo::new("app".to_string(), Pid::from_u32(1), 1.0, 0, long_details)];
+        let metrics = render_metrics(&results, &host());
+
+        let expected = format!("details=\"{}...\"", "x".repeat(DETAILS_LABEL_MAX_LEN - 3));
+        assert!(metrics.contains(&expected));
+        assert!(!metrics.contains(&"x".repeat(DETAILS_LABEL_MAX_LEN + 1)));
+    }
+
+    #[test]
+    fn test_render_cpu_stats_metrics() {
+        use crate::cpu_calculator::CpuStats;
+
+        let results = vec![ProcessInfo::new("cargo".to_string(), Pid::from_u32(42), 12.5, 4096, "build".to_string())
+            .with_cpu_stats(CpuStats { p50: 5.0, p95: 80.0, p99: 95.5, max: 100.0, stddev: 22.5, ..CpuStats::default() })];
+        let metrics = render_cpu_stats_metrics(&results);
+
+        let labels = "{pid=\"42\",start_time=\"0\",name=\"cargo\",details=\"build\"}";
+        assert!(metrics.contains("# TYPE process_shepherd_process_cpu_p95_percent gauge"));
+        assert!(metrics.contains(&format!("process_shepherd_process_cpu_p50_percent{} 5", labels)));
+        assert!(metrics.contains(&format!("process_shepherd_process_cpu_p95_percent{} 80", labels)));
+        assert!(metrics.contains(&format!("process_shepherd_process_cpu_p99_percent{} 95.5", labels)));
+        assert!(metrics.contains(&format!("process_shepherd_process_cpu_max_percent{} 100", labels)));
+        assert!(metrics.contains(&format!("process_shepherd_process_cpu_stddev{} 22.5", labels)));
+    }
+
+    #[test]
+    fn test_render_user_metrics() {
+        let users = vec![ProcessGroup {
+            key: "build\"bot".to_string(),
+            cpu_percent: 75.5,
+            memory_bytes: 8192,
+            pids: vec![Pid::from_u32(1), Pid::from_u32(2)],
+        }];
+        let metrics = render_user_metrics(&users);
+
+        assert!(metrics.contains("# TYPE process_shepherd_user_cpu_percent gauge"));
+        assert!(metrics.contains("process_shepherd_user_cpu_percent{user=\"build\\\"bot\"} 75.5"));
+        assert!(metrics.contains("process_shepherd_user_memory_bytes{user=\"build\\\"bot\"} 8192"));
+        assert!(metrics.contains("process_shepherd_user_processes{user=\"build\\\"bot\"} 2"));
+    }
+
+    #[test]
+    fn test_render_cpu_time_metrics() {
+        let results = vec![
+            ProcessInfo::new("cargo".to_string(), Pid::from_u32(42), 25.0, 4096, String::new())
+                .with_cpu_seconds(Some(30.0))
+                .with_children_cpu_seconds(Some(4.5)),
+            ProcessInfo::new("sampled".to_string(), Pid::from_u32(43), 1.0, 1024, String::new()),
+        ];
+        let totals = CpuTimeTotals { process_secs: 31.5, busy_secs: Some(33.0) };
+        let metrics = render_cpu_time_metrics(&results, &totals);
+
+        assert!(metrics.contains("# TYPE process_shepherd_process_cpu_seconds gauge"));
+        assert!(metrics.contains("process_shepherd_process_cpu_seconds{pid=\"42\",start_time=\"0\",name=\"cargo\",details=\"\"} 30"));
+        assert!(metrics.contains("process_shepherd_process_children_cpu_seconds{pid=\"42\",start_time=\"0\",name=\"cargo\",details=\"\"} 4.5"));
+        assert!(!metrics.contains("pid=\"43\""));
+        assert!(metrics.contains("process_shepherd_processes_cpu_seconds 31.5"));
+        assert!(metrics.contains("process_shepherd_busy_cpu_seconds 33"));
+
+        let totals = CpuTimeTotals { process_secs: 31.5, busy_secs: None };
+        assert!(!render_cpu_time_metrics(&results, &totals).contains("busy"));
+    }
+
+    #[test]
+    fn test_server_serves_latest_metrics_on_localhost() {
+        let server = MetricsServer::start("127.0.0.1:0".parse().unwrap()).unwrap();
+        server.update("process_shepherd_cpu_count 4\n".to_string());
+
+        let response = http_get(server.local_addr(), "/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("text/plain; version=0.0.4"));
+        assert!(response.ends_with("process_shepherd_cpu_count 4\n"));
+
+        server.update("process_shepherd_cpu_count 8\n".to_string());
+        let response = http_get(server.local_addr(), "/metrics");
+        assert!(response.ends_with("process_shepherd_cpu_count 8\n"));
+    }
+
+    #[test]
+    fn test_server_serves_metrics_while_another_client_is_idle() {
+        let server = MetricsServer::start("127.0.0.1:0".parse().unwrap()).unwrap();
+        server.update("process_shepherd_cpu_count 4\n".to_string());
+
+        // Connect without ever sending a request line
+        let _idle = TcpStream::connect(server.local_addr()).unwrap();
+
+        let response = http_get(server.local_addr(), "/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with("process_shepherd_cpu_count 4\n"));
+    }
+
+    #[test]
+    fn test_server_rejects_oversized_requests() {
+        let server = MetricsServer::start("127.0.0.1:0".parse().unwrap()).unwrap();
+
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        // A request line that never ends within the limit
+        let path = "x".repeat(MAX_REQUEST_BYTES as usize - "GET /".len());
+        write!(stream, "GET /{}", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 431 Request Header Fields Too Large"));
+    }
+
+    #[test]
+    fn test_server_closes_connections_over_the_limit() {
+        let server = MetricsServer::start("127.0.0.1:0".parse().unwrap()).unwrap();
+        let _idle: Vec<TcpStream> =
+            (0..MAX_CONNECTIONS).map(|_| TcpStream::connect(server.local_addr()).unwrap()).collect();
+
+        // Closed without a response while every slot is taken
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).unwrap();
+        assert!(response.is_empty());
+    }
+
+    #[test]
+    fn test_server_returns_not_found_for_other_paths() {
+        let server = MetricsServer::start("127.0.0.1:0".parse().unwrap()).unwrap();
+
+        let response = http_get(server.local_addr(), "/");
+        assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+    }
+}
@@ -0,0 +1,270 @@
+use crate::ProcessInfo;
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// Column used to rank processes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortKey {
+    /// Average CPU percentage over the tracking window
+    #[default]
+    Cpu,
+    Memory,
+    Pid,
+    Name,
+    /// Change in CPU percentage since the previous ranking
+    Trend,
+    /// Highest single-sample CPU percentage in the tracking window
+    PeakCpu,
+}
+
+impl SortKey {
+    /// All sort keys, in the order they are listed in help texts
+    pub const ALL: [SortKey; 6] = [
+        SortKey::Cpu,
+        SortKey::Memory,
+        SortKey::Pid,
+        SortKey::Name,
+        SortKey::Trend,
+        SortKey::PeakCpu,
+    ];
+
+    /// Name accepted on the command line
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SortKey::Cpu => "cpu",
+            SortKey::Memory => "memory",
+            SortKey::Pid => "pid",
+            SortKey::Name => "name",
+            SortKey::Trend => "trend",
+            SortKey::PeakCpu => "peak",
+        }
+    }
+
+    /// Human-readable column label
+    pub fn label(self) -> &'static str {
+        match self {
+            SortKey::Cpu => "CPU %",
+            SortKey::Memory => "Memory",
+            SortKey::Pid => "PID",
+            SortKey::Name => "Name",
+            SortKey::Trend => "Trend",
+            SortKey::PeakCpu => "Peak CPU %",
+        }
+    }
+
+    /// Natural order for the key: alphabetical/numeric identifiers ascend, measurements descend
+    pub fn default_order(self) -> SortOrder {
+        match self {
+            SortKey::Pid | SortKey::Name => SortOrder::Ascending,
+            _ => SortOrder::Descending,
+        }
+    }
+}
+
+impl fmt::Display for SortKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SortKey::ALL
+            .into_iter()
+            .find(|key| key.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names: Vec<&str> = SortKey::ALL.iter().map(|key| key.as_str()).collect();
+                format!("'{}' is not a valid sort key (expected one of: {})", s, names.join(", "))
+            })
+    }
+}
+
+/// Direction of a sort
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+impl SortOrder {
+    pub fn reversed(self) -> Self {
+        match self {
+            SortOrder::Ascending => SortOrder::Descending,
+            SortOrder::Descending => SortOrder::Ascending,
+        }
+    }
+
+    /// Name accepted on the command line
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SortOrder::Ascending => "asc",
+            SortOrder::Descending => "desc",
+        }
+    }
+
+    /// Arrow shown next to the sorted column
+    pub fn indicator(self) -> &'static str {
+        match self {
+            SortOrder::Ascending => "▲",
+            SortOrder::Descending => "▼",
+        }
+    }
+}
+
+impl FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "asc" | "ascending" => Ok(SortOrder::Ascending),
+            "desc" | "descending" => Ok(SortOrder::Descending),
+            _ => Err(format!("'{}' is not a valid sort order (expected asc or desc)", s)),
+        }
+    }
+}
+
+/// Describe a sort for headers and status bars, e.g. "CPU % ▼"
+pub fn describe_sort(key: SortKey, order: SortOrder) -> String {
+    format!("{} {}", key.label(), order.indicator())
+}
+
+fn compare_f32(a: f32, b: f32) -> Ordering {
+    a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+}
+
+/// Compare two processes by the key in ascending order
+fn compare_by_key(a: &ProcessInfo, b: &ProcessInfo, key: SortKey) -> Ordering {
+    match key {
+        SortKey::Cpu => compare_f32(a.cpu_percent, b.cpu_percent),
+        SortKey::Memory => a.memory_bytes.cmp(&b.memory_bytes),
+        SortKey::Pid => a.pid.cmp(&b.pid),
+        SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+        SortKey::Trend => compare_f32(a.trend_delta().unwrap_or(0.0), b.trend_delta().unwrap_or(0.0)),
+        SortKey::PeakCpu => compare_f32(a.peak_cpu_percent, b.peak_cpu_percent),
+    }
+}
+
+/// Compare two processes by the key and order
+///
+/// Ties are broken by CPU percentage (descending) and then PID, so the order is stable
+/// between refreshes.
+pub fn compare_processes(a: &ProcessInfo, b: &ProcessInfo, key: SortKey, order: SortOrder) -> Ordering {
+    let primary = match order {
+        SortOrder::Ascending => compare_by_key(a, b, key),
+        SortOrder::Descending => compare_by_key(b, a, key),
+    };
+    primary
+        .then_with(|| compare_f32(b.cpu_percent, a.cpu_percent))
+        .then_with(|| a.pid.cmp(&b.pid))
+}
+
+/// Sort processes in place by the key and order (see [`compare_processes`])
+pub fn sort_processes(processes: &mut [ProcessInfo], key: SortKey, order: SortOrder) {
+    processes.sort_by(|a, b| compare_processes(a, b, key, order));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sysinfo::Pid;
+
+    fn info(pid: u32, name: &str, cpu: f32, memory: u64) -> ProcessInfo {
+        ProcessInfo::new(name.to_string(), Pid::from_u32(pid), cpu, memory, String::new())
+    }
+
+    fn pids(processes: &[ProcessInfo]) -> Vec<u32> {
+        processes.iter().map(|p| p.pid.as_u32()).collect()
+    }
+
+    fn sample() -> Vec<ProcessInfo> {
+        vec![
+            info(3, "beta", 10.0, 300).with_previous_cpu_percent(Some(20.0)).with_peak_cpu_percent(90.0),
+            info(1, "Alpha", 30.0, 100).with_previous_cpu_percent(Some(25.0)).with_peak_cpu_percent(40.0),
+            info(2, "gamma", 20.0, 200).with_peak_cpu_percent(20.0),
+        ]
+    }
+
+    #[test]
+    fn test_sort_by_cpu_descending() {
+        let mut processes = sample();
+        sort_processes(&mut processes, SortKey::Cpu, SortOrder::Descending);
+        assert_eq!(pids(&processes), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_sort_by_memory_both_orders() {
+        let mut processes = sample();
+        sort_processes(&mut processes, SortKey::Memory, SortOrder::Descending);
+        assert_eq!(pids(&processes), vec![3, 2, 1]);
+
+        sort_processes(&mut processes, SortKey::Memory, SortOrder::Ascending);
+        assert_eq!(pids(&processes), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_sort_by_pid_and_name() {
+        let mut processes = sample();
+        sort_processes(&mut processes, SortKey::Pid, SortOrder::Ascending);
+        assert_eq!(pids(&processes), vec![1, 2, 3]);
+
+        // Names compare case-insensitively
+        sort_processes(&mut processes, SortKey::Name, SortOrder::Descending);
+        assert_eq!(pids(&processes), vec![2, 3, 1]);
+    }
+
+    #[test]
+    fn test_sort_by_trend_treats_missing_previous_as_zero() {
+        let mut processes = sample();
+        sort_processes(&mut processes, SortKey::Trend, SortOrder::Descending);
+        // Deltas: pid 1 = +5, pid 2 = none (0), pid 3 = -10
+        assert_eq!(pids(&processes), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_sort_by_peak_cpu() {
+        let mut processes = sample();
+        sort_processes(&mut processes, SortKey::PeakCpu, SortOrder::Descending);
+        assert_eq!(pids(&processes), vec![3, 1, 2]);
+    }
+
+    #[test]
+    fn test_ties_broken_by_cpu_then_pid() {
+        let mut processes = vec![info(5, "a", 1.0, 100), info(4, "b", 1.0, 100), info(6, "c", 9.0, 100)];
+        sort_processes(&mut processes, SortKey::Memory, SortOrder::Descending);
+        assert_eq!(pids(&processes), vec![6, 4, 5]);
+    }
+
+    #[test]
+    fn test_sort_key_parsing() {
+        for key in SortKey::ALL {
+            assert_eq!(key.as_str().parse::<SortKey>(), Ok(key));
+        }
+        assert_eq!("MEMORY".parse::<SortKey>(), Ok(SortKey::Memory));
+        assert!("disk".parse::<SortKey>().is_err());
+    }
+
+    #[test]
+    fn test_sort_order_parsing_and_reversal() {
+        assert_eq!("asc".parse::<SortOrder>(), Ok(SortOrder::Ascending));
+        assert_eq!("Descending".parse::<SortOrder>(), Ok(SortOrder::Descending));
+        assert!("up".parse::<SortOrder>().is_err());
+        assert_eq!(SortOrder::Ascending.reversed(), SortOrder::Descending);
+    }
+
+    #[test]
+    fn test_default_orders() {
+        assert_eq!(SortKey::Cpu.default_order(), SortOrder::Descending);
+        assert_eq!(SortKey::Memory.default_order(), SortOrder::Descending);
+        assert_eq!(SortKey::Name.default_order(), SortOrder::Ascending);
+        assert_eq!(SortKey::Pid.default_order(), SortOrder::Ascending);
+    }
+
+    #[test]
+    fn test_describe_sort() {
+        assert_eq!(describe_sort(SortKey::Cpu, SortOrder::Descending), "CPU % ▼");
+        assert_eq!(describe_sort(SortKey::Name, SortOrder::Ascending), "Name ▲");
+    }
+}
@@ -0,0 +1,127 @@
+use crate::ProcessInfo;
+
+/// Machine-wide utilisation reported by a [`crate::ProcessSource`] at one refresh
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SystemStats {
+    /// Busy percentage of the whole machine since the previous refresh (0-100)
+    pub cpu_percent: f32,
+    /// Busy percentage of every core since the previous refresh (0-100 each)
+    pub core_percents: Vec<f32>,
+    /// Load average over 1, 5 and 15 minutes, `None` where unsupported (Windows)
+    pub load_average: Option<[f64; 3]>,
+    pub memory_used_bytes: u64,
+    pub memory_total_bytes: u64,
+    pub swap_used_bytes: u64,
+    pub swap_total_bytes: u64,
+}
+
+/// Machine-wide utilisation shown above the ranking, with the share of the CPU used by the
+/// listed processes
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemSummary {
+    pub stats: SystemStats,
+    /// Number of processes running at the refresh
+    pub process_count: usize,
+    /// Number of listed processes
+    pub listed_count: usize,
+    /// Share of the whole machine used by the listed processes at the refresh, in percent
+    pub listed_cpu_percent: f32,
+}
+
+impl SystemSummary {
+    /// Summarize the machine, attributing to the listed processes the CPU they used in the latest sample
+    ///
+    /// # Arguments
+    /// * `stats` - Machine-wide utilisation at the latest refresh
+    /// * `process_count` - Number of processes running at the latest refresh
+    /// * `listed` - Processes shown in the ranking; exited ones no longer use any CPU
+    /// * `cpu_count` - Number of CPU cores, to relate raw per-process usage to the whole machine
+    ///
+    /// # Examples
+    /// ```
+    /// use process_shepherd::system_summary::{SystemStats, SystemSummary};
+    /// use process_shepherd::ProcessInfo;
+    /// use sysinfo::Pid;
+    ///
+    /// let stats = SystemStats { cpu_percent: 40.0, ..SystemStats::default() };
+    /// let busy = ProcessInfo::new("busy".to_string(), Pid::from_u32(1), 20.0, 0, String::new())
+    ///     .with_latest_cpu_usage(100.0);
+    /// let summary = SystemSummary::new(stats, 120, &[busy], 4.0);
+    ///
+    /// assert_eq!(summary.listed_cpu_percent, 25.0);
+    /// assert_eq!(summary.other_cpu_percent(), 15.0);
+    /// ```
+    pub fn new(stats: SystemStats, process_count: usize, listed: &[ProcessInfo], cpu_count: f32) -> Self {
+        // Folded from 0.0, as an empty f32 sum is -0.0
+        let listed_usage = listed
+            .iter()
+            .filter(|info| info.exited_at.is_none())
+            .fold(0.0, |sum, info| sum + info.latest_cpu_usage);
+        // Per-process and machine-wide usage are sampled separately, so the sum can slightly overshoot
+        let listed_cpu_percent = (listed_usage / cpu_count.max(1.0)).min(stats.cpu_percent);
+
+        Self {
+            stats,
+            process_count,
+            listed_count: listed.len(),
+            listed_cpu_percent,
+        }
+    }
+
+    /// Share of the whole machine used by everything but the listed processes, in percent
+    pub fn other_cpu_percent(&self) -> f32 {
+        (self.stats.cpu_percent - self.listed_cpu_percent).max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use sysinfo::Pid;
+
+    fn listed(pid: u32, latest_cpu_usage: f32) -> ProcessInfo {
+        ProcessInfo::new(format!("p{}", pid), Pid::from_u32(pid), 0.0, 0, String::new())
+            .with_latest_cpu_usage(latest_cpu_usage)
+    }
+
+    fn stats(cpu_percent: f32) -> SystemStats {
+        SystemStats { cpu_percent, ..SystemStats::default() }
+    }
+
+    #[test]
+    fn test_listed_share_relative_to_whole_machine() {
+        let summary = SystemSummary::new(stats(50.0), 300, &[listed(1, 150.0), listed(2, 50.0)], 8.0);
+
+        assert_eq!(summary.process_count, 300);
+        assert_eq!(summary.listed_count, 2);
+        assert_eq!(summary.listed_cpu_percent, 25.0);
+        assert_eq!(summary.other_cpu_percent(), 25.0);
+    }
+
+    #[test]
+    fn test_listed_share_capped_at_machine_usage() {
+        let summary = SystemSummary::new(stats(10.0), 5, &[listed(1, 100.0)], 4.0);
+
+        assert_eq!(summary.listed_cpu_percent, 10.0);
+        assert_eq!(summary.other_cpu_percent(), 0.0);
+    }
+
+    #[test]
+    fn test_exited_processes_use_no_cpu() {
+        let exited = listed(1, 200.0).with_exited_at(Some(Utc::now()));
+        let summary = SystemSummary::new(stats(30.0), 5, &[exited, listed(2, 40.0)], 2.0);
+
+        assert_eq!(summary.listed_count, 2);
+        assert_eq!(summary.listed_cpu_percent, 20.0);
+        assert_eq!(summary.other_cpu_percent(), 10.0);
+    }
+
+    #[test]
+    fn test_nothing_listed() {
+        let summary = SystemSummary::new(stats(30.0), 5, &[], 2.0);
+
+        assert!(summary.listed_cpu_percent.is_sign_positive());
+        assert_eq!(summary.other_cpu_percent(), 30.0);
+    }
+}
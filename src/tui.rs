@@ -0,0 +1,1613 @@
+use crate::cpu_calculator::{AveragingMode, CpuScale};
+use crate::display::{
+    format_details, format_header_settings, format_memory, format_sparkline, sparkline_width, EXTRA_INFO_WIDTH,
+    MEMORY_WIDTH,
+};
+use crate::filter::Pattern;
+use crate::grouping::ProcessGroup;
+use crate::process_details::ProcessDetails;
+use crate::process_source::{ProcessKey, ProcessSource};
+use crate::process_tree::{filter_forest, flatten_forest, sort_forest, TreeNode, TreeRow};
+use crate::sorting::{describe_sort, sort_processes, SortKey, SortOrder};
+use crate::{ProcessInfo, ProcessTracker};
+use chrono::{DateTime, Utc};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Clear, Paragraph, Row, Sparkline, Table, TableState, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::HashSet;
+use std::io;
+use std::time::Instant;
+
+/// Number of rows moved by PageUp / PageDown
+const PAGE_SIZE: usize = 10;
+
+/// Most users listed in the per-user panel
+const USER_PANEL_ROWS: usize = 5;
+
+/// Height of the CPU history chart in the detail pane, including its title
+const DETAIL_CHART_HEIGHT: u16 = 6;
+
+/// Width of the labels in the detail pane
+const DETAIL_LABEL_WIDTH: usize = 13;
+
+/// Marker in front of the selected row of the process table
+const HIGHLIGHT_SYMBOL: &str = "> ";
+
+/// Width taken by the left and right border of a panel
+const BORDER_WIDTH: usize = 2;
+
+/// Key bindings shown in the help overlay
+const HELP_LINES: &[(&str, &str)] = &[
+    ("q / Esc / Ctrl+C", "Quit"),
+    ("p / Space", "Pause or resume the display"),
+    ("↑ / k, ↓ / j", "Move the selection"),
+    ("PgUp / PgDn", "Move the selection by a page"),
+    ("Home / End", "Jump to the first or last process"),
+    ("c / m / i / n", "Sort by CPU, memory, PID or name"),
+    ("t / x", "Sort by trend or peak CPU"),
+    ("r", "Reverse the sort order"),
+    ("/", "Filter by name, command line or details (Enter applies, empty clears)"),
+    ("v", "Switch between the flat list and the process tree"),
+    ("← / →", "Collapse or expand the selected process in the tree"),
+    ("Enter / d", "Show or hide the details of the selected process"),
+    ("u", "Show or hide the per-user totals"),
+    ("s", "Show or hide the CPU distribution columns (P50, P95, P99, Max, StdDev)"),
+    ("? / h", "Show or hide this help"),
+];
+
+/// How the view starts out; everything can be changed with hotkeys
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ViewOptions {
+    /// Start in the process tree view instead of the flat list
+    pub tree: bool,
+    /// Show the per-user totals panel
+    pub user_summary: bool,
+    /// Show the CPU distribution columns in the flat list
+    pub cpu_stats: bool,
+}
+
+/// Tracker settings shown in the TUI header
+#[derive(Debug, Clone, PartialEq)]
+pub struct TuiSettings {
+    pub retention_seconds: i64,
+    pub update_interval_secs: u64,
+    pub top_n: usize,
+    pub cpu_threshold: f32,
+    pub sort_key: SortKey,
+    pub sort_order: SortOrder,
+    /// What 100% means in the CPU percentages
+    pub cpu_scale: CpuScale,
+    /// Whether only focused processes are tracked (the CPU threshold does not apply)
+    pub focused: bool,
+    /// How the samples are averaged; the moving average keeps no samples for the history or statistics
+    pub averaging: AveragingMode,
+}
+
+impl TuiSettings {
+    pub fn from_tracker<S: ProcessSource>(tracker: &ProcessTracker<S>) -> Self {
+        Self {
+            retention_seconds: tracker.retention_seconds(),
+            update_interval_secs: tracker.update_interval().as_secs(),
+            top_n: tracker.top_n(),
+            cpu_threshold: tracker.cpu_threshold(),
+            sort_key: tracker.sort_key(),
+            sort_order: tracker.sort_order(),
+            cpu_scale: tracker.cpu_scale(),
+            focused: !tracker.focus().is_empty(),
+            averaging: tracker.averaging(),
+        }
+    }
+}
+
+/// State of the interactive full-screen view
+pub struct TuiApp {
+    settings: TuiSettings,
+    results: Vec<ProcessInfo>,
+    last_update: Option<DateTime<Utc>>,
+    table_state: TableState,
+    /// Process of the selected row, so the selection follows the process when the ranking changes
+    selected_key: Option<ProcessKey>,
+    paused: bool,
+    show_help: bool,
+    should_quit: bool,
+    /// Text being typed at the "/" filter prompt, if the prompt is open
+    filter_input: Option<String>,
+    /// Filter applied from the prompt
+    query: Option<Pattern>,
+    /// Error shown in the status bar (e.g. an invalid filter pattern)
+    message: Option<String>,
+    tree_view: bool,
+    tree: Vec<TreeNode>,
+    /// Visible rows of the tree, without the children of collapsed processes
+    tree_rows: Vec<TreeRow>,
+    collapsed: HashSet<ProcessKey>,
+    /// Totals per user over every tracked process
+    users: Vec<ProcessGroup>,
+    show_users: bool,
+    show_cpu_stats: bool,
+    show_details: bool,
+    /// Details of the selected process, looked up by the caller while the detail pane is open
+    details: Option<ProcessDetails>,
+}
+
+impl TuiApp {
+    pub fn new(settings: TuiSettings) -> Self {
+        Self {
+            settings,
+            results: Vec::new(),
+            last_update: None,
+            table_state: TableState::default(),
+            selected_key: None,
+            paused: false,
+            show_help: false,
+            should_quit: false,
+            filter_input: None,
+            query: None,
+            message: None,
+            tree_view: false,
+            tree: Vec::new(),
+            tree_rows: Vec::new(),
+            collapsed: HashSet::new(),
+            users: Vec::new(),
+            show_users: false,
+            show_cpu_stats: false,
+            show_details: false,
+            details: None,
+        }
+    }
+
+    /// Start in the given view
+    pub fn with_options(mut self, options: &ViewOptions) -> Self {
+        self.tree_view = options.tree;
+        self.show_users = options.user_summary;
+        self.show_cpu_stats = options.cpu_stats && self.keeps_samples();
+        self
+    }
+
+    /// Whether the ranking carries the samples of the window (the moving average only keeps its state)
+    fn keeps_samples(&self) -> bool {
+        self.settings.averaging != AveragingMode::Ewma
+    }
+
+    fn toggle_cpu_stats(&mut self) {
+        if self.keeps_samples() {
+            self.show_cpu_stats = !self.show_cpu_stats;
+        } else {
+            self.message = Some("CPU statistics need the samples of the window, which --averaging ewma does not keep".to_string());
+        }
+    }
+
+    /// Replace the displayed ranking (ignored while paused)
+    pub fn set_results(&mut self, results: Vec<ProcessInfo>, last_update: Option<DateTime<Utc>>) {
+        if self.paused {
+            return;
+        }
+
+        self.results = results;
+        self.last_update = last_update;
+        if !self.tree_view {
+            self.reselect();
+        }
+    }
+
+    /// Replace the process tree (ignored while paused)
+    pub fn set_tree(&mut self, tree: Vec<TreeNode>) {
+        if self.paused {
+            return;
+        }
+
+        self.tree = tree;
+        self.tree_rows = flatten_forest(&self.tree, &self.collapsed);
+        if self.tree_view {
+            self.reselect();
+        }
+    }
+
+    /// Replace the per-user totals (ignored while paused)
+    pub fn set_users(&mut self, users: Vec<ProcessGroup>) {
+        if !self.paused {
+            self.users = users;
+        }
+    }
+
+    pub fn is_user_panel_visible(&self) -> bool {
+        self.show_users
+    }
+
+    pub fn is_cpu_stats_visible(&self) -> bool {
+        self.show_cpu_stats
+    }
+
+    pub fn is_detail_pane_visible(&self) -> bool {
+        self.show_details
+    }
+
+    /// Process whose details the detail pane needs, `None` while it is closed
+    pub fn detail_key(&self) -> Option<ProcessKey> {
+        if self.show_details {
+            self.selected_process().map(|info| info.key())
+        } else {
+            None
+        }
+    }
+
+    /// Replace the details of the selected process (while paused, only when the selection moved)
+    pub fn set_details(&mut self, details: Option<ProcessDetails>) {
+        let same_process = self.details.as_ref().map(|d| d.key) == details.as_ref().map(|d| d.key);
+        if !self.paused || !same_process {
+            self.details = details;
+        }
+    }
+
+    pub fn results(&self) -> &[ProcessInfo] {
+        &self.results
+    }
+
+    /// Visible rows of the process tree
+    pub fn tree_rows(&self) -> &[TreeRow] {
+        &self.tree_rows
+    }
+
+    pub fn is_tree_view(&self) -> bool {
+        self.tree_view
+    }
+
+    pub fn selected_process(&self) -> Option<&ProcessInfo> {
+        let index = self.table_state.selected()?;
+        if self.tree_view {
+            self.tree_rows.get(index).map(|row| &row.info)
+        } else {
+            self.results.get(index)
+        }
+    }
+
+    /// Number of rows in the current view
+    fn row_count(&self) -> usize {
+        if self.tree_view {
+            self.tree_rows.len()
+        } else {
+            self.results.len()
+        }
+    }
+
+    /// Index of the process's row in the current view
+    fn row_of(&self, key: ProcessKey) -> Option<usize> {
+        if self.tree_view {
+            self.tree_rows.iter().position(|row| row.info.key() == key)
+        } else {
+            self.results.iter().position(|info| info.key() == key)
+        }
+    }
+
+    /// Keep the selection on the same process if it is still listed, otherwise on the same row
+    fn reselect(&mut self) {
+        let index = self
+            .selected_key
+            .and_then(|key| self.row_of(key))
+            .or(self.table_state.selected());
+        self.select(index);
+    }
+
+    fn toggle_tree_view(&mut self) {
+        self.tree_view = !self.tree_view;
+        self.reselect();
+    }
+
+    /// Expand or collapse the children of the selected process in the tree
+    fn set_selected_expanded(&mut self, expanded: bool) {
+        let Some(row) = self.table_state.selected().and_then(|i| self.tree_rows.get(i)) else {
+            return;
+        };
+        if !self.tree_view || row.child_count == 0 {
+            return;
+        }
+
+        let key = row.info.key();
+        if expanded {
+            self.collapsed.remove(&key);
+        } else {
+            self.collapsed.insert(key);
+        }
+        self.tree_rows = flatten_forest(&self.tree, &self.collapsed);
+        self.reselect();
+    }
+
+    pub fn sort_key(&self) -> SortKey {
+        self.settings.sort_key
+    }
+
+    pub fn sort_order(&self) -> SortOrder {
+        self.settings.sort_order
+    }
+
+    /// Sort by the key; choosing the current key again reverses the order
+    fn set_sort_key(&mut self, key: SortKey) {
+        let order = if key == self.settings.sort_key {
+            self.settings.sort_order.reversed()
+        } else {
+            key.default_order()
+        };
+        self.apply_sort(key, order);
+    }
+
+    /// Re-sort the displayed rows right away; the tracker picks the sort up on the next tick
+    fn apply_sort(&mut self, key: SortKey, order: SortOrder) {
+        self.settings.sort_key = key;
+        self.settings.sort_order = order;
+        sort_processes(&mut self.results, key, order);
+        sort_forest(&mut self.tree, key, order);
+        self.tree_rows = flatten_forest(&self.tree, &self.collapsed);
+
+        let index = self.selected_key.and_then(|key| self.row_of(key));
+        if index.is_some() {
+            self.select(index);
+        }
+    }
+
+    /// Filter applied from the "/" prompt
+    pub fn query(&self) -> Option<&Pattern> {
+        self.query.as_ref()
+    }
+
+    pub fn is_editing_filter(&self) -> bool {
+        self.filter_input.is_some()
+    }
+
+    /// Apply the text typed at the filter prompt; an empty text clears the filter
+    fn submit_filter(&mut self, text: String) {
+        if text.is_empty() {
+            self.query = None;
+            return;
+        }
+
+        match Pattern::parse(&text) {
+            Ok(pattern) => {
+                // Hide non-matching rows right away; the tracker applies the filter from the next tick
+                let matches = |info: &ProcessInfo| pattern.matches_any(&[&info.name, &info.command_line, &info.extra_info]);
+                self.results.retain(|info| matches(info));
+                self.tree = filter_forest(std::mem::take(&mut self.tree), matches);
+                sort_forest(&mut self.tree, self.settings.sort_key, self.settings.sort_order);
+                self.tree_rows = flatten_forest(&self.tree, &self.collapsed);
+                let index = self.table_state.selected();
+                self.select(index);
+                self.query = Some(pattern);
+            }
+            Err(e) => self.message = Some(e),
+        }
+    }
+
+    fn handle_filter_key(&mut self, key: KeyEvent) {
+        let Some(input) = self.filter_input.as_mut() else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Enter => {
+                let text = self.filter_input.take().unwrap_or_default();
+                self.submit_filter(text);
+            }
+            KeyCode::Esc => self.filter_input = None,
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Char(c) => input.push(c),
+            _ => {}
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn is_help_visible(&self) -> bool {
+        self.show_help
+    }
+
+    pub fn should_quit(&self) -> bool {
+        self.should_quit
+    }
+
+    /// Select the row at the index, clamped to the list (or nothing if the list is empty)
+    fn select(&mut self, index: Option<usize>) {
+        let count = self.row_count();
+        let index = if count == 0 { None } else { index.map(|i| i.min(count - 1)) };
+        self.table_state.select(index);
+        self.selected_key = self.selected_process().map(|info| info.key());
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let index = match self.table_state.selected() {
+            Some(current) => current.saturating_add_signed(delta),
+            None => 0,
+        };
+        self.select(Some(index));
+    }
+
+    /// Apply a key press to the view state
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.should_quit = true;
+            return;
+        }
+
+        self.message = None;
+        if self.filter_input.is_some() {
+            self.handle_filter_key(key);
+            return;
+        }
+
+        match key.code {
+            KeyCode::Char('q') => self.should_quit = true,
+            KeyCode::Esc if self.show_help => self.show_help = false,
+            KeyCode::Esc if self.show_details => self.show_details = false,
+            KeyCode::Esc => self.should_quit = true,
+            KeyCode::Char('p') | KeyCode::Char(' ') => self.paused = !self.paused,
+            KeyCode::Char('?') | KeyCode::Char('h') => self.show_help = !self.show_help,
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::PageUp => self.move_selection(-(PAGE_SIZE as isize)),
+            KeyCode::PageDown => self.move_selection(PAGE_SIZE as isize),
+            KeyCode::Home => self.select(Some(0)),
+            KeyCode::End => self.select(Some(usize::MAX)),
+            KeyCode::Char('c') => self.set_sort_key(SortKey::Cpu),
+            KeyCode::Char('m') => self.set_sort_key(SortKey::Memory),
+            KeyCode::Char('i') => self.set_sort_key(SortKey::Pid),
+            KeyCode::Char('n') => self.set_sort_key(SortKey::Name),
+            KeyCode::Char('t') => self.set_sort_key(SortKey::Trend),
+            KeyCode::Char('x') => self.set_sort_key(SortKey::PeakCpu),
+            KeyCode::Char('r') => self.apply_sort(self.settings.sort_key, self.settings.sort_order.reversed()),
+            KeyCode::Char('v') => self.toggle_tree_view(),
+            KeyCode::Char('u') => self.show_users = !self.show_users,
+            KeyCode::Char('s') => self.toggle_cpu_stats(),
+            KeyCode::Enter | KeyCode::Char('d') => self.show_details = !self.show_details,
+            KeyCode::Left | KeyCode::Char('-') => self.set_selected_expanded(false),
+            KeyCode::Right | KeyCode::Char('+') => self.set_selected_expanded(true),
+            KeyCode::Char('/') => {
+                let current = self.query.as_ref().map(|p| p.as_str().to_string()).unwrap_or_default();
+                self.filter_input = Some(current);
+            }
+            _ => {}
+        }
+    }
+
+    /// Draw the header, process table, status bar and (optionally) the help overlay
+    pub fn render(&mut self, frame: &mut Frame) {
+        // Borders and column headings take three lines
+        let users_height = if self.show_users {
+            self.users.len().clamp(1, USER_PANEL_ROWS) as u16 + 3
+        } else {
+            0
+        };
+        let details = self.show_details.then(|| self.selected_process()).flatten().map(|info| {
+            let lines = detail_lines(info, self.details.as_ref().filter(|d| d.key == info.key()));
+            (info.clone(), lines)
+        });
+        // Text (long command lines wrap), chart and borders
+        let text_width = frame.area().width.saturating_sub(2).max(1) as usize;
+        let details_height = details.as_ref().map_or(0, |(_, lines)| {
+            let text_height: usize = lines.iter().map(|line| line.width().max(1).div_ceil(text_width)).sum();
+            text_height as u16 + DETAIL_CHART_HEIGHT + 2
+        });
+        let [header_area, table_area, details_area, users_area, status_area] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Min(3),
+            Constraint::Length(details_height),
+            Constraint::Length(users_height),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        self.render_header(frame, header_area);
+        self.render_table(frame, table_area);
+        if let Some((info, lines)) = details {
+            render_details(frame, details_area, &info, lines);
+        }
+        if self.show_users {
+            self.render_users(frame, users_area);
+        }
+        self.render_status_bar(frame, status_area);
+
+        if self.show_help {
+            render_help(frame);
+        }
+    }
+
+    fn render_header(&self, frame: &mut Frame, area: Rect) {
+        let timestamp = self
+            .last_update
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| "collecting samples...".to_string());
+        let threshold = if self.settings.focused {
+            "Focus mode".to_string()
+        } else {
+            format!("Threshold {:.1}%", self.settings.cpu_threshold)
+        };
+        let lines = vec![
+            Line::styled(
+                "=== Process Shepherd - CPU Usage Tracker ===",
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Line::raw(format!(
+                "{} | {} | Timestamp: {}",
+                format_header_settings(
+                    self.settings.retention_seconds,
+                    self.settings.update_interval_secs,
+                    self.settings.top_n,
+                    self.settings.cpu_scale
+                ),
+                threshold,
+                timestamp
+            )),
+        ];
+        frame.render_widget(Paragraph::new(lines), area);
+    }
+
+    fn render_table(&mut self, frame: &mut Frame, area: Rect) {
+        let arrow = self.settings.sort_order.indicator();
+        let label = |text: &str, key: SortKey| {
+            if self.settings.sort_key == key {
+                format!("{} {}", text, arrow)
+            } else {
+                text.to_string()
+            }
+        };
+
+        let (header, rows, widths) = if self.tree_view {
+            let header = Row::new([
+                label("Process Tree", SortKey::Name),
+                label("PID", SortKey::Pid),
+                self.settings.cpu_scale.column_label().to_string(),
+                label("Tree %", SortKey::Cpu),
+                "Memory".to_string(),
+                label("Tree Mem", SortKey::Memory),
+                "Details".to_string(),
+            ]);
+            let rows: Vec<Row> = self
+                .tree_rows
+                .iter()
+                .map(|row| {
+                    let cells = [
+                        row.display_name(),
+                        row.info.pid.as_u32().to_string(),
+                        format!("{:.2}", row.info.cpu_percent),
+                        format!("{:.2}", row.subtree_cpu_percent),
+                        format_memory(row.info.memory_bytes),
+                        format_memory(row.subtree_memory_bytes),
+                        format_details(&row.info),
+                    ];
+                    exited_style(Row::new(cells), &row.info)
+                })
+                .collect();
+            let widths = vec![
+                Constraint::Length(36),
+                Constraint::Length(8),
+                Constraint::Length(8),
+                Constraint::Length(9),
+                Constraint::Length(10),
+                Constraint::Length(11),
+                Constraint::Min(10),
+            ];
+            (header, rows, widths)
+        } else {
+            let mut header = vec![
+                "#".to_string(),
+                label("Process Name", SortKey::Name),
+                label("PID", SortKey::Pid),
+                label(self.settings.cpu_scale.column_label(), SortKey::Cpu),
+                String::new(),
+            ];
+            let mut widths = vec![
+                Constraint::Length(3),
+                Constraint::Length(27),
+                Constraint::Length(8),
+                Constraint::Length(8),
+                Constraint::Length(1),
+            ];
+            if self.show_cpu_stats {
+                header.extend(["P50", "P95", "P99"].map(String::from));
+                // The maximum is the peak CPU sort key
+                header.extend([label("Max", SortKey::PeakCpu), "StdDev".to_string()]);
+                widths.extend([Constraint::Length(7); 5]);
+            }
+            // CPU time accounting reports the CPU-seconds consumed in the window
+            let show_cpu_seconds = self.results.iter().any(|info| info.cpu_seconds.is_some());
+            if show_cpu_seconds {
+                header.push("CPU s".to_string());
+                widths.push(Constraint::Length(9));
+            }
+            let show_children_cpu_seconds = self.results.iter().any(|info| info.children_cpu_seconds.is_some());
+            if show_children_cpu_seconds {
+                header.push("Child s".to_string());
+                widths.push(Constraint::Length(9));
+            }
+            // The sample history takes the room left next to Memory and the usual width of Details
+            let fixed_width: usize = widths
+                .iter()
+                .map(|width| match width {
+                    Constraint::Length(length) => *length as usize,
+                    _ => 0,
+                })
+                .sum();
+            // History, Memory and Details each follow a one-space gap
+            let column_gaps = widths.len() + 2;
+            let table_width =
+                fixed_width + MEMORY_WIDTH + EXTRA_INFO_WIDTH + column_gaps + BORDER_WIDTH + HIGHLIGHT_SYMBOL.len();
+            let history_width = if self.keeps_samples() { sparkline_width(area.width as usize, table_width) } else { 0 };
+            if history_width > 0 {
+                header.push("History".to_string());
+                widths.push(Constraint::Length(history_width as u16));
+            }
+            header.extend([label("Memory", SortKey::Memory), "Details".to_string()]);
+            widths.extend([Constraint::Length(MEMORY_WIDTH as u16), Constraint::Min(10)]);
+
+            let rows: Vec<Row> = self
+                .results
+                .iter()
+                .enumerate()
+                .map(|(i, info)| {
+                    let trend = info.trend().map_or(" ", |trend| trend.indicator());
+                    let mut cells = vec![
+                        (i + 1).to_string(),
+                        info.name.clone(),
+                        info.pid.as_u32().to_string(),
+                        format!("{:.2}", info.cpu_percent),
+                        trend.to_string(),
+                    ];
+                    if self.show_cpu_stats {
+                        let stats = &info.cpu_stats;
+                        cells.extend(
+                            [stats.p50, stats.p95, stats.p99, stats.max, stats.stddev].map(|value| format!("{:.2}", value)),
+                        );
+                    }
+                    if show_cpu_seconds {
+                        cells.push(info.cpu_seconds.map_or("-".to_string(), |secs| format!("{:.2}", secs)));
+                    }
+                    if show_children_cpu_seconds {
+                        cells.push(info.children_cpu_seconds.map_or("-".to_string(), |secs| format!("{:.2}", secs)));
+                    }
+                    if history_width > 0 {
+                        cells.push(format_sparkline(&info.cpu_samples, history_width));
+                    }
+                    cells.extend([format_memory(info.memory_bytes), format_details(info)]);
+                    exited_style(Row::new(cells), info)
+                })
+                .collect();
+            (Row::new(header), rows, widths)
+        };
+
+        let title = if rows.is_empty() {
+            " No process data available yet. Collecting samples... ".to_string()
+        } else if self.tree_view {
+            format!(" Process tree ({} shown) ", rows.len())
+        } else {
+            format!(" Processes ({}) ", rows.len())
+        };
+
+        let table = Table::new(rows, widths)
+            .header(header.style(Style::default().add_modifier(Modifier::BOLD)))
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol(HIGHLIGHT_SYMBOL);
+
+        frame.render_stateful_widget(table, area, &mut self.table_state);
+    }
+
+    fn render_users(&self, frame: &mut Frame, area: Rect) {
+        let header = Row::new(["User", "Procs", self.settings.cpu_scale.column_label(), "Memory"])
+            .style(Style::default().add_modifier(Modifier::BOLD));
+        let rows: Vec<Row> = self
+            .users
+            .iter()
+            .take(USER_PANEL_ROWS)
+            .map(|user| {
+                Row::new([
+                    user.key.clone(),
+                    user.instance_count().to_string(),
+                    format!("{:.2}", user.cpu_percent),
+                    format_memory(user.memory_bytes),
+                ])
+            })
+            .collect();
+        let widths = [
+            Constraint::Min(16),
+            Constraint::Length(6),
+            Constraint::Length(8),
+            Constraint::Length(10),
+        ];
+        let title = format!(" Users ({}) ", self.users.len());
+
+        frame.render_widget(
+            Table::new(rows, widths)
+                .header(header)
+                .block(Block::default().borders(Borders::ALL).title(title)),
+            area,
+        );
+    }
+
+    fn render_status_bar(&self, frame: &mut Frame, area: Rect) {
+        if let Some(input) = &self.filter_input {
+            frame.render_widget(
+                Paragraph::new(format!("/{}█  (Enter apply, Esc cancel)", input))
+                    .style(Style::default().add_modifier(Modifier::REVERSED)),
+                area,
+            );
+            return;
+        }
+
+        if let Some(message) = &self.message {
+            frame.render_widget(
+                Paragraph::new(format!(" {}", message)).style(Style::default().add_modifier(Modifier::REVERSED)),
+                area,
+            );
+            return;
+        }
+
+        let filter = self
+            .query
+            .as_ref()
+            .map(|p| format!(" | Filter: {}", p))
+            .unwrap_or_default();
+        let state = match (self.paused, self.tree_view) {
+            (false, false) => "LIVE",
+            (true, false) => "PAUSED",
+            (false, true) => "LIVE | Tree",
+            (true, true) => "PAUSED | Tree",
+        };
+        let selection = self
+            .selected_process()
+            .map(|info| format!(" | Selected: {} ({})", info.name, info.pid.as_u32()))
+            .unwrap_or_default();
+        let text = format!(
+            " {} | Sort: {}{}{} | q quit  p pause  / filter  v tree  Enter details  ? help",
+            state,
+            describe_sort(self.settings.sort_key, self.settings.sort_order),
+            filter,
+            selection
+        );
+        frame.render_widget(
+            Paragraph::new(text).style(Style::default().add_modifier(Modifier::REVERSED)),
+            area,
+        );
+    }
+}
+
+/// Dim the row of an exited process kept in the ranking
+fn exited_style<'a>(row: Row<'a>, info: &ProcessInfo) -> Row<'a> {
+    if info.exited_at.is_some() {
+        row.style(Style::default().add_modifier(Modifier::DIM))
+    } else {
+        row
+    }
+}
+
+/// Label and value of one line of the detail pane
+fn detail_line(label: &str, value: impl Into<String>) -> Line<'static> {
+    Line::raw(format!("{:<DETAIL_LABEL_WIDTH$}{}", label, value.into()))
+}
+
+/// Text of the detail pane; `details` adds what is only looked up for the selected process
+fn detail_lines(info: &ProcessInfo, details: Option<&ProcessDetails>) -> Vec<Line<'static>> {
+    let command_line = if info.command_line.is_empty() { info.name.clone() } else { info.command_line.clone() };
+    let mut lines = vec![
+        detail_line("Command:", command_line),
+        detail_line("Executable:", if info.exe.is_empty() { "unknown" } else { &info.exe }),
+    ];
+    if let Some(details) = details {
+        lines.push(detail_line("Directory:", if details.cwd.is_empty() { "unknown" } else { &details.cwd }));
+    }
+    for (i, title) in info.window_title.split(" | ").filter(|t| !t.trim().is_empty()).enumerate() {
+        lines.push(detail_line(if i == 0 { "Windows:" } else { "" }, title));
+    }
+    if let Some(details) = details.filter(|d| !d.ancestors.is_empty()) {
+        let chain: Vec<String> = details
+            .ancestors
+            .iter()
+            .map(|(pid, name)| if name.is_empty() { pid.to_string() } else { format!("{} ({})", name, pid) })
+            .collect();
+        lines.push(detail_line("Parents:", chain.join(" ← ")));
+    }
+    let started = DateTime::from_timestamp(info.start_time as i64, 0)
+        .filter(|_| info.start_time > 0)
+        .map_or("unknown".to_string(), |t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string());
+    lines.push(detail_line("Started:", started));
+    lines.push(detail_line("User:", if info.user.is_empty() { "unknown" } else { &info.user }));
+
+    let mut memory = format!("{} resident", format_memory(info.memory_bytes));
+    if let Some(details) = details {
+        if let Some(breakdown) = details.memory {
+            memory.push_str(&format!(
+                " ({} anonymous, {} file, {} shared)",
+                format_memory(breakdown.anonymous_bytes),
+                format_memory(breakdown.file_bytes),
+                format_memory(breakdown.shared_bytes)
+            ));
+        }
+        memory.push_str(&format!(", {} virtual", format_memory(details.virtual_memory_bytes)));
+        if let Some(breakdown) = details.memory {
+            memory.push_str(&format!(", {} swapped", format_memory(breakdown.swap_bytes)));
+        }
+    }
+    lines.push(detail_line("Memory:", memory));
+    if let Some(thread_count) = details.and_then(|d| d.thread_count) {
+        lines.push(detail_line("Threads:", thread_count.to_string()));
+    }
+    lines
+}
+
+/// Draw the detail pane of the selected process: its details and a chart of its CPU history
+fn render_details(frame: &mut Frame, area: Rect, info: &ProcessInfo, lines: Vec<Line<'static>>) {
+    let exited = info
+        .exited_at
+        .map(|t| format!(" [exited at {}]", t.format("%H:%M:%S")))
+        .unwrap_or_default();
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(" {} ({}){} ", info.name, info.pid.as_u32(), exited));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let [text_area, chart_area] =
+        Layout::vertical([Constraint::Min(1), Constraint::Length(DETAIL_CHART_HEIGHT)]).areas(inner);
+    frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), text_area);
+
+    // The chart shows the most recent samples that fit, scaled to the highest of them
+    let samples = &info.cpu_samples[info.cpu_samples.len().saturating_sub(chart_area.width as usize)..];
+    let data: Vec<u64> = samples.iter().map(|sample| (sample * 100.0).round().max(0.0) as u64).collect();
+    let title = match info.cpu_samples.last() {
+        Some(latest) => format!(
+            "CPU history: {} samples | peak {:.2}% | latest {:.2}%",
+            info.cpu_samples.len(),
+            info.peak_cpu_percent,
+            latest
+        ),
+        // The moving average keeps only a running peak
+        None => format!("CPU history: not kept | peak {:.2}%", info.peak_cpu_percent),
+    };
+    frame.render_widget(
+        Sparkline::default()
+            .block(Block::default().title(title))
+            .data(&data)
+            .max(data.iter().copied().max().unwrap_or(0).max(1)),
+        chart_area,
+    );
+}
+
+/// Centered rectangle of the given size, clipped to the area
+fn centered_rect(area: Rect, width: u16, height: u16) -> Rect {
+    let [area] = Layout::horizontal([Constraint::Length(width)]).flex(Flex::Center).areas(area);
+    let [area] = Layout::vertical([Constraint::Length(height)]).flex(Flex::Center).areas(area);
+    area
+}
+
+fn render_help(frame: &mut Frame) {
+    let lines: Vec<Line> = HELP_LINES
+        .iter()
+        .map(|(keys, action)| Line::raw(format!(" {:<18} {}", keys, action)))
+        .collect();
+    let area = centered_rect(frame.area(), 56, lines.len() as u16 + 2);
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(" Help ")),
+        area,
+    );
+}
+
+/// Run the full-screen view until the user quits
+///
+/// The tracker is updated every update interval; `on_tick` is called after each
+/// ranking (also while the display is paused) so exports keep running.
+pub fn run<S, F>(tracker: &mut ProcessTracker<S>, options: &ViewOptions, on_tick: F) -> io::Result<()>
+where
+    S: ProcessSource,
+    F: FnMut(&ProcessTracker<S>, &[ProcessInfo]),
+{
+    let mut terminal = ratatui::try_init()?;
+    let result = run_loop(&mut terminal, tracker, options, on_tick);
+    ratatui::restore();
+    result
+}
+
+fn run_loop<S, F>(
+    terminal: &mut DefaultTerminal,
+    tracker: &mut ProcessTracker<S>,
+    options: &ViewOptions,
+    mut on_tick: F,
+) -> io::Result<()>
+where
+    S: ProcessSource,
+    F: FnMut(&ProcessTracker<S>, &[ProcessInfo]),
+{
+    let mut app = TuiApp::new(TuiSettings::from_tracker(tracker)).with_options(options);
+    let mut next_tick = Instant::now();
+
+    loop {
+        let now = Instant::now();
+        if now >= next_tick {
+            tracker.update();
+            let results = tracker.rank_processes();
+            on_tick(tracker, &results);
+            app.set_tree(tracker.process_tree());
+            app.set_users(tracker.user_summary());
+            app.set_results(results, tracker.last_update());
+            next_tick = now + tracker.update_interval();
+        }
+        app.set_details(app.detail_key().and_then(|key| tracker.process_details(key)));
+
+        terminal.draw(|frame| app.render(frame))?;
+
+        // Wait for input until the next sample is due; resizes just trigger a redraw
+        if event::poll(next_tick.saturating_duration_since(Instant::now()))? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    app.handle_key(key);
+                    tracker.set_sort(app.sort_key(), app.sort_order());
+                    tracker.set_query(app.query().cloned());
+                }
+            }
+        }
+
+        if app.should_quit() {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process_source::{ProcessSnapshot, ScriptedSource};
+    use chrono::TimeZone;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+    use sysinfo::Pid;
+
+    fn settings() -> TuiSettings {
+        TuiSettings {
+            retention_seconds: 60,
+            update_interval_secs: 2,
+            top_n: 20,
+            cpu_threshold: 1.0,
+            sort_key: SortKey::Cpu,
+            sort_order: SortOrder::Descending,
+            cpu_scale: CpuScale::Machine,
+            focused: false,
+            averaging: AveragingMode::Mean,
+        }
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn processes(count: u32) -> Vec<ProcessInfo> {
+        (1..=count)
+            .map(|pid| ProcessInfo::new(format!("proc{}", pid), Pid::from_u32(pid), 10.0, 1024, String::new()))
+            .collect()
+    }
+
+    fn render_to_string(app: &mut TuiApp, width: u16, height: u16) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| app.render(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer.content().iter().map(|cell| cell.symbol()).collect()
+    }
+
+    #[test]
+    fn test_quit_keys() {
+        for event in [
+            key(KeyCode::Char('q')),
+            key(KeyCode::Esc),
+            KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL),
+        ] {
+            let mut app = TuiApp::new(settings());
+            app.handle_key(event);
+            assert!(app.should_quit());
+        }
+    }
+
+    #[test]
+    fn test_escape_closes_help_before_quitting() {
+        let mut app = TuiApp::new(settings());
+        app.handle_key(key(KeyCode::Char('?')));
+        assert!(app.is_help_visible());
+
+        app.handle_key(key(KeyCode::Esc));
+        assert!(!app.is_help_visible());
+        assert!(!app.should_quit());
+    }
+
+    #[test]
+    fn test_pause_freezes_results() {
+        let mut app = TuiApp::new(settings());
+        app.set_results(processes(2), None);
+
+        app.handle_key(key(KeyCode::Char('p')));
+        assert!(app.is_paused());
+        app.set_results(processes(5), None);
+        assert_eq!(app.results().len(), 2);
+
+        app.handle_key(key(KeyCode::Char(' ')));
+        assert!(!app.is_paused());
+        app.set_results(processes(5), None);
+        assert_eq!(app.results().len(), 5);
+    }
+
+    #[test]
+    fn test_selection_moves_and_clamps() {
+        let mut app = TuiApp::new(settings());
+        app.set_results(processes(3), None);
+        assert!(app.selected_process().is_none());
+
+        app.handle_key(key(KeyCode::Down));
+        assert_eq!(app.selected_process().unwrap().pid.as_u32(), 1);
+        app.handle_key(key(KeyCode::Char('j')));
+        app.handle_key(key(KeyCode::Down));
+        app.handle_key(key(KeyCode::Down));
+        assert_eq!(app.selected_process().unwrap().pid.as_u32(), 3);
+
+        app.handle_key(key(KeyCode::Up));
+        assert_eq!(app.selected_process().unwrap().pid.as_u32(), 2);
+        app.handle_key(key(KeyCode::PageUp));
+        assert_eq!(app.selected_process().unwrap().pid.as_u32(), 1);
+        app.handle_key(key(KeyCode::End));
+        assert_eq!(app.selected_process().unwrap().pid.as_u32(), 3);
+        app.handle_key(key(KeyCode::Home));
+        assert_eq!(app.selected_process().unwrap().pid.as_u32(), 1);
+    }
+
+    #[test]
+    fn test_selection_follows_process_after_reranking() {
+        let mut app = TuiApp::new(settings());
+        app.set_results(processes(3), None);
+        app.handle_key(key(KeyCode::End));
+        assert_eq!(app.selected_process().unwrap().pid.as_u32(), 3);
+
+        let mut reranked = processes(3);
+        reranked.reverse();
+        app.set_results(reranked, None);
+        assert_eq!(app.selected_process().unwrap().pid.as_u32(), 3);
+        assert_eq!(app.table_state.selected(), Some(0));
+    }
+
+    #[test]
+    fn test_selection_cleared_when_list_empties() {
+        let mut app = TuiApp::new(settings());
+        app.set_results(processes(2), None);
+        app.handle_key(key(KeyCode::Down));
+
+        app.set_results(Vec::new(), None);
+        assert!(app.selected_process().is_none());
+    }
+
+    #[test]
+    fn test_render_shows_header_rows_and_status_bar() {
+        let mut app = TuiApp::new(settings());
+        app.set_results(processes(2), None);
+        app.handle_key(key(KeyCode::Down));
+
+        let screen = render_to_string(&mut app, 120, 12);
+        assert!(screen.contains("Process Shepherd"));
+        assert!(screen.contains("Tracking window: 60 seconds"));
+        assert!(screen.contains("proc1"));
+        assert!(screen.contains("proc2"));
+        assert!(screen.contains("LIVE | Sort: CPU % ▼ | Selected: proc1 (1)"));
+    }
+
+    #[test]
+    fn test_render_focus_mode_replaces_threshold() {
+        let mut app = TuiApp::new(TuiSettings {
+            focused: true,
+            ..settings()
+        });
+
+        let screen = render_to_string(&mut app, 140, 12);
+        assert!(screen.contains("Focus mode"));
+        assert!(!screen.contains("Threshold"));
+    }
+
+    #[test]
+    fn test_render_paused_and_help() {
+        let mut app = TuiApp::new(settings());
+        app.handle_key(key(KeyCode::Char('p')));
+        app.handle_key(key(KeyCode::Char('?')));
+
+        let screen = render_to_string(&mut app, 120, 20);
+        assert!(screen.contains("PAUSED"));
+        assert!(screen.contains("Help"));
+        assert!(screen.contains("Pause or resume the display"));
+    }
+
+    #[test]
+    fn test_render_small_terminal_does_not_panic() {
+        let mut app = TuiApp::new(settings());
+        app.set_results(processes(50), None);
+        app.handle_key(key(KeyCode::End));
+        app.handle_key(key(KeyCode::Char('?')));
+
+        render_to_string(&mut app, 20, 5);
+    }
+
+    #[test]
+    fn test_sort_hotkeys_resort_rows() {
+        let mut app = TuiApp::new(settings());
+        let results = vec![
+            ProcessInfo::new("small".to_string(), Pid::from_u32(1), 50.0, 10, String::new()),
+            ProcessInfo::new("large".to_string(), Pid::from_u32(2), 5.0, 9000, String::new()),
+        ];
+        app.set_results(results, None);
+
+        app.handle_key(key(KeyCode::Char('m')));
+        assert_eq!(app.sort_key(), SortKey::Memory);
+        assert_eq!(app.sort_order(), SortOrder::Descending);
+        assert_eq!(app.results()[0].name, "large");
+
+        // Same key again toggles the direction
+        app.handle_key(key(KeyCode::Char('m')));
+        assert_eq!(app.sort_order(), SortOrder::Ascending);
+        assert_eq!(app.results()[0].name, "small");
+
+        app.handle_key(key(KeyCode::Char('r')));
+        assert_eq!(app.sort_order(), SortOrder::Descending);
+
+        app.handle_key(key(KeyCode::Char('n')));
+        assert_eq!(app.sort_key(), SortKey::Name);
+        assert_eq!(app.sort_order(), SortOrder::Ascending);
+        assert_eq!(app.results()[0].name, "large");
+    }
+
+    #[test]
+    fn test_sort_keeps_selected_process() {
+        let mut app = TuiApp::new(settings());
+        app.set_results(processes(3), None);
+        app.handle_key(key(KeyCode::Down));
+        assert_eq!(app.selected_process().unwrap().pid.as_u32(), 1);
+
+        app.handle_key(key(KeyCode::Char('i')));
+        app.handle_key(key(KeyCode::Char('i')));
+        assert_eq!(app.results()[0].pid.as_u32(), 3);
+        assert_eq!(app.selected_process().unwrap().pid.as_u32(), 1);
+    }
+
+    #[test]
+    fn test_render_marks_sorted_column() {
+        let mut app = TuiApp::new(settings());
+        app.handle_key(key(KeyCode::Char('m')));
+
+        let screen = render_to_string(&mut app, 120, 10);
+        assert!(screen.contains("Memory ▼"));
+        assert!(screen.contains("Sort: Memory ▼"));
+    }
+
+    fn type_text(app: &mut TuiApp, text: &str) {
+        for c in text.chars() {
+            app.handle_key(key(KeyCode::Char(c)));
+        }
+    }
+
+    #[test]
+    fn test_filter_prompt_applies_pattern() {
+        let mut app = TuiApp::new(settings());
+        app.set_results(processes(12), None);
+
+        app.handle_key(key(KeyCode::Char('/')));
+        assert!(app.is_editing_filter());
+        // Hotkeys are typed into the prompt instead of being handled
+        type_text(&mut app, "proc1q");
+        app.handle_key(key(KeyCode::Backspace));
+        assert!(!app.should_quit());
+
+        app.handle_key(key(KeyCode::Enter));
+        assert!(!app.is_editing_filter());
+        assert_eq!(app.query().map(|p| p.as_str()), Some("proc1"));
+        let names: Vec<&str> = app.results().iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["proc1", "proc10", "proc11", "proc12"]);
+    }
+
+    #[test]
+    fn test_filter_prompt_applies_to_tree_right_away() {
+        let mut app = TuiApp::new(settings()).with_options(&ViewOptions { tree: true, ..ViewOptions::default() });
+        app.set_tree(tree());
+        assert_eq!(app.tree_rows().len(), 4);
+        app.handle_key(key(KeyCode::End));
+
+        app.handle_key(key(KeyCode::Char('/')));
+        type_text(&mut app, "tab");
+        app.handle_key(key(KeyCode::Enter));
+
+        let names: Vec<String> = app.tree_rows().iter().map(|row| row.display_name()).collect();
+        assert_eq!(names, vec!["tab"]);
+        assert_eq!(app.selected_process().map(|info| info.name.as_str()), Some("tab"));
+    }
+
+    #[test]
+    fn test_filter_prompt_escape_cancels() {
+        let mut app = TuiApp::new(settings());
+        app.handle_key(key(KeyCode::Char('/')));
+        type_text(&mut app, "abc");
+        app.handle_key(key(KeyCode::Esc));
+
+        assert!(!app.is_editing_filter());
+        assert!(app.query().is_none());
+        assert!(!app.should_quit());
+    }
+
+    #[test]
+    fn test_filter_prompt_empty_clears_filter() {
+        let mut app = TuiApp::new(settings());
+        app.handle_key(key(KeyCode::Char('/')));
+        type_text(&mut app, "cargo");
+        app.handle_key(key(KeyCode::Enter));
+        assert!(app.query().is_some());
+
+        // Reopening the prompt starts from the current filter
+        app.handle_key(key(KeyCode::Char('/')));
+        for _ in 0.."cargo".len() {
+            app.handle_key(key(KeyCode::Backspace));
+        }
+        app.handle_key(key(KeyCode::Enter));
+        assert!(app.query().is_none());
+    }
+
+    #[test]
+    fn test_filter_prompt_invalid_pattern_shows_message() {
+        let mut app = TuiApp::new(settings());
+        app.handle_key(key(KeyCode::Char('/')));
+        type_text(&mut app, "re:(");
+        app.handle_key(key(KeyCode::Enter));
+
+        assert!(app.query().is_none());
+        let screen = render_to_string(&mut app, 120, 10);
+        assert!(screen.contains("Invalid regex"));
+    }
+
+    #[test]
+    fn test_render_filter_prompt_and_active_filter() {
+        let mut app = TuiApp::new(settings());
+        app.handle_key(key(KeyCode::Char('/')));
+        type_text(&mut app, "node");
+
+        let screen = render_to_string(&mut app, 120, 10);
+        assert!(screen.contains("/node█"));
+
+        app.handle_key(key(KeyCode::Enter));
+        let screen = render_to_string(&mut app, 120, 10);
+        assert!(screen.contains("Filter: node"));
+    }
+
+    fn tree() -> Vec<TreeNode> {
+        use crate::process_tree::build_forest;
+
+        build_forest(vec![
+            ProcessInfo::new("browser".to_string(), Pid::from_u32(10), 1.0, 1024, String::new()),
+            ProcessInfo::new("tab".to_string(), Pid::from_u32(11), 20.0, 1024, String::new())
+                .with_parent(Some(Pid::from_u32(10))),
+            ProcessInfo::new("gpu".to_string(), Pid::from_u32(12), 5.0, 1024, String::new())
+                .with_parent(Some(Pid::from_u32(10))),
+            ProcessInfo::new("editor".to_string(), Pid::from_u32(20), 3.0, 1024, String::new()),
+        ])
+    }
+
+    #[test]
+    fn test_tree_view_toggle_keeps_selected_process() {
+        let mut app = TuiApp::new(settings());
+        app.set_tree(tree());
+        app.set_results(processes(20), None);
+        app.handle_key(key(KeyCode::Char('v')));
+        assert!(app.is_tree_view());
+        assert_eq!(app.tree_rows().len(), 4);
+
+        app.handle_key(key(KeyCode::End));
+        assert_eq!(app.selected_process().unwrap().name, "editor");
+
+        app.handle_key(key(KeyCode::Char('v')));
+        assert!(!app.is_tree_view());
+        assert_eq!(app.selected_process().unwrap().pid.as_u32(), 20);
+    }
+
+    #[test]
+    fn test_tree_collapse_and_expand_selected() {
+        let mut app = TuiApp::new(settings()).with_options(&ViewOptions { tree: true, ..ViewOptions::default() });
+        app.set_tree(tree());
+        app.handle_key(key(KeyCode::Home));
+
+        app.handle_key(key(KeyCode::Left));
+        assert_eq!(app.tree_rows().len(), 2);
+        assert!(!app.tree_rows()[0].expanded);
+
+        // Collapsing a leaf does nothing
+        app.handle_key(key(KeyCode::Down));
+        app.handle_key(key(KeyCode::Char('-')));
+        assert_eq!(app.tree_rows().len(), 2);
+
+        app.handle_key(key(KeyCode::Up));
+        app.handle_key(key(KeyCode::Right));
+        assert_eq!(app.tree_rows().len(), 4);
+
+        // The collapsed state survives the next tick
+        app.handle_key(key(KeyCode::Left));
+        app.set_tree(tree());
+        assert_eq!(app.tree_rows().len(), 2);
+    }
+
+    /// A tracker keeping an exited process next to the one that reused its PID, each with a child
+    fn reused_pid_tracker() -> ProcessTracker<ScriptedSource> {
+        let at = |seconds| Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap() + chrono::Duration::seconds(seconds);
+        let started = |pid, name, parent, cpu_usage, start_seconds: i64| {
+            ProcessSnapshot::new(Pid::from_u32(pid), name, cpu_usage)
+                .with_parent(Pid::from_u32(parent))
+                .with_start_time(at(start_seconds).timestamp() as u64)
+        };
+        let source = ScriptedSource::new(1.0)
+            .with_tick(
+                at(0),
+                vec![started(1, "init", 0, 0.0, -600), started(7, "old", 1, 60.0, -60), started(8, "job", 7, 2.0, -30)],
+            )
+            .with_tick(
+                at(2),
+                vec![started(1, "init", 0, 0.0, -600), started(7, "new", 1, 5.0, 1), started(9, "task", 7, 40.0, 1)],
+            )
+            .with_tick(
+                at(4),
+                vec![started(1, "init", 0, 0.0, -600), started(7, "new", 1, 90.0, 1), started(9, "task", 7, 90.0, 1)],
+            );
+        ProcessTracker::builder().keep_exited(true).cpu_threshold(0.0).build_with_source(source)
+    }
+
+    #[test]
+    fn test_tree_keeps_processes_sharing_a_pid_apart() {
+        let mut tracker = reused_pid_tracker();
+        tracker.update();
+        tracker.update();
+        let mut app = TuiApp::new(settings()).with_options(&ViewOptions { tree: true, ..ViewOptions::default() });
+        app.set_tree(tracker.process_tree());
+
+        let names: Vec<String> = app.tree_rows().iter().map(|row| row.display_name()).collect();
+        assert_eq!(names, vec!["init", "├─ old", "│  └─ job", "└─ new", "   └─ task"]);
+
+        // Collapsing the new process leaves the exited one with the same PID expanded
+        app.handle_key(key(KeyCode::End));
+        app.handle_key(key(KeyCode::Up));
+        app.handle_key(key(KeyCode::Left));
+        let names: Vec<String> = app.tree_rows().iter().map(|row| row.display_name()).collect();
+        assert_eq!(names, vec!["init", "├─ old", "│  └─ job", "└─ + new"]);
+    }
+
+    #[test]
+    fn test_selection_follows_process_not_its_reused_pid() {
+        let mut tracker = reused_pid_tracker();
+        tracker.update();
+        tracker.update();
+        let mut app = TuiApp::new(settings());
+        app.set_results(tracker.rank_processes(), tracker.last_update());
+
+        let row = app.results().iter().position(|info| info.name == "new").unwrap();
+        app.handle_key(key(KeyCode::Home));
+        for _ in 0..row {
+            app.handle_key(key(KeyCode::Down));
+        }
+        assert_eq!(app.selected_process().unwrap().name, "new");
+
+        // The ranking changes around it; the selection stays off the exited process with the same PID
+        tracker.update();
+        app.set_results(tracker.rank_processes(), tracker.last_update());
+        assert_eq!(app.selected_process().unwrap().name, "new");
+        assert!(app.selected_process().unwrap().exited_at.is_none());
+    }
+
+    #[test]
+    fn test_tree_sort_applies_to_every_level() {
+        let mut app = TuiApp::new(settings()).with_options(&ViewOptions { tree: true, ..ViewOptions::default() });
+        app.set_tree(tree());
+        app.handle_key(key(KeyCode::Char('i')));
+
+        let pids: Vec<u32> = app.tree_rows().iter().map(|row| row.info.pid.as_u32()).collect();
+        assert_eq!(pids, vec![10, 11, 12, 20]);
+
+        app.handle_key(key(KeyCode::Char('c')));
+        let pids: Vec<u32> = app.tree_rows().iter().map(|row| row.info.pid.as_u32()).collect();
+        assert_eq!(pids, vec![10, 11, 12, 20]);
+    }
+
+    #[test]
+    fn test_render_tree_view() {
+        let mut app = TuiApp::new(settings()).with_options(&ViewOptions { tree: true, ..ViewOptions::default() });
+        app.set_tree(tree());
+        app.handle_key(key(KeyCode::Home));
+
+        let screen = render_to_string(&mut app, 140, 12);
+        assert!(screen.contains("Process tree (4 shown)"));
+        assert!(screen.contains("Tree %"));
+        assert!(screen.contains("├─ tab"));
+        assert!(screen.contains("└─ gpu"));
+        assert!(screen.contains("26.00"));
+        assert!(screen.contains("LIVE | Tree | Sort"));
+    }
+
+    #[test]
+    fn test_user_panel_toggle_and_render() {
+        let users = vec![
+            ProcessGroup {
+                key: "ci".to_string(),
+                cpu_percent: 80.0,
+                memory_bytes: 1024 * 1024,
+                pids: vec![Pid::from_u32(1), Pid::from_u32(2)],
+            },
+            ProcessGroup {
+                key: "alice".to_string(),
+                cpu_percent: 0.5,
+                memory_bytes: 1024,
+                pids: vec![Pid::from_u32(3)],
+            },
+        ];
+        let mut app = TuiApp::new(settings());
+        app.set_users(users);
+        assert!(!app.is_user_panel_visible());
+        assert!(!render_to_string(&mut app, 120, 20).contains("Users (2)"));
+
+        app.handle_key(key(KeyCode::Char('u')));
+        assert!(app.is_user_panel_visible());
+
+        let screen = render_to_string(&mut app, 120, 20);
+        assert!(screen.contains("Users (2)"));
+        assert!(screen.contains("ci"));
+        assert!(screen.contains("80.00"));
+        assert!(screen.contains("alice"));
+    }
+
+    #[test]
+    fn test_cpu_column_labelled_with_scale() {
+        let results = vec![ProcessInfo::new("build".to_string(), Pid::from_u32(7), 350.0, 1024, String::new())];
+        let mut app = TuiApp::new(TuiSettings { cpu_scale: CpuScale::Core, ..settings() });
+        app.set_results(results, None);
+
+        let screen = render_to_string(&mut app, 140, 10);
+        assert!(screen.contains("Core % ▼"));
+        assert!(screen.contains("100% = one core"));
+        assert!(screen.contains("350.00"));
+    }
+
+    #[test]
+    fn test_detail_pane_toggle() {
+        let mut app = TuiApp::new(settings());
+        app.set_results(processes(3), None);
+        app.handle_key(key(KeyCode::Down));
+        app.handle_key(key(KeyCode::Down));
+        assert_eq!(app.detail_key(), None);
+
+        app.handle_key(key(KeyCode::Enter));
+        assert!(app.is_detail_pane_visible());
+        assert_eq!(app.detail_key(), Some(ProcessKey::new(Pid::from_u32(2), 0)));
+
+        // Esc closes the pane before quitting
+        app.handle_key(key(KeyCode::Esc));
+        assert!(!app.is_detail_pane_visible());
+        assert!(!app.should_quit());
+
+        app.handle_key(key(KeyCode::Char('d')));
+        assert!(app.is_detail_pane_visible());
+    }
+
+    #[test]
+    fn test_detail_pane_shows_untruncated_details() {
+        use crate::process_details::MemoryBreakdown;
+
+        let command_line = format!("/usr/bin/python3 -m pytest {}", "tests/test_integration.py ".repeat(4));
+        let info = ProcessInfo::new("python3".to_string(), Pid::from_u32(42), 25.0, 64 * 1024 * 1024, String::new())
+            .with_command_line(command_line.trim_end().to_string())
+            .with_exe("/usr/bin/python3".to_string())
+            .with_user("ci".to_string())
+            .with_start_time(1_704_110_400)
+            .with_cpu_samples(vec![5.0, 10.0, 40.0])
+            .with_peak_cpu_percent(40.0);
+        let mut app = TuiApp::new(settings());
+        app.set_results(vec![info], None);
+        app.handle_key(key(KeyCode::Down));
+        app.handle_key(key(KeyCode::Enter));
+        app.set_details(Some(ProcessDetails {
+            key: ProcessKey::new(Pid::from_u32(42), 1_704_110_400),
+            cwd: "/home/ci/project".to_string(),
+            virtual_memory_bytes: 2 * 1024 * 1024 * 1024,
+            thread_count: Some(9),
+            ancestors: vec![(Pid::from_u32(41), "bash".to_string()), (Pid::from_u32(1), "init".to_string())],
+            memory: Some(MemoryBreakdown { anonymous_bytes: 48 * 1024 * 1024, ..MemoryBreakdown::default() }),
+        }));
+
+        let screen = render_to_string(&mut app, 200, 40);
+        assert!(screen.contains(" python3 (42) "));
+        assert!(screen.contains("tests/test_integration.py tests/test_integration.py tests/test_integration.py tests/test_integration.py"));
+        assert!(screen.contains("Directory:   /home/ci/project"));
+        assert!(screen.contains("Parents:     bash (41) ← init (1)"));
+        assert!(screen.contains("Started:     2024-01-01 12:00:00 UTC"));
+        assert!(screen.contains("User:        ci"));
+        assert!(screen.contains("64 MB resident (48 MB anonymous, 0 B file, 0 B shared), 2.0 GB virtual, 0 B swapped"));
+        assert!(screen.contains("Threads:     9"));
+        assert!(screen.contains("CPU history: 3 samples | peak 40.00% | latest 40.00%"));
+    }
+
+    #[test]
+    fn test_detail_pane_ignores_details_of_other_process() {
+        let mut app = TuiApp::new(settings());
+        app.set_results(processes(2), None);
+        app.handle_key(key(KeyCode::Down));
+        app.handle_key(key(KeyCode::Enter));
+        app.set_details(Some(ProcessDetails {
+            key: ProcessKey::new(Pid::from_u32(2), 0),
+            cwd: "/elsewhere".to_string(),
+            virtual_memory_bytes: 0,
+            thread_count: None,
+            ancestors: Vec::new(),
+            memory: None,
+        }));
+
+        let screen = render_to_string(&mut app, 160, 30);
+        assert!(screen.contains(" proc1 (1) "));
+        assert!(!screen.contains("/elsewhere"));
+    }
+
+    #[test]
+    fn test_sparkline_column_fits_terminal_width() {
+        let results = vec![ProcessInfo::new("ramp".to_string(), Pid::from_u32(7), 10.0, 1024, String::new())
+            .with_cpu_samples(vec![0.0, 10.0, 20.0])];
+        let mut app = TuiApp::new(settings());
+        app.set_results(results, None);
+
+        let screen = render_to_string(&mut app, 160, 10);
+        assert!(screen.contains("History"));
+        assert!(screen.contains("▁▅█"));
+
+        let screen = render_to_string(&mut app, 100, 10);
+        assert!(!screen.contains("History"));
+        assert!(!screen.contains("▁▅█"));
+    }
+
+    #[test]
+    fn test_cpu_stats_columns_toggle() {
+        use crate::cpu_calculator::CpuStats;
+
+        let results = vec![ProcessInfo::new("spiky".to_string(), Pid::from_u32(7), 10.0, 1024, String::new())
+            .with_cpu_stats(CpuStats { p50: 1.5, p95: 87.25, p99: 99.0, max: 100.0, stddev: 30.0, ..CpuStats::default() })];
+        let mut app = TuiApp::new(settings()).with_options(&ViewOptions { cpu_stats: true, ..ViewOptions::default() });
+        app.set_results(results, None);
+        assert!(app.is_cpu_stats_visible());
+
+        let screen = render_to_string(&mut app, 140, 10);
+        assert!(screen.contains("P95"));
+        assert!(screen.contains("StdDev"));
+        assert!(screen.contains("87.25"));
+        assert!(screen.contains("30.00"));
+
+        app.handle_key(key(KeyCode::Char('s')));
+        assert!(!app.is_cpu_stats_visible());
+        let screen = render_to_string(&mut app, 140, 10);
+        assert!(!screen.contains("P95"));
+        assert!(!screen.contains("87.25"));
+    }
+
+    #[test]
+    fn test_moving_average_hides_history_and_stats() {
+        let results = vec![ProcessInfo::new("smoothed".to_string(), Pid::from_u32(7), 10.0, 1024, String::new())
+            .with_peak_cpu_percent(90.0)];
+        let settings = TuiSettings { averaging: AveragingMode::Ewma, ..settings() };
+        let mut app = TuiApp::new(settings).with_options(&ViewOptions { cpu_stats: true, ..ViewOptions::default() });
+        app.set_results(results, None);
+        assert!(!app.is_cpu_stats_visible());
+
+        app.handle_key(key(KeyCode::Char('s')));
+        assert!(!app.is_cpu_stats_visible());
+        let screen = render_to_string(&mut app, 200, 20);
+        assert!(screen.contains("--averaging ewma"));
+        assert!(!screen.contains("History"));
+        assert!(!screen.contains("P95"));
+
+        // The running peak is still shown and sorted by
+        app.handle_key(key(KeyCode::Char('x')));
+        app.handle_key(key(KeyCode::Down));
+        app.handle_key(key(KeyCode::Enter));
+        let screen = render_to_string(&mut app, 200, 20);
+        assert!(screen.contains("CPU history: not kept | peak 90.00%"));
+    }
+}
@@ -144,7 +144,11 @@ mod tests {
     fn test_get_all_window_titles_returns_map() {
         // Should not panic
         let all_titles = get_all_window_titles();
-        // Result can be empty or non-empty depending on platform and running processes
-        assert!(all_titles.len() >= 0);
+        // Result can be empty or non-empty depending on platform and running processes,
+        // but window titles are only collected on Windows
+        #[cfg(not(windows))]
+        assert!(all_titles.is_empty());
+        #[cfg(windows)]
+        let _ = all_titles;
     }
 }